@@ -0,0 +1,933 @@
+//! Ikki CLI - headless access to an Ikki wallet
+//!
+//! Uses the same data directory and `wallet_config.json` as the desktop app,
+//! and prints JSON to stdout so it can be scripted.
+
+use std::io::{BufRead, Read};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Duration;
+
+use bip0039::{Count, English, Mnemonic};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+
+use ikki_lib::rpc::{self, RpcEndpoint, SharedWallet};
+use ikki_lib::wallet::{
+    default_data_dir, encrypt_plaintext_dbs, frost_aggregate, frost_commit, frost_dealer_keygen,
+    frost_dkg_part1, frost_dkg_part2, frost_dkg_part3, frost_sign, frost_signing_package,
+    inspect_pczt, is_plaintext_db, seed_from_phrase, sign_pczt, watch_only_key, ActivationHeights,
+    AddressType, DkgRound2Package, DkgSecret, ExportFormat, ExportRange, FrostGroup, FrostKeyShare,
+    IkkiWallet, Network, PcztSummary, PoolBalance, StoredWalletConfig, TransactionQuery, UrPayload,
+    UrScanner, ValuePool, WatchOnlyKey, ZcashConfig, DEFAULT_AUTO_LOCK_MINUTES,
+    DEFAULT_FRAGMENT_LEN, WALLET_CONFIG_FILE,
+};
+
+const USAGE: &str = "\
+Usage: ikki-cli [--data-dir DIR] [--fixtures FILE] <command> [options]
+
+  --fixtures FILE  Use an in-process lightwalletd serving recorded fixtures instead of
+                   the network; broadcast transactions are captured, not relayed
+                   (builds with the test-fixtures feature only)
+
+Commands:
+  init [WALLET OPTIONS]                  Create a new wallet and print its seed phrase
+  import [WALLET OPTIONS]                Import a wallet, reading the seed phrase from stdin
+  import-viewing-key [WALLET OPTIONS]    Create a watch-only wallet, reading the JSON output
+                                         of export-viewing-key from stdin
+  export-viewing-key                     Print the viewing key for a watch-only copy
+  set-password                           Encrypt the wallet database with a password read
+                                         from stdin, or change the existing one
+  sync                                   Sync the wallet with the chain
+  balance                                Show the balance per pool
+  addresses                              List the wallet's addresses
+  new-address [--type TYPE] [--label L]  Generate a new address (shielded, full, orchard,
+                                         sapling, transparent)
+  send <ADDRESS> <ZATOSHIS|max> [--memo TEXT]
+                                         Send funds
+  pczt-create <PATH> <ADDRESS> <ZATOSHIS|max> [--memo TEXT]
+                                         Write an unsigned PCZT for an offline signer
+  pczt-sign <IN> <OUT>                   Show the outputs and fee of a PCZT, then prove and
+                                         sign it with the seed once 'y' is read from stdin;
+                                         needs no network access
+  pczt-send <PATH>                       Broadcast a signed PCZT
+  ur-encode <pczt|transaction> <PATH> [--fragment-len N]
+  ur-encode viewing-key [--fragment-len N]
+                                         Print animated QR (UR) frames for a file or the
+                                         viewing key
+  ur-decode [OUT]                        Read scanned UR frames from stdin, one per line;
+                                         writes PCZTs and transactions to OUT and prints
+                                         viewing keys
+  history [--limit N]                    List recent transactions
+  export <PATH> [--format csv|json]      Export transaction history
+  serve-rpc [--port N | --socket PATH] [--sync-interval SECS]
+                                         Serve the zcashd-compatible JSON-RPC API until
+                                         interrupted, authenticating with <data-dir>/.cookie
+  record-fixtures <PATH> --from HEIGHT --to HEIGHT [--server URL]
+                                         Record blocks, tree states and transactions from
+                                         lightwalletd into a fixtures file (test-fixtures
+                                         builds only)
+
+FROST multisig (t-of-n Orchard accounts; files are exchanged between participants):
+  frost-dealer --threshold T --participants N --out DIR
+                                         Create group.json and share-<ID>.json for each
+                                         participant with a trusted dealer
+  frost-dkg-part1 --id ID --threshold T --participants N --out DIR
+                                         Start a distributed key generation; broadcast
+                                         dkg-round1-<ID>.json, keep dkg-secret-<ID>.json
+  frost-dkg-part2 <SECRET> <ROUND1...> --out DIR
+                                         Process every participant's round 1 file; send
+                                         each dkg-round2-<ID>-to-<TO>.json to its recipient
+  frost-dkg-part3 <SECRET> <ROUND2...> --out DIR
+                                         Finish the DKG with the round 2 files sent to us,
+                                         writing share-<ID>.json and group.json
+  frost-commit <SHARE> <PCZT> --out DIR  Commit to nonces for a PCZT; send
+                                         commitments-<ID>.json, keep nonces-<ID>.json
+  frost-signing-package <GROUP> <PCZT> <COMMITMENTS...> --out FILE
+                                         Coordinator: build the signing package
+  frost-sign <SHARE> <NONCES> <SIGNING-PACKAGE> --out FILE
+                                         Sign the package; deletes the used nonces
+  frost-aggregate <GROUP> <SIGNING-PACKAGE> <SHARES...> --out FILE
+                                         Coordinator: write the signed PCZT for pczt-send
+
+  The coordinator's watch-only wallet is created with `import-viewing-key < group.json`.
+
+Wallet options (stored in the wallet config):
+  --birthday HEIGHT                      Height to scan from
+  --network mainnet|testnet|regtest      Network, testnet by default
+  --activation-heights SPEC              Regtest upgrade heights, e.g. canopy=1,nu5=1,nu6=none;
+                                         unlisted upgrades activate at height 1
+  --server URL                           lightwalletd server
+
+Environment:
+  IKKI_WALLET_PASSWORD                   Password of an encrypted wallet; init and import
+                                         encrypt the new wallet's database with it";
+
+/// Environment variable holding the wallet password
+const PASSWORD_VAR: &str = "IKKI_WALLET_PASSWORD";
+
+/// Parsed command-line arguments: positionals plus `--flag value` options
+struct Args {
+    positional: Vec<String>,
+    options: Vec<(String, String)>,
+}
+
+impl Args {
+    fn parse(args: impl Iterator<Item = String>) -> anyhow::Result<Self> {
+        let mut positional = Vec::new();
+        let mut options = Vec::new();
+        let mut args = args.peekable();
+        while let Some(arg) = args.next() {
+            if arg == "-h" || arg == "--help" {
+                positional.insert(0, "help".to_string());
+            } else if let Some(name) = arg.strip_prefix("--") {
+                let value = args
+                    .next()
+                    .ok_or_else(|| anyhow::anyhow!("Missing value for --{name}"))?;
+                options.push((name.to_string(), value));
+            } else {
+                positional.push(arg);
+            }
+        }
+        Ok(Self {
+            positional,
+            options,
+        })
+    }
+
+    fn option(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn birthday(&self) -> anyhow::Result<Option<u64>> {
+        self.height("birthday")
+    }
+
+    fn network(&self) -> anyhow::Result<Option<Network>> {
+        let heights = self.option("activation-heights");
+        let network = match self.option("network") {
+            None if heights.is_none() => return Ok(None),
+            None | Some("regtest") => {
+                Network::Regtest(ActivationHeights::parse(heights.unwrap_or_default())?)
+            }
+            Some(_) if heights.is_some() => {
+                anyhow::bail!("--activation-heights only applies to regtest")
+            }
+            Some("mainnet") => Network::Mainnet,
+            Some("testnet") => Network::Testnet,
+            Some(other) => anyhow::bail!("Unknown network: {other}"),
+        };
+        Ok(Some(network))
+    }
+
+    fn height(&self, name: &str) -> anyhow::Result<Option<u64>> {
+        self.option(name)
+            .map(|h| {
+                h.parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid {name} height: {h}"))
+            })
+            .transpose()
+    }
+
+    fn count(&self, name: &str) -> anyhow::Result<u16> {
+        let value = self
+            .option(name)
+            .ok_or_else(|| anyhow::anyhow!("Missing --{name} N"))?;
+        value
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid {name}: {value}"))
+    }
+
+    fn out(&self) -> anyhow::Result<PathBuf> {
+        self.option("out")
+            .map(PathBuf::from)
+            .ok_or_else(|| anyhow::anyhow!("Missing --out PATH"))
+    }
+
+    /// Positional arguments from `index` on
+    fn positionals_from(&self, index: usize) -> &[String] {
+        self.positional.get(index..).unwrap_or_default()
+    }
+
+    fn positional(&self, index: usize, name: &str) -> anyhow::Result<&str> {
+        self.positional
+            .get(index)
+            .map(String::as_str)
+            .ok_or_else(|| anyhow::anyhow!("Missing argument <{name}>"))
+    }
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    // Logs go to stderr so stdout stays machine-readable
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::from_default_env()
+                .add_directive(tracing::Level::WARN.into()),
+        )
+        .init();
+
+    match run().await {
+        Ok(output) => {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&output).unwrap_or_default()
+            );
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", json!({ "error": e.to_string() }));
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run() -> anyhow::Result<Value> {
+    let args = Args::parse(std::env::args().skip(1))?;
+    let data_dir = args
+        .option("data-dir")
+        .map(PathBuf::from)
+        .unwrap_or_else(default_data_dir);
+    let config_path = data_dir.join(WALLET_CONFIG_FILE);
+    let mock = start_mock(&args)?;
+
+    let command = args
+        .positional
+        .first()
+        .map(String::as_str)
+        .unwrap_or("help");
+    match command {
+        "help" => {
+            println!("{USAGE}");
+            std::process::exit(0);
+        }
+        "init" => {
+            let seed = Mnemonic::<English>::generate(Count::Words24)
+                .phrase()
+                .to_string();
+            let wallet = create_wallet(&data_dir, seed.clone(), None, &args, mock.as_ref()).await?;
+            Ok(json!({
+                "address": wallet.get_address()?,
+                "seed_phrase": seed,
+            }))
+        }
+        "import" => {
+            let mut seed = String::new();
+            std::io::stdin().lock().read_line(&mut seed)?;
+            let seed = seed.trim().to_string();
+            let wallet = create_wallet(&data_dir, seed, None, &args, mock.as_ref()).await?;
+            Ok(json!({ "address": wallet.get_address()? }))
+        }
+        "import-viewing-key" => {
+            let mut input = String::new();
+            std::io::stdin().lock().read_to_string(&mut input)?;
+            let viewing_key: WatchOnlyKey = serde_json::from_str(&input)
+                .map_err(|e| anyhow::anyhow!("Invalid viewing key JSON: {e}"))?;
+            let wallet = create_wallet(
+                &data_dir,
+                String::new(),
+                Some(viewing_key),
+                &args,
+                mock.as_ref(),
+            )
+            .await?;
+            Ok(json!({ "address": wallet.get_address()?, "watch_only": true }))
+        }
+        "set-password" => {
+            let mut stored = load_stored_config(&config_path, &data_dir)?;
+            let old_key = stored.db_key(wallet_password().as_deref())?;
+            let mut password = String::new();
+            std::io::stdin().lock().read_line(&mut password)?;
+            let changing = old_key.is_some();
+            let key = stored.set_password(password.trim_end_matches(['\r', '\n']), old_key)?;
+
+            // Save the sealed key first: plaintext databases left by an
+            // interrupted run are encrypted the next time the wallet opens
+            stored.save(&config_path)?;
+            if !changing {
+                let config = stored.to_config(data_dir.clone(), Some(&key))?;
+                let db_path = config.wallet_db_path();
+                let paths = [db_path.clone(), config.address_book_db_path()];
+                if let Err(e) = encrypt_plaintext_dbs(&paths, &key) {
+                    // Nothing was encrypted while wallet.db is still plaintext
+                    if is_plaintext_db(&db_path).unwrap_or(false) {
+                        stored.remove_password(&key)?;
+                        stored.save(&config_path)?;
+                    }
+                    return Err(e);
+                }
+            }
+            Ok(json!({ "encrypted": true }))
+        }
+        // Commands that only need the stored keys, so they work on an offline machine
+        "export-viewing-key" => {
+            let config = load_config(&config_path, &data_dir)?;
+            Ok(serde_json::to_value(config_viewing_key(&config)?)?)
+        }
+        "pczt-sign" => {
+            let config = load_config(&config_path, &data_dir)?;
+            let seed_phrase = config
+                .seed_phrase
+                .as_deref()
+                .ok_or_else(|| anyhow::anyhow!("A watch-only wallet cannot sign PCZTs"))?;
+            let input = PathBuf::from(args.positional(1, "IN")?);
+            let output = PathBuf::from(args.positional(2, "OUT")?);
+            let seed = seed_from_phrase(seed_phrase)?;
+            let pczt = std::fs::read(&input)?;
+
+            // Show what is being signed and wait for the user to approve it
+            let summary = pczt_summary_json(&inspect_pczt(&seed, config.network, &pczt)?);
+            eprintln!("{}", serde_json::to_string_pretty(&summary)?);
+            eprint!("Sign this transaction? [y/N] ");
+            let mut answer = String::new();
+            std::io::stdin().lock().read_line(&mut answer)?;
+            if !matches!(answer.trim(), "y" | "Y" | "yes") {
+                anyhow::bail!("Signing cancelled");
+            }
+
+            let signed = sign_pczt(&seed, config.network, &pczt)?;
+            std::fs::write(&output, signed)?;
+            Ok(json!({ "path": output, "pczt": summary }))
+        }
+        "ur-encode" => {
+            let fragment_len = match args.option("fragment-len") {
+                Some(len) => len
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid fragment length: {len}"))?,
+                None => DEFAULT_FRAGMENT_LEN,
+            };
+            let payload = match args.positional(1, "KIND")? {
+                "viewing-key" => {
+                    let config = load_config(&config_path, &data_dir)?;
+                    UrPayload::ViewingKey(config_viewing_key(&config)?)
+                }
+                "pczt" => UrPayload::Pczt(std::fs::read(args.positional(2, "PATH")?)?),
+                "transaction" => {
+                    UrPayload::Transaction(std::fs::read(args.positional(2, "PATH")?)?)
+                }
+                other => anyhow::bail!("Unknown UR kind: {other}"),
+            };
+            Ok(json!({
+                "kind": payload.kind(),
+                "frames": payload.encode_frames(fragment_len)?,
+            }))
+        }
+        "ur-decode" => {
+            let mut scanner = UrScanner::new();
+            for line in std::io::stdin().lock().lines() {
+                let line = line?;
+                if !line.trim().is_empty() && scanner.receive(&line)?.complete {
+                    break;
+                }
+            }
+            let payload = scanner
+                .payload()?
+                .ok_or_else(|| anyhow::anyhow!("Not enough frames to decode the UR"))?;
+            let kind = payload.kind();
+            match payload {
+                UrPayload::ViewingKey(key) => Ok(serde_json::to_value(key)?),
+                UrPayload::Pczt(data) | UrPayload::Transaction(data) => {
+                    let path = PathBuf::from(args.positional(1, "OUT")?);
+                    std::fs::write(&path, data)?;
+                    Ok(json!({ "kind": kind, "path": path }))
+                }
+            }
+        }
+        command if command.starts_with("frost-") => run_frost_command(command, &args),
+        #[cfg(feature = "test-fixtures")]
+        "record-fixtures" => {
+            use ikki_lib::wallet::{connect_lightwalletd, MockFixtures, DEFAULT_LIGHTWALLETD_URL};
+
+            let path = PathBuf::from(args.positional(1, "PATH")?);
+            let from = args
+                .height("from")?
+                .ok_or_else(|| anyhow::anyhow!("Missing --from HEIGHT"))?;
+            let to = args
+                .height("to")?
+                .ok_or_else(|| anyhow::anyhow!("Missing --to HEIGHT"))?;
+            let mut client =
+                connect_lightwalletd(args.option("server").unwrap_or(DEFAULT_LIGHTWALLETD_URL))
+                    .await?;
+            let fixtures = MockFixtures::record(&mut client, from, to).await?;
+            fixtures.save(&path)?;
+            Ok(json!({
+                "path": path,
+                "blocks": fixtures.blocks.len(),
+                "transactions": fixtures.transactions.len(),
+            }))
+        }
+        _ => {
+            let config = load_config(&config_path, &data_dir)?;
+            let mut wallet = open_wallet(config, mock.as_ref()).await?;
+            if command == "serve-rpc" {
+                return serve_rpc(wallet, &data_dir, &args).await;
+            }
+            run_wallet_command(&mut wallet, command, &args).await
+        }
+    }
+}
+
+/// Serve the JSON-RPC API, syncing in the background, until Ctrl-C
+async fn serve_rpc(
+    wallet: IkkiWallet,
+    data_dir: &std::path::Path,
+    args: &Args,
+) -> anyhow::Result<Value> {
+    let endpoint = match (args.option("socket"), args.option("port")) {
+        #[cfg(unix)]
+        (Some(path), _) => RpcEndpoint::Unix(PathBuf::from(path)),
+        #[cfg(not(unix))]
+        (Some(_), _) => anyhow::bail!("Unix sockets are not supported on this platform"),
+        (None, Some(port)) => RpcEndpoint::Tcp(
+            port.parse()
+                .map_err(|_| anyhow::anyhow!("Invalid port: {port}"))?,
+        ),
+        (None, None) => RpcEndpoint::Tcp(18232),
+    };
+    let sync_interval: u64 = match args.option("sync-interval") {
+        Some(secs) => secs
+            .parse()
+            .map_err(|_| anyhow::anyhow!("Invalid sync interval: {secs}"))?,
+        None => 60,
+    };
+
+    let wallet: SharedWallet = Arc::new(Mutex::new(Some(wallet)));
+    let handle = rpc::start(wallet.clone(), endpoint, data_dir).await?;
+    eprintln!(
+        "{}",
+        json!({
+            "endpoint": handle.endpoint().to_string(),
+            "cookie_path": handle.cookie_path(),
+        })
+    );
+
+    let sync_task = tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(sync_interval.max(1)));
+        loop {
+            interval.tick().await;
+            if let Some(wallet) = wallet.lock().await.as_mut() {
+                if let Err(e) = wallet.sync().await {
+                    tracing::warn!("Background sync failed: {}", e);
+                }
+            }
+        }
+    });
+
+    tokio::signal::ctrl_c().await?;
+    sync_task.abort();
+    handle.stop();
+    Ok(json!({ "stopped": true }))
+}
+
+/// Run a FROST multisig command; these only exchange files and work offline
+fn run_frost_command(command: &str, args: &Args) -> anyhow::Result<Value> {
+    let network = args.network()?.unwrap_or(Network::Testnet);
+    match command {
+        "frost-dealer" => {
+            let dir = args.out()?;
+            std::fs::create_dir_all(&dir)?;
+            let threshold = args.count("threshold")?;
+            let shares = frost_dealer_keygen(threshold, args.count("participants")?, network)?;
+            let group = &shares[0].group;
+            write_json(&dir.join("group.json"), group)?;
+            for share in &shares {
+                write_json(&dir.join(format!("share-{}.json", share.identifier)), share)?;
+            }
+            Ok(json!({
+                "group": dir.join("group.json"),
+                "ufvk": group.ufvk,
+                "shares": shares.len(),
+            }))
+        }
+        "frost-dkg-part1" => {
+            let dir = args.out()?;
+            std::fs::create_dir_all(&dir)?;
+            let id = args.count("id")?;
+            let (secret, package) =
+                frost_dkg_part1(id, args.count("threshold")?, args.count("participants")?)?;
+            let secret_path = dir.join(format!("dkg-secret-{id}.json"));
+            let round1_path = dir.join(format!("dkg-round1-{id}.json"));
+            write_json(&secret_path, &secret)?;
+            write_json(&round1_path, &package)?;
+            Ok(json!({ "secret": secret_path, "round1": round1_path }))
+        }
+        "frost-dkg-part2" => {
+            let secret_path = PathBuf::from(args.positional(1, "SECRET")?);
+            let mut secret: DkgSecret = read_json(&secret_path)?;
+            let received = args
+                .positionals_from(2)
+                .iter()
+                .map(read_json)
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let packages = frost_dkg_part2(&mut secret, received)?;
+            write_json(&secret_path, &secret)?;
+
+            let dir = args.out()?;
+            std::fs::create_dir_all(&dir)?;
+            let paths = packages
+                .iter()
+                .map(|package| {
+                    let path = dir.join(format!(
+                        "dkg-round2-{}-to-{}.json",
+                        package.sender, package.recipient
+                    ));
+                    write_json(&path, package)?;
+                    Ok(path)
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            Ok(json!({ "round2": paths }))
+        }
+        "frost-dkg-part3" => {
+            let secret_path = PathBuf::from(args.positional(1, "SECRET")?);
+            let secret: DkgSecret = read_json(&secret_path)?;
+            let received = args
+                .positionals_from(2)
+                .iter()
+                .map(read_json)
+                .collect::<anyhow::Result<Vec<DkgRound2Package>>>()?;
+            let share = frost_dkg_part3(&secret, &received, network)?;
+
+            let dir = args.out()?;
+            std::fs::create_dir_all(&dir)?;
+            let share_path = dir.join(format!("share-{}.json", share.identifier));
+            write_json(&share_path, &share)?;
+            write_json(&dir.join("group.json"), &share.group)?;
+            std::fs::remove_file(&secret_path)?;
+            Ok(json!({
+                "share": share_path,
+                "group": dir.join("group.json"),
+                "ufvk": share.group.ufvk,
+            }))
+        }
+        "frost-commit" => {
+            let share: FrostKeyShare = read_json(args.positional(1, "SHARE")?)?;
+            let pczt = std::fs::read(args.positional(2, "PCZT")?)?;
+            let (nonces, commitments) = frost_commit(&share, &pczt)?;
+
+            let dir = args.out()?;
+            std::fs::create_dir_all(&dir)?;
+            let nonces_path = dir.join(format!("nonces-{}.json", share.identifier));
+            let commitments_path = dir.join(format!("commitments-{}.json", share.identifier));
+            write_json(&nonces_path, &nonces)?;
+            write_json(&commitments_path, &commitments)?;
+            Ok(json!({ "nonces": nonces_path, "commitments": commitments_path }))
+        }
+        "frost-signing-package" => {
+            let group: FrostGroup = read_json(args.positional(1, "GROUP")?)?;
+            let pczt = std::fs::read(args.positional(2, "PCZT")?)?;
+            let commitments = args
+                .positionals_from(3)
+                .iter()
+                .map(read_json)
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let package = frost_signing_package(&group, &pczt, &commitments)?;
+            let path = args.out()?;
+            write_json(&path, &package)?;
+            Ok(json!({ "path": path, "signers": commitments.len() }))
+        }
+        "frost-sign" => {
+            let share: FrostKeyShare = read_json(args.positional(1, "SHARE")?)?;
+            let nonces_path = PathBuf::from(args.positional(2, "NONCES")?);
+            let nonces = read_json(&nonces_path)?;
+            let package = read_json(args.positional(3, "SIGNING-PACKAGE")?)?;
+            let shares = frost_sign(&share, &nonces, &package)?;
+
+            // Reusing nonces for another signature would reveal the key share
+            std::fs::remove_file(&nonces_path)?;
+            let path = args.out()?;
+            write_json(&path, &shares)?;
+            Ok(json!({ "path": path }))
+        }
+        "frost-aggregate" => {
+            let group: FrostGroup = read_json(args.positional(1, "GROUP")?)?;
+            let package = read_json(args.positional(2, "SIGNING-PACKAGE")?)?;
+            let shares = args
+                .positionals_from(3)
+                .iter()
+                .map(read_json)
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let signed = frost_aggregate(&group, &package, &shares)?;
+            let path = args.out()?;
+            std::fs::write(&path, signed)?;
+            Ok(json!({ "path": path }))
+        }
+        _ => anyhow::bail!("Unknown command: {command}"),
+    }
+}
+
+/// Read a JSON file exchanged between FROST participants
+fn read_json<T: DeserializeOwned>(path: impl AsRef<Path>) -> anyhow::Result<T> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {e}", path.display()))?;
+    serde_json::from_str(&contents).map_err(|e| anyhow::anyhow!("Invalid {}: {e}", path.display()))
+}
+
+/// Write a JSON file, readable only by the current user since it may hold key material
+fn write_json(path: &Path, value: &impl Serialize) -> anyhow::Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(value)?)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+
+    Ok(())
+}
+
+/// Viewing key of the stored wallet, derived from its seed unless it is watch-only
+fn config_viewing_key(config: &ZcashConfig) -> anyhow::Result<WatchOnlyKey> {
+    match (&config.seed_phrase, &config.viewing_key) {
+        (Some(seed_phrase), _) => watch_only_key(&seed_from_phrase(seed_phrase)?, config.network),
+        (None, Some(key)) => Ok(key.clone()),
+        (None, None) => anyhow::bail!("The wallet has no seed phrase or viewing key"),
+    }
+}
+
+/// Wallet password from the environment
+fn wallet_password() -> Option<String> {
+    std::env::var(PASSWORD_VAR).ok().filter(|p| !p.is_empty())
+}
+
+/// Load the stored wallet config file
+fn load_stored_config(
+    config_path: &std::path::Path,
+    data_dir: &std::path::Path,
+) -> anyhow::Result<StoredWalletConfig> {
+    StoredWalletConfig::load(config_path)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "No wallet found in {}; run `init` or `import`",
+            data_dir.display()
+        )
+    })
+}
+
+/// Load the stored wallet configuration, unlocking it with the wallet password
+fn load_config(
+    config_path: &std::path::Path,
+    data_dir: &std::path::Path,
+) -> anyhow::Result<ZcashConfig> {
+    let stored = load_stored_config(config_path, data_dir)?;
+    let db_key = stored
+        .db_key(wallet_password().as_deref())
+        .map_err(|e| anyhow::anyhow!("{e}; set {PASSWORD_VAR}"))?;
+    let mut config = stored.to_config(data_dir.to_path_buf(), db_key.as_ref())?;
+    config.db_key = db_key;
+    Ok(config)
+}
+
+/// In-process lightwalletd serving `--fixtures`
+#[cfg(feature = "test-fixtures")]
+type Mock = ikki_lib::wallet::MockLightwalletd;
+
+/// Stand-in for the mock in builds without the `test-fixtures` feature
+#[cfg(not(feature = "test-fixtures"))]
+enum Mock {}
+
+/// Start the mock lightwalletd if `--fixtures` was given
+#[cfg(feature = "test-fixtures")]
+fn start_mock(args: &Args) -> anyhow::Result<Option<Mock>> {
+    args.option("fixtures")
+        .map(|path| {
+            let fixtures = ikki_lib::wallet::MockFixtures::load(std::path::Path::new(path))?;
+            Mock::start(fixtures)
+        })
+        .transpose()
+}
+
+#[cfg(not(feature = "test-fixtures"))]
+fn start_mock(args: &Args) -> anyhow::Result<Option<Mock>> {
+    if args.option("fixtures").is_some() {
+        anyhow::bail!("--fixtures requires a build with the test-fixtures feature");
+    }
+    Ok(None)
+}
+
+/// Open a wallet connected to lightwalletd, or to the mock when fixtures are in use
+async fn open_wallet(mut config: ZcashConfig, mock: Option<&Mock>) -> anyhow::Result<IkkiWallet> {
+    // The seed is read from the config file on every run, so locking it away
+    // in memory would only stop a long-running serve-rpc from sending
+    config.auto_lock_minutes = 0;
+    match mock {
+        #[cfg(feature = "test-fixtures")]
+        Some(mock) => IkkiWallet::with_client(config, mock.connect().await?),
+        #[cfg(not(feature = "test-fixtures"))]
+        Some(mock) => match *mock {},
+        None => IkkiWallet::new(config).await,
+    }
+}
+
+/// Create a wallet database and persist its config
+///
+/// Watch-only wallets pass an empty seed and their viewing key.
+async fn create_wallet(
+    data_dir: &std::path::Path,
+    seed: String,
+    viewing_key: Option<WatchOnlyKey>,
+    args: &Args,
+    mock: Option<&Mock>,
+) -> anyhow::Result<IkkiWallet> {
+    let config_path = data_dir.join(WALLET_CONFIG_FILE);
+    if config_path.exists() {
+        anyhow::bail!("A wallet already exists in {}", data_dir.display());
+    }
+
+    let mut stored = StoredWalletConfig {
+        seed,
+        encrypted_seed: None,
+        birthday_height: args.birthday()?,
+        confirmations: Default::default(),
+        network: args.network()?,
+        lightwalletd_url: args.option("server").map(str::to_string),
+        viewing_key,
+        wrapped_db_key: None,
+        auto_lock_minutes: DEFAULT_AUTO_LOCK_MINUTES,
+    };
+    let mut config = stored.to_config(data_dir.to_path_buf(), None)?;
+    config.db_key = wallet_password()
+        .map(|password| stored.set_password(&password, None))
+        .transpose()?;
+    let mut wallet = open_wallet(config, mock).await?;
+    wallet.init_account().await?;
+    stored.save(&config_path)?;
+    Ok(wallet)
+}
+
+async fn run_wallet_command(
+    wallet: &mut IkkiWallet,
+    command: &str,
+    args: &Args,
+) -> anyhow::Result<Value> {
+    match command {
+        "sync" => {
+            let reorg = wallet.sync().await?;
+            Ok(json!({
+                "block_height": wallet.get_block_height().await?,
+                "reorg": reorg.map(|r| json!({
+                    "old_height": r.old_height,
+                    "new_height": r.new_height,
+                    "affected_txids": r.affected_txids,
+                })),
+                "balance": balance_json(wallet)?,
+            }))
+        }
+        "balance" => balance_json(wallet),
+        "addresses" => {
+            let records = wallet.get_all_addresses()?;
+            Ok(Value::Array(
+                records
+                    .into_iter()
+                    .map(|r| {
+                        json!({
+                            "address": r.address,
+                            "diversifier_index": r.diversifier_index.to_string(),
+                            "address_type": r.address_type.map(|t| t.as_str()),
+                            "issued_address": r.issued_address,
+                            "label": r.label,
+                            "received_total": r.received_total,
+                        })
+                    })
+                    .collect(),
+            ))
+        }
+        "new-address" => {
+            let address_type = match args.option("type") {
+                Some(name) => AddressType::parse(name)
+                    .ok_or_else(|| anyhow::anyhow!("Unknown address type: {name}"))?,
+                None => AddressType::default(),
+            };
+            let address = wallet.get_new_address(address_type, args.option("label"))?;
+            Ok(json!({
+                "address": address.address,
+                "address_type": address.address_type.as_str(),
+                "unified_address": address.unified_address,
+                "diversifier_index": address.diversifier_index.to_string(),
+            }))
+        }
+        "send" => {
+            let to_address = args.positional(1, "ADDRESS")?;
+            let amount = args.positional(2, "ZATOSHIS")?;
+            let memo = args.option("memo").map(|m| m.as_bytes().to_vec());
+            let result = if amount == "max" {
+                wallet.send_max(to_address, memo).await?
+            } else {
+                let amount = amount
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid amount: {amount}"))?;
+                wallet.send_to_address(to_address, amount, memo).await?
+            };
+            Ok(json!({
+                "txid": result.txid,
+                "txids": result.txids,
+                "amount": result.amount,
+                "fee": result.fee,
+            }))
+        }
+        "pczt-create" => {
+            let path = PathBuf::from(args.positional(1, "PATH")?);
+            let to_address = args.positional(2, "ADDRESS")?;
+            let amount = match args.positional(3, "ZATOSHIS")? {
+                "max" => None,
+                amount => Some(
+                    amount
+                        .parse()
+                        .map_err(|_| anyhow::anyhow!("Invalid amount: {amount}"))?,
+                ),
+            };
+            let memo = args.option("memo").map(|m| m.as_bytes().to_vec());
+            let proposal = wallet.create_pczt(to_address, amount, memo)?;
+            std::fs::write(&path, &proposal.pczt)?;
+            Ok(json!({
+                "path": path,
+                "amount": proposal.amount,
+                "fee": proposal.fee,
+            }))
+        }
+        "pczt-send" => {
+            let path = args.positional(1, "PATH")?;
+            let result = wallet.broadcast_pczt(&std::fs::read(path)?).await?;
+            Ok(json!({
+                "txid": result.txid,
+                "amount": result.amount,
+                "fee": result.fee,
+            }))
+        }
+        "history" => {
+            let limit = match args.option("limit") {
+                Some(limit) => limit
+                    .parse()
+                    .map_err(|_| anyhow::anyhow!("Invalid limit: {limit}"))?,
+                None => 50,
+            };
+            let page = wallet.query_transactions(&TransactionQuery {
+                limit,
+                ..Default::default()
+            })?;
+            Ok(Value::Array(
+                page.transactions
+                    .into_iter()
+                    .map(|tx| {
+                        json!({
+                            "txid": tx.txid,
+                            "kind": format!("{:?}", tx.kind).to_lowercase(),
+                            "amount": tx.amount,
+                            "timestamp": tx.timestamp,
+                            "mined_height": tx.mined_height,
+                            "is_pending": tx.is_pending,
+                            "address": tx.address,
+                            "contact_name": tx.contact_name,
+                            "memo": tx.memo,
+                        })
+                    })
+                    .collect(),
+            ))
+        }
+        "export" => {
+            let path = PathBuf::from(args.positional(1, "PATH")?);
+            let format = match args.option("format").unwrap_or("csv") {
+                "csv" => ExportFormat::Csv,
+                "json" => ExportFormat::Json,
+                other => anyhow::bail!("Unknown export format: {other}"),
+            };
+            let count = wallet.export_transactions(format, ExportRange::default(), &path)?;
+            Ok(json!({ "path": path, "count": count }))
+        }
+        other => anyhow::bail!("Unknown command: {other}\n\n{USAGE}"),
+    }
+}
+
+fn pczt_summary_json(summary: &PcztSummary) -> Value {
+    let outputs: Vec<Value> = summary
+        .outputs
+        .iter()
+        .map(|output| {
+            json!({
+                "pool": match output.pool {
+                    ValuePool::Transparent => "transparent",
+                    ValuePool::Sapling => "sapling",
+                    ValuePool::Orchard => "orchard",
+                },
+                "address": output.address,
+                "value": output.value,
+                "is_change": output.is_change,
+            })
+        })
+        .collect();
+    json!({
+        "outputs": outputs,
+        "amount": summary.amount(),
+        "fee": summary.fee,
+    })
+}
+
+fn pool_json(balance: &PoolBalance) -> Value {
+    json!({
+        "spendable": balance.spendable,
+        "change_pending_confirmation": balance.change_pending_confirmation,
+        "value_pending_spendability": balance.value_pending_spendability,
+        "total": balance.total(),
+    })
+}
+
+fn balance_json(wallet: &IkkiWallet) -> anyhow::Result<Value> {
+    let breakdown = wallet.get_balance_breakdown()?;
+    Ok(json!({
+        "total": breakdown.total(),
+        "spendable": breakdown.spendable(),
+        "pools": {
+            "transparent": pool_json(&breakdown.transparent),
+            "sapling": pool_json(&breakdown.sapling),
+            "orchard": pool_json(&breakdown.orchard),
+        },
+        "fully_scanned_height": breakdown.fully_scanned_height,
+        "chain_tip_height": breakdown.chain_tip_height,
+    }))
+}
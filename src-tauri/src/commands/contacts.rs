@@ -0,0 +1,100 @@
+//! Address book Tauri commands
+
+use crate::state::AppState;
+use crate::wallet::{Contact, ContactInput, ErrorContext, IkkiError};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+/// Address book import result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportContactsResult {
+    pub imported: usize,
+    pub skipped: usize,
+}
+
+/// List all contacts
+#[tauri::command]
+pub async fn list_contacts(state: State<'_, AppState>) -> Result<Vec<Contact>, IkkiError> {
+    let wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_ref().ok_or(IkkiError::NotInitialized)?;
+
+    wallet
+        .address_book()
+        .list()
+        .context("Failed to list contacts")
+}
+
+/// Add a contact
+#[tauri::command]
+pub async fn add_contact(
+    state: State<'_, AppState>,
+    contact: ContactInput,
+) -> Result<Contact, IkkiError> {
+    let wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_ref().ok_or(IkkiError::NotInitialized)?;
+
+    wallet
+        .address_book()
+        .add(&contact)
+        .context("Failed to add contact")
+}
+
+/// Update a contact
+#[tauri::command]
+pub async fn update_contact(
+    state: State<'_, AppState>,
+    id: i64,
+    contact: ContactInput,
+) -> Result<Contact, IkkiError> {
+    let wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_ref().ok_or(IkkiError::NotInitialized)?;
+
+    wallet
+        .address_book()
+        .update(id, &contact)
+        .context("Failed to update contact")
+}
+
+/// Delete a contact
+#[tauri::command]
+pub async fn delete_contact(state: State<'_, AppState>, id: i64) -> Result<(), IkkiError> {
+    let wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_ref().ok_or(IkkiError::NotInitialized)?;
+
+    wallet
+        .address_book()
+        .delete(id)
+        .context("Failed to delete contact")
+}
+
+/// Export the address book to a JSON file
+#[tauri::command]
+pub async fn export_contacts(state: State<'_, AppState>, path: String) -> Result<usize, IkkiError> {
+    let wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_ref().ok_or(IkkiError::NotInitialized)?;
+
+    wallet
+        .address_book()
+        .export_json(std::path::Path::new(&path))
+        .context("Failed to export contacts")
+}
+
+/// Import contacts from a JSON file
+#[tauri::command]
+pub async fn import_contacts(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<ImportContactsResult, IkkiError> {
+    let wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_ref().ok_or(IkkiError::NotInitialized)?;
+
+    let summary = wallet
+        .address_book()
+        .import_json(std::path::Path::new(&path))
+        .context("Failed to import contacts")?;
+
+    Ok(ImportContactsResult {
+        imported: summary.imported,
+        skipped: summary.skipped,
+    })
+}
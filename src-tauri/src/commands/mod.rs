@@ -1,4 +1,7 @@
 //! Tauri commands
 
+pub mod contacts;
+pub mod qr;
+pub mod rpc;
 pub mod transactions;
 pub mod wallet;
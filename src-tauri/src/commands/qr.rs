@@ -0,0 +1,119 @@
+//! Animated QR (UR) Tauri commands
+
+use crate::state::AppState;
+use crate::wallet::{
+    ErrorContext, IkkiError, UrKind, UrPayload, UrScanProgress, UrScanner, WatchOnlyKey,
+    DEFAULT_FRAGMENT_LEN,
+};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+use tracing::info;
+
+/// Frames of an animated QR code, shown in a loop
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UrFrames {
+    pub kind: UrKind,
+    pub frames: Vec<String>,
+}
+
+/// Payload decoded from a completed scan
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScannedUr {
+    pub kind: UrKind,
+    /// File the PCZT or transaction was written to
+    pub path: Option<String>,
+    /// Scanned viewing key
+    pub viewing_key: Option<WatchOnlyKey>,
+}
+
+/// Encode a PCZT or transaction file, or the wallet's viewing key, as UR frames
+#[tauri::command]
+pub async fn encode_ur(
+    state: State<'_, AppState>,
+    kind: UrKind,
+    path: Option<String>,
+    max_fragment_len: Option<usize>,
+) -> Result<UrFrames, IkkiError> {
+    let payload = match kind {
+        UrKind::ViewingKey => {
+            let wallet_lock = state.wallet.lock().await;
+            let wallet = wallet_lock.as_ref().ok_or(IkkiError::NotInitialized)?;
+            UrPayload::ViewingKey(
+                wallet
+                    .export_viewing_key()
+                    .context("Failed to export viewing key")?,
+            )
+        }
+        UrKind::Pczt | UrKind::Transaction => {
+            let path = path.ok_or(IkkiError::Other("A file path is required".to_string()))?;
+            let data = std::fs::read(&path).context("Failed to read file")?;
+            match kind {
+                UrKind::Pczt => UrPayload::Pczt(data),
+                _ => UrPayload::Transaction(data),
+            }
+        }
+    };
+
+    let frames = payload
+        .encode_frames(max_fragment_len.unwrap_or(DEFAULT_FRAGMENT_LEN))
+        .context("Failed to encode QR frames")?;
+    Ok(UrFrames { kind, frames })
+}
+
+/// Add a scanned frame to the animated QR code being scanned
+#[tauri::command]
+pub async fn scan_ur_frame(
+    state: State<'_, AppState>,
+    frame: String,
+) -> Result<UrScanProgress, IkkiError> {
+    state
+        .ur_scanner
+        .lock()
+        .await
+        .receive(&frame)
+        .context("Invalid QR frame")
+}
+
+/// Take the payload of a completed scan, writing PCZTs and transactions to `path`
+///
+/// Resets the scanner for the next code.
+#[tauri::command]
+pub async fn finish_ur_scan(
+    state: State<'_, AppState>,
+    path: Option<String>,
+) -> Result<ScannedUr, IkkiError> {
+    let mut scanner = state.ur_scanner.lock().await;
+    let payload = scanner
+        .payload()
+        .context("Failed to decode QR code")?
+        .ok_or(IkkiError::Other("The QR code has not been fully scanned".to_string()))?;
+    let kind = payload.kind();
+
+    let scanned = match payload {
+        UrPayload::ViewingKey(key) => ScannedUr {
+            kind,
+            path: None,
+            viewing_key: Some(key),
+        },
+        UrPayload::Pczt(data) | UrPayload::Transaction(data) => {
+            let path = path.ok_or(IkkiError::Other("A file path is required".to_string()))?;
+            std::fs::write(&path, data).context("Failed to write file")?;
+            info!("Wrote scanned {} to {}", kind.ur_type(), path);
+            ScannedUr {
+                kind,
+                path: Some(path),
+                viewing_key: None,
+            }
+        }
+    };
+
+    *scanner = UrScanner::new();
+    Ok(scanned)
+}
+
+/// Discard the frames scanned so far
+#[tauri::command]
+pub async fn reset_ur_scan(state: State<'_, AppState>) -> Result<(), IkkiError> {
+    *state.ur_scanner.lock().await = UrScanner::new();
+    Ok(())
+}
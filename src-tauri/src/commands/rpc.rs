@@ -0,0 +1,76 @@
+//! Local JSON-RPC server Tauri commands
+
+use crate::rpc::{self, RpcEndpoint};
+use crate::state::AppState;
+use crate::wallet::{default_data_dir, ErrorContext, IkkiError};
+use serde::{Deserialize, Serialize};
+use tauri::State;
+
+/// Default port, matching zcashd on testnet
+const DEFAULT_RPC_PORT: u16 = 18232;
+
+/// Running RPC server details
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RpcServerInfo {
+    /// `http://127.0.0.1:<port>` or `unix:<path>`
+    pub endpoint: String,
+    /// Cookie file holding the `__cookie__:<password>` credentials
+    pub cookie_path: String,
+}
+
+/// Start the local JSON-RPC server on a localhost port or a Unix socket
+#[tauri::command]
+pub async fn start_rpc_server(
+    state: State<'_, AppState>,
+    port: Option<u16>,
+    socket_path: Option<String>,
+) -> Result<RpcServerInfo, IkkiError> {
+    let mut server = state.rpc_server.lock().await;
+    if server.is_some() {
+        return Err("RPC server is already running".into());
+    }
+
+    let endpoint = match socket_path {
+        #[cfg(unix)]
+        Some(path) => RpcEndpoint::Unix(path.into()),
+        #[cfg(not(unix))]
+        Some(_) => return Err("Unix sockets are not supported on this platform".into()),
+        None => RpcEndpoint::Tcp(port.unwrap_or(DEFAULT_RPC_PORT)),
+    };
+
+    let handle = rpc::start(state.wallet.clone(), endpoint, &default_data_dir())
+        .await
+        .context("Failed to start RPC server")?;
+
+    let info = RpcServerInfo {
+        endpoint: handle.endpoint().to_string(),
+        cookie_path: handle.cookie_path().display().to_string(),
+    };
+    *server = Some(handle);
+    Ok(info)
+}
+
+/// Stop the local JSON-RPC server
+#[tauri::command]
+pub async fn stop_rpc_server(state: State<'_, AppState>) -> Result<(), IkkiError> {
+    if let Some(handle) = state.rpc_server.lock().await.take() {
+        handle.stop();
+    }
+    Ok(())
+}
+
+/// Get the running RPC server, if any
+#[tauri::command]
+pub async fn get_rpc_server_status(
+    state: State<'_, AppState>,
+) -> Result<Option<RpcServerInfo>, IkkiError> {
+    Ok(state
+        .rpc_server
+        .lock()
+        .await
+        .as_ref()
+        .map(|handle| RpcServerInfo {
+            endpoint: handle.endpoint().to_string(),
+            cookie_path: handle.cookie_path().display().to_string(),
+        }))
+}
@@ -1,11 +1,16 @@
 //! Transaction-related Tauri commands
 
 use crate::state::{AppState, PendingTransaction, PendingTxState, PendingTxStatus};
+use crate::wallet::{
+    is_tex_address, ErrorContext, ExportFormat as WalletExportFormat, ExportRange, IkkiError,
+    MemoContent, PcztSummary, TransactionDetails as WalletTransactionDetails, TransactionDirection,
+    TransactionKind, TransactionQuery, TransactionRecord, ValuePool,
+};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tauri::State;
-use tracing::{info, error};
+use tracing::{error, info};
 use uuid::Uuid;
 
 /// Transaction type
@@ -16,6 +21,19 @@ pub enum TransactionType {
     Received,
     Shielding,
     Internal,
+    Mixed,
+}
+
+impl From<TransactionKind> for TransactionType {
+    fn from(kind: TransactionKind) -> Self {
+        match kind {
+            TransactionKind::Sent => TransactionType::Sent,
+            TransactionKind::Received => TransactionType::Received,
+            TransactionKind::Shielding => TransactionType::Shielding,
+            TransactionKind::Internal => TransactionType::Internal,
+            TransactionKind::Mixed => TransactionType::Mixed,
+        }
+    }
 }
 
 /// Transaction status
@@ -35,6 +53,7 @@ pub struct Transaction {
     pub amount: i64,
     pub timestamp: u64,
     pub address: Option<String>,
+    pub contact_name: Option<String>,
     pub memo: Option<String>,
     pub status: TransactionStatus,
     pub confirmations: u32,
@@ -44,7 +63,10 @@ pub struct Transaction {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SendResult {
     pub txid: String,
+    /// All transactions of the payment in broadcast order (two for TEX recipients)
+    pub txids: Vec<String>,
     pub amount: u64,
+    /// Combined fee of all transactions
     pub fee: u64,
 }
 
@@ -55,89 +77,601 @@ pub async fn send_transaction(
     to_address: String,
     amount: u64,
     memo: Option<String>,
-) -> Result<SendResult, String> {
+) -> Result<SendResult, IkkiError> {
     let mut wallet_lock = state.wallet.lock().await;
-    let wallet = wallet_lock.as_mut().ok_or("Wallet not initialized")?;
+    let wallet = wallet_lock.as_mut().ok_or(IkkiError::NotInitialized)?;
 
     let memo_bytes = memo.map(|m| m.into_bytes());
 
     let result = wallet
         .send_to_address(&to_address, amount, memo_bytes)
         .await
-        .map_err(|e| format!("Send failed: {e}"))?;
+        .context("Send failed")?;
 
     Ok(SendResult {
         txid: result.txid,
+        txids: result.txids,
         amount,
         fee: result.fee,
     })
 }
 
+/// Maximum sendable amount and the fee it would pay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaxTransferInfo {
+    pub amount: u64,
+    pub fee: u64,
+}
+
+/// Compute the maximum amount that can be sent to an address, without sending
+#[tauri::command]
+pub async fn propose_max_transfer(
+    state: State<'_, AppState>,
+    to_address: String,
+    memo: Option<String>,
+) -> Result<MaxTransferInfo, IkkiError> {
+    let mut wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_mut().ok_or(IkkiError::NotInitialized)?;
+
+    let max = wallet
+        .propose_max_transfer(&to_address, memo.map(|m| m.into_bytes()))
+        .context("Failed to compute max amount")?;
+
+    Ok(MaxTransferInfo {
+        amount: max.amount,
+        fee: max.fee,
+    })
+}
+
+/// Send the entire spendable balance to an address
+#[tauri::command]
+pub async fn send_max(
+    state: State<'_, AppState>,
+    to_address: String,
+    memo: Option<String>,
+) -> Result<SendResult, IkkiError> {
+    let mut wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_mut().ok_or(IkkiError::NotInitialized)?;
+
+    let result = wallet
+        .send_max(&to_address, memo.map(|m| m.into_bytes()))
+        .await
+        .context("Send failed")?;
+
+    Ok(SendResult {
+        txid: result.txid,
+        txids: result.txids,
+        amount: result.amount,
+        fee: result.fee,
+    })
+}
+
+/// Unsigned PCZT written for an offline signer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PcztInfo {
+    pub path: String,
+    pub amount: u64,
+    pub fee: u64,
+}
+
+/// Create an unsigned PCZT paying an address and write it to a file
+///
+/// Sends the entire spendable balance when `amount` is omitted.
+#[tauri::command]
+pub async fn create_pczt(
+    state: State<'_, AppState>,
+    to_address: String,
+    amount: Option<u64>,
+    memo: Option<String>,
+    path: String,
+) -> Result<PcztInfo, IkkiError> {
+    let mut wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_mut().ok_or(IkkiError::NotInitialized)?;
+
+    let proposal = wallet
+        .create_pczt(&to_address, amount, memo.map(|m| m.into_bytes()))
+        .context("Failed to create PCZT")?;
+    std::fs::write(&path, &proposal.pczt).context("Failed to write PCZT")?;
+
+    info!("Wrote unsigned PCZT to {}", path);
+    Ok(PcztInfo {
+        path,
+        amount: proposal.amount,
+        fee: proposal.fee,
+    })
+}
+
+/// Payment made by a PCZT
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PcztOutputInfo {
+    pub pool: Pool,
+    pub address: String,
+    pub value: u64,
+    pub is_change: bool,
+}
+
+/// Outputs and fee of a PCZT, shown for confirmation before signing
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PcztSummaryInfo {
+    pub outputs: Vec<PcztOutputInfo>,
+    /// Total paid to recipients, excluding change
+    pub amount: u64,
+    pub fee: u64,
+}
+
+impl From<PcztSummary> for PcztSummaryInfo {
+    fn from(summary: PcztSummary) -> Self {
+        PcztSummaryInfo {
+            amount: summary.amount(),
+            fee: summary.fee,
+            outputs: summary
+                .outputs
+                .into_iter()
+                .map(|o| PcztOutputInfo {
+                    pool: o.pool.into(),
+                    address: o.address,
+                    value: o.value,
+                    is_change: o.is_change,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Decode the outputs and fee of a PCZT file so the user can confirm them
+#[tauri::command]
+pub async fn inspect_pczt(
+    state: State<'_, AppState>,
+    input_path: String,
+) -> Result<PcztSummaryInfo, IkkiError> {
+    let wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_ref().ok_or(IkkiError::NotInitialized)?;
+
+    let pczt = std::fs::read(&input_path).context("Failed to read PCZT")?;
+    let summary = wallet.inspect_pczt(&pczt).context("Failed to read PCZT")?;
+    Ok(summary.into())
+}
+
+/// Prove and sign a PCZT file with the wallet's seed, writing the signed PCZT
+///
+/// `confirmed` is the summary from `inspect_pczt` the user approved; the PCZT
+/// is only signed if it still pays exactly that.
+#[tauri::command]
+pub async fn sign_pczt(
+    state: State<'_, AppState>,
+    input_path: String,
+    output_path: String,
+    confirmed: PcztSummaryInfo,
+) -> Result<(), IkkiError> {
+    let mut wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_mut().ok_or(IkkiError::NotInitialized)?;
+
+    let pczt = std::fs::read(&input_path).context("Failed to read PCZT")?;
+    let summary = PcztSummaryInfo::from(wallet.inspect_pczt(&pczt).context("Failed to read PCZT")?);
+    if summary != confirmed {
+        return Err(IkkiError::Other(
+            "The PCZT does not match the confirmed payment".to_string(),
+        ));
+    }
+    let signed = wallet.sign_pczt(&pczt).context("Failed to sign PCZT")?;
+    std::fs::write(&output_path, signed).context("Failed to write signed PCZT")?;
+
+    info!("Wrote signed PCZT to {}", output_path);
+    Ok(())
+}
+
+/// Finalize and broadcast a signed PCZT file
+#[tauri::command]
+pub async fn broadcast_pczt(
+    state: State<'_, AppState>,
+    path: String,
+) -> Result<SendResult, IkkiError> {
+    let mut wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_mut().ok_or(IkkiError::NotInitialized)?;
+
+    let pczt = std::fs::read(&path).context("Failed to read PCZT")?;
+    let result = wallet
+        .broadcast_pczt(&pczt)
+        .await
+        .context("Broadcast failed")?;
+
+    Ok(SendResult {
+        txid: result.txid,
+        txids: result.txids,
+        amount: result.amount,
+        fee: result.fee,
+    })
+}
+
+/// Convert a wallet transaction record to the frontend format
+fn to_frontend_transaction(r: TransactionRecord, current_height: u32) -> Transaction {
+    // Calculate actual confirmations based on mined height
+    let confirmations = if let Some(mined_height) = r.mined_height {
+        if current_height >= mined_height {
+            current_height - mined_height + 1
+        } else {
+            0
+        }
+    } else {
+        0
+    };
+
+    Transaction {
+        txid: r.txid,
+        tx_type: r.kind.into(),
+        amount: r.amount,
+        timestamp: r.timestamp,
+        address: r.address,
+        contact_name: r.contact_name,
+        memo: r.memo,
+        status: if r.is_pending {
+            TransactionStatus::Pending
+        } else {
+            TransactionStatus::Confirmed
+        },
+        confirmations,
+    }
+}
+
 /// Get transaction history
 #[tauri::command]
-pub async fn get_transactions(state: State<'_, AppState>) -> Result<Vec<Transaction>, String> {
+pub async fn get_transactions(state: State<'_, AppState>) -> Result<Vec<Transaction>, IkkiError> {
     let mut wallet_lock = state.wallet.lock().await;
-    let wallet = wallet_lock.as_mut().ok_or("Wallet not initialized")?;
+    let wallet = wallet_lock.as_mut().ok_or(IkkiError::NotInitialized)?;
 
     // Get current block height to calculate confirmations
-    let current_height = wallet
-        .get_block_height()
-        .await
-        .unwrap_or(0) as u32;
+    let current_height = wallet.get_block_height().await.unwrap_or(0) as u32;
 
     // Get recent transactions from the wallet
     let records = wallet
         .get_recent_transactions(50)
-        .map_err(|e| format!("Failed to get transactions: {e}"))?;
+        .context("Failed to get transactions")?;
 
     // Convert to frontend format
     let transactions: Vec<Transaction> = records
         .into_iter()
-        .map(|r| {
-            // Calculate actual confirmations based on mined height
-            let confirmations = if let Some(mined_height) = r.mined_height {
-                if current_height >= mined_height {
-                    current_height - mined_height + 1
-                } else {
-                    0
-                }
-            } else {
-                0
-            };
-
-            Transaction {
-                txid: r.txid,
-                tx_type: if r.is_sent {
-                    TransactionType::Sent
-                } else {
-                    TransactionType::Received
-                },
-                amount: r.amount,
-                timestamp: r.timestamp,
-                address: None,
-                memo: r.memo,
-                status: if r.is_pending {
-                    TransactionStatus::Pending
-                } else {
-                    TransactionStatus::Confirmed
-                },
-                confirmations,
-            }
-        })
+        .map(|r| to_frontend_transaction(r, current_height))
         .collect();
 
     Ok(transactions)
 }
 
+/// Transaction direction filter
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DirectionFilter {
+    Sent,
+    Received,
+}
+
+/// Value pool filter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Pool {
+    Transparent,
+    Sapling,
+    Orchard,
+}
+
+impl From<Pool> for ValuePool {
+    fn from(pool: Pool) -> Self {
+        match pool {
+            Pool::Transparent => ValuePool::Transparent,
+            Pool::Sapling => ValuePool::Sapling,
+            Pool::Orchard => ValuePool::Orchard,
+        }
+    }
+}
+
+impl From<ValuePool> for Pool {
+    fn from(pool: ValuePool) -> Self {
+        match pool {
+            ValuePool::Transparent => Pool::Transparent,
+            ValuePool::Sapling => Pool::Sapling,
+            ValuePool::Orchard => Pool::Orchard,
+        }
+    }
+}
+
+/// Transaction history filters from the frontend
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TransactionFilter {
+    pub direction: Option<DirectionFilter>,
+    pub pool: Option<Pool>,
+    pub from_time: Option<u64>,
+    pub to_time: Option<u64>,
+    pub min_amount: Option<u64>,
+    pub max_amount: Option<u64>,
+    pub search: Option<String>,
+    pub is_pending: Option<bool>,
+}
+
+/// A page of transaction history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionPage {
+    pub transactions: Vec<Transaction>,
+    pub next_cursor: Option<String>,
+}
+
+/// Query transaction history with filters and cursor-based pagination
+#[tauri::command]
+pub async fn query_transactions(
+    state: State<'_, AppState>,
+    filter: Option<TransactionFilter>,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> Result<TransactionPage, IkkiError> {
+    let mut wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_mut().ok_or(IkkiError::NotInitialized)?;
+
+    let current_height = wallet.get_block_height().await.unwrap_or(0) as u32;
+
+    let filter = filter.unwrap_or_default();
+    let query = TransactionQuery {
+        direction: filter.direction.map(|d| match d {
+            DirectionFilter::Sent => TransactionDirection::Sent,
+            DirectionFilter::Received => TransactionDirection::Received,
+        }),
+        pool: filter.pool.map(ValuePool::from),
+        from_time: filter.from_time,
+        to_time: filter.to_time,
+        min_amount: filter.min_amount,
+        max_amount: filter.max_amount,
+        search: filter.search,
+        is_pending: filter.is_pending,
+        cursor,
+        limit: limit.unwrap_or(0) as usize,
+    };
+
+    let page = wallet
+        .query_transactions(&query)
+        .context("Failed to query transactions")?;
+
+    Ok(TransactionPage {
+        transactions: page
+            .transactions
+            .into_iter()
+            .map(|r| to_frontend_transaction(r, current_height))
+            .collect(),
+        next_cursor: page.next_cursor,
+    })
+}
+
+/// ZIP 302 memo type
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MemoType {
+    Empty,
+    Text,
+    Arbitrary,
+    Future,
+}
+
+/// Decoded memo for frontend
+///
+/// Text memos carry `text`; binary (arbitrary or future format) memos carry `hex`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoInfo {
+    pub memo_type: MemoType,
+    pub text: Option<String>,
+    pub hex: Option<String>,
+}
+
+impl From<MemoContent> for MemoInfo {
+    fn from(memo: MemoContent) -> Self {
+        match memo {
+            MemoContent::Empty => MemoInfo {
+                memo_type: MemoType::Empty,
+                text: None,
+                hex: None,
+            },
+            MemoContent::Text(text) => MemoInfo {
+                memo_type: MemoType::Text,
+                text: Some(text),
+                hex: None,
+            },
+            MemoContent::Arbitrary(data) => MemoInfo {
+                memo_type: MemoType::Arbitrary,
+                text: None,
+                hex: Some(hex::encode(data)),
+            },
+            MemoContent::Future(data) => MemoInfo {
+                memo_type: MemoType::Future,
+                text: None,
+                hex: Some(hex::encode(data)),
+            },
+        }
+    }
+}
+
+/// Memo attached to a transaction output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionMemo {
+    pub pool: Pool,
+    pub output_index: u32,
+    pub is_received: bool,
+    pub memo: MemoInfo,
+}
+
+/// Transaction output for frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionOutput {
+    pub pool: Pool,
+    pub output_index: u32,
+    pub address: Option<String>,
+    pub value: u64,
+    pub memo: Option<MemoInfo>,
+    pub is_change: bool,
+    pub is_received: bool,
+}
+
+/// Full transaction details for frontend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionDetails {
+    pub txid: String,
+    pub tx_type: TransactionType,
+    pub amount: i64,
+    pub fee: Option<u64>,
+    pub mined_height: Option<u32>,
+    pub block_hash: Option<String>,
+    pub block_time: Option<u64>,
+    pub expiry_height: Option<u32>,
+    pub raw_size: Option<usize>,
+    pub confirmations: u32,
+    pub outputs: Vec<TransactionOutput>,
+}
+
+/// Get the full details of a transaction, including every output
+#[tauri::command]
+pub async fn get_transaction_details(
+    state: State<'_, AppState>,
+    txid: String,
+) -> Result<TransactionDetails, IkkiError> {
+    let mut wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_mut().ok_or(IkkiError::NotInitialized)?;
+
+    let current_height = wallet.get_block_height().await.unwrap_or(0) as u32;
+
+    let details: WalletTransactionDetails = wallet
+        .get_transaction_details(&txid)
+        .context("Failed to get transaction details")?
+        .ok_or_else(|| IkkiError::NotFound(format!("Transaction {txid}")))?;
+
+    let confirmations = match details.mined_height {
+        Some(mined_height) if current_height >= mined_height => current_height - mined_height + 1,
+        _ => 0,
+    };
+
+    Ok(TransactionDetails {
+        txid: details.txid,
+        tx_type: details.kind.into(),
+        amount: details.amount,
+        fee: details.fee,
+        mined_height: details.mined_height,
+        block_hash: details.block_hash,
+        block_time: details.block_time,
+        expiry_height: details.expiry_height,
+        raw_size: details.raw_size,
+        confirmations,
+        outputs: details
+            .outputs
+            .into_iter()
+            .map(|o| TransactionOutput {
+                pool: o.pool.into(),
+                output_index: o.output_index,
+                address: o.address,
+                value: o.value,
+                memo: o.memo.map(MemoInfo::from),
+                is_change: o.is_change,
+                is_received: o.is_received,
+            })
+            .collect(),
+    })
+}
+
+/// Get every memo of a transaction with its output index and pool
+#[tauri::command]
+pub async fn get_transaction_memos(
+    state: State<'_, AppState>,
+    txid: String,
+) -> Result<Vec<TransactionMemo>, IkkiError> {
+    let wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_ref().ok_or(IkkiError::NotInitialized)?;
+
+    let memos = wallet
+        .get_transaction_memos(&txid)
+        .context("Failed to get transaction memos")?;
+
+    Ok(memos
+        .into_iter()
+        .map(|m| TransactionMemo {
+            pool: m.pool.into(),
+            output_index: m.output_index,
+            is_received: m.is_received,
+            memo: m.memo.into(),
+        })
+        .collect())
+}
+
+/// Transaction history export format
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// Time range for history export (inclusive, Unix seconds)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportTimeRange {
+    pub from_time: Option<u64>,
+    pub to_time: Option<u64>,
+}
+
+/// Export result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExportResult {
+    pub path: String,
+    pub count: usize,
+}
+
+/// Export transaction history to a CSV or JSON file at the given path
+#[tauri::command]
+pub async fn export_transactions(
+    state: State<'_, AppState>,
+    format: ExportFormat,
+    path: String,
+    range: Option<ExportTimeRange>,
+) -> Result<ExportResult, IkkiError> {
+    let wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_ref().ok_or(IkkiError::NotInitialized)?;
+
+    let range = range.unwrap_or_default();
+    let format = match format {
+        ExportFormat::Csv => WalletExportFormat::Csv,
+        ExportFormat::Json => WalletExportFormat::Json,
+    };
+
+    let count = wallet
+        .export_transactions(
+            format,
+            ExportRange {
+                from_time: range.from_time,
+                to_time: range.to_time,
+            },
+            std::path::Path::new(&path),
+        )
+        .context("Failed to export transactions")?;
+
+    info!("Exported {} transactions to {}", count, path);
+    Ok(ExportResult { path, count })
+}
+
 /// Start a transaction in the background
 /// Returns immediately with a pending transaction ID
+///
+/// With `max` set the entire spendable balance is sent and `amount` is only the
+/// estimate shown until the payment is broadcast.
 #[tauri::command]
 pub async fn send_transaction_background(
     state: State<'_, AppState>,
     to_address: String,
     amount: u64,
     memo: Option<String>,
-) -> Result<PendingTransaction, String> {
+    max: Option<bool>,
+) -> Result<PendingTransaction, IkkiError> {
+    // Fail fast on a locked wallet; a busy wallet is checked when the send runs
+    if let Ok(wallet_lock) = state.wallet.try_lock() {
+        if wallet_lock
+            .as_ref()
+            .is_some_and(|wallet| wallet.is_locked())
+        {
+            return Err(IkkiError::WalletLocked);
+        }
+    }
+
     // Generate unique ID for tracking
     let pending_id = Uuid::new_v4().to_string();
     let created_at = SystemTime::now()
@@ -149,6 +683,9 @@ pub async fn send_transaction_background(
     let pending_tx = PendingTransaction {
         id: pending_id.clone(),
         txid: None,
+        txids: Vec::new(),
+        is_tex: is_tex_address(&to_address),
+        fee: None,
         to_address: to_address.clone(),
         amount,
         memo: memo.clone(),
@@ -166,6 +703,7 @@ pub async fn send_transaction_background(
     let wallet = state.wallet.clone();
     let pending_state = state.pending_tx_state.clone();
     let id = pending_id.clone();
+    let amount = (!max.unwrap_or(false)).then_some(amount);
 
     // Spawn background task for transaction
     tokio::spawn(async move {
@@ -176,15 +714,26 @@ pub async fn send_transaction_background(
 }
 
 /// Background task that builds and broadcasts the transaction
+///
+/// Sends the entire spendable balance when `amount` is None.
 async fn run_background_transaction(
     wallet: Arc<tokio::sync::Mutex<Option<crate::wallet::IkkiWallet>>>,
     pending_state: Arc<PendingTxState>,
     id: String,
     to_address: String,
-    amount: u64,
+    amount: Option<u64>,
     memo: Option<String>,
 ) {
-    info!("Building transaction {} to {} for {} zatoshis", id, to_address, amount);
+    match amount {
+        Some(amount) => info!(
+            "Building transaction {} to {} for {} zatoshis",
+            id, to_address, amount
+        ),
+        None => info!(
+            "Building transaction {} of the max amount to {}",
+            id, to_address
+        ),
+    }
 
     // Build and send the transaction
     let result = {
@@ -193,24 +742,57 @@ async fn run_background_transaction(
             Some(w) => w,
             None => {
                 error!("Wallet not initialized for transaction {}", id);
-                pending_state.update_status(&id, PendingTxStatus::Failed, None, Some("Wallet not initialized".to_string())).await;
+                pending_state
+                    .update_status(
+                        &id,
+                        PendingTxStatus::Failed,
+                        None,
+                        Some(IkkiError::NotInitialized),
+                    )
+                    .await;
                 return;
             }
         };
 
         let memo_bytes = memo.map(|m| m.into_bytes());
-        wallet.send_to_address(&to_address, amount, memo_bytes).await
+        match amount {
+            Some(amount) => {
+                wallet
+                    .send_to_address(&to_address, amount, memo_bytes)
+                    .await
+            }
+            None => wallet.send_max(&to_address, memo_bytes).await,
+        }
     };
 
     match result {
         Ok(send_result) => {
-            info!("Transaction {} broadcast successfully with txid {}", id, send_result.txid);
-            pending_state.update_status(&id, PendingTxStatus::Broadcast, Some(send_result.txid), None).await;
-        }
-        Err(e) => {
-            error!("Transaction {} failed: {}", id, e);
-            pending_state.update_status(&id, PendingTxStatus::Failed, None, Some(e.to_string())).await;
+            info!(
+                "Transaction {} broadcast successfully with txid {}",
+                id, send_result.txid
+            );
+            pending_state
+                .mark_broadcast(
+                    &id,
+                    send_result.txid,
+                    send_result.txids,
+                    send_result.amount,
+                    send_result.fee,
+                )
+                .await;
         }
+        Err(e) => match IkkiError::from(e) {
+            error @ IkkiError::PartialBroadcast { .. } => {
+                error!("Transaction {} {}", id, error);
+                pending_state.mark_partially_broadcast(&id, error).await;
+            }
+            error => {
+                error!("Transaction {} failed: {}", id, error);
+                pending_state
+                    .update_status(&id, PendingTxStatus::Failed, None, Some(error))
+                    .await;
+            }
+        },
     }
 }
 
@@ -218,7 +800,7 @@ async fn run_background_transaction(
 #[tauri::command]
 pub async fn get_pending_transactions(
     state: State<'_, AppState>,
-) -> Result<Vec<PendingTransaction>, String> {
+) -> Result<Vec<PendingTransaction>, IkkiError> {
     Ok(state.pending_tx_state.get_all().await)
 }
 
@@ -227,7 +809,7 @@ pub async fn get_pending_transactions(
 pub async fn get_pending_transaction(
     state: State<'_, AppState>,
     id: String,
-) -> Result<Option<PendingTransaction>, String> {
+) -> Result<Option<PendingTransaction>, IkkiError> {
     Ok(state.pending_tx_state.get(&id).await)
 }
 
@@ -236,7 +818,7 @@ pub async fn get_pending_transaction(
 pub async fn dismiss_pending_transaction(
     state: State<'_, AppState>,
     id: String,
-) -> Result<(), String> {
+) -> Result<(), IkkiError> {
     state.pending_tx_state.remove(&id).await;
     Ok(())
 }
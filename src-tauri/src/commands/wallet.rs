@@ -1,12 +1,20 @@
 //! Wallet-related Tauri commands
 
 use crate::state::AppState;
-use crate::wallet::{IkkiWallet, ZcashConfig};
+use crate::wallet::{
+    default_data_dir, is_plaintext_db, AddressEncoding, AddressType, BalanceBreakdown, ChainReorg,
+    ConfirmationSettings, DbKey, ErrorContext, IkkiError, IkkiWallet, PoolBalance, ReceiverKind,
+    StoredWalletConfig, WatchOnlyKey, DEFAULT_AUTO_LOCK_MINUTES, WALLET_CONFIG_FILE,
+};
+use zcash_protocol::consensus::NetworkType;
 use serde::{Deserialize, Serialize};
-use std::io::{Read, Write};
 use std::sync::Arc;
-use tauri::{AppHandle, Emitter, State};
-use tracing::info;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tracing::{info, warn};
+
+/// How often the backend checks whether the wallet should auto-lock
+const AUTO_LOCK_CHECK_INTERVAL: Duration = Duration::from_secs(15);
 
 /// Wallet information returned to frontend
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -16,12 +24,75 @@ pub struct WalletInfo {
     pub block_height: u64,
 }
 
+/// Balance of one value pool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolBalanceInfo {
+    /// Spendable under the confirmation policy
+    pub spendable: u64,
+    /// Change from own transactions awaiting confirmations
+    pub change_pending_confirmation: u64,
+    /// Received value awaiting confirmations or scanning
+    pub value_pending_spendability: u64,
+    /// Sum of the above
+    pub total: u64,
+}
+
+impl From<&PoolBalance> for PoolBalanceInfo {
+    fn from(balance: &PoolBalance) -> Self {
+        Self {
+            spendable: balance.spendable,
+            change_pending_confirmation: balance.change_pending_confirmation,
+            value_pending_spendability: balance.value_pending_spendability,
+            total: balance.total(),
+        }
+    }
+}
+
+/// Per-pool balances
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolBalances {
+    pub transparent: PoolBalanceInfo,
+    pub sapling: PoolBalanceInfo,
+    pub orchard: PoolBalanceInfo,
+}
+
 /// Balance breakdown
+///
+/// `total`, `shielded` and `transparent` include value that is not yet
+/// spendable; `spendable` is what can be sent right now.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BalanceInfo {
+    /// All value across all pools
     pub total: u64,
+    /// Value spendable across all pools
+    pub spendable: u64,
+    /// All value in the shielded pools
     pub shielded: u64,
+    /// All value in the transparent pool
     pub transparent: u64,
+    pub pools: PoolBalances,
+    /// Height up to which every block has been scanned
+    pub fully_scanned_height: Option<u32>,
+    /// Chain tip height known to the wallet
+    pub chain_tip_height: Option<u32>,
+}
+
+impl From<&BalanceBreakdown> for BalanceInfo {
+    fn from(breakdown: &BalanceBreakdown) -> Self {
+        Self {
+            total: breakdown.total(),
+            spendable: breakdown.spendable(),
+            shielded: breakdown.shielded_total(),
+            transparent: breakdown.transparent.total(),
+            pools: PoolBalances {
+                transparent: PoolBalanceInfo::from(&breakdown.transparent),
+                sapling: PoolBalanceInfo::from(&breakdown.sapling),
+                orchard: PoolBalanceInfo::from(&breakdown.orchard),
+            },
+            fully_scanned_height: breakdown.fully_scanned_height,
+            chain_tip_height: breakdown.chain_tip_height,
+        }
+    }
 }
 
 /// Sync result
@@ -31,85 +102,171 @@ pub struct SyncResult {
     pub balance: BalanceInfo,
 }
 
-/// Stored wallet configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct StoredWalletConfig {
-    seed: String,
+/// Get path to seed storage file
+fn get_seed_path() -> Result<std::path::PathBuf, IkkiError> {
+    Ok(default_data_dir().join(WALLET_CONFIG_FILE))
+}
+
+/// Wallet config for a seed and birthday, keeping the stored confirmation,
+/// network and password settings
+fn wallet_config_for(
+    seed: &str,
     birthday_height: Option<u64>,
+) -> Result<StoredWalletConfig, IkkiError> {
+    let stored = load_wallet_config()?;
+    Ok(StoredWalletConfig {
+        seed: seed.to_string(),
+        encrypted_seed: None,
+        birthday_height,
+        confirmations: stored
+            .as_ref()
+            .map(|config| config.confirmations)
+            .unwrap_or_default(),
+        network: stored.as_ref().and_then(|config| config.network),
+        lightwalletd_url: stored.as_ref().and_then(|config| config.lightwalletd_url.clone()),
+        viewing_key: None,
+        wrapped_db_key: stored
+            .as_ref()
+            .and_then(|config| config.wrapped_db_key.clone()),
+        auto_lock_minutes: stored
+            .map(|config| config.auto_lock_minutes)
+            .unwrap_or(DEFAULT_AUTO_LOCK_MINUTES),
+    })
 }
 
-/// Get wallet data directory path
-fn get_data_dir() -> Result<std::path::PathBuf, String> {
-    let data_dir = dirs::home_dir()
-        .ok_or("Could not find home directory")?
-        .join(".ikki");
-    Ok(data_dir)
+/// Wallet database key for a wallet being created or imported
+///
+/// Existing encrypted databases need their password. Otherwise a password
+/// sets up encryption: the sealed key is persisted right away, and the
+/// databases are encrypted when the wallet opens them. Either way the seed
+/// phrase is sealed under the key before the config is written.
+fn new_wallet_db_key(
+    stored: &mut StoredWalletConfig,
+    password: Option<&str>,
+) -> Result<Option<DbKey>, IkkiError> {
+    let data_dir = default_data_dir();
+    let has_databases = ["wallet.db", "address_book.db"]
+        .iter()
+        .any(|file| data_dir.join(file).exists());
+    if stored.has_password() && has_databases {
+        let key = stored
+            .db_key(password)
+            .context("Failed to unlock wallet")?;
+        if let Some(key) = &key {
+            stored.seal_seed(key).context("Failed to encrypt seed")?;
+        }
+        return Ok(key);
+    }
+
+    stored.wrapped_db_key = None;
+    let Some(password) = password else {
+        return Ok(None);
+    };
+    let key = stored
+        .set_password(password, None)
+        .context("Failed to set password")?;
+    write_wallet_config(stored)?;
+    Ok(Some(key))
 }
 
-/// Get path to seed storage file
-fn get_seed_path() -> Result<std::path::PathBuf, String> {
-    Ok(get_data_dir()?.join("wallet_config.json"))
+/// Confirmation settings from the stored config, or the defaults if there is none
+fn stored_confirmation_settings() -> Result<ConfirmationSettings, IkkiError> {
+    Ok(load_wallet_config()?
+        .map(|config| config.confirmations)
+        .unwrap_or_default())
 }
 
-/// Store wallet config (seed + birthday) to file
-fn store_wallet_config(seed: &str, birthday_height: Option<u64>) -> Result<(), String> {
-    let config = StoredWalletConfig {
-        seed: seed.to_string(),
-        birthday_height,
-    };
-    let json = serde_json::to_string_pretty(&config)
-        .map_err(|e| format!("Failed to serialize config: {e}"))?;
+/// Write the wallet config file
+fn write_wallet_config(config: &StoredWalletConfig) -> Result<(), IkkiError> {
+    config
+        .save(&get_seed_path()?)
+        .context("Failed to write config")
+}
 
+/// Load wallet config from file
+fn load_wallet_config() -> Result<Option<StoredWalletConfig>, IkkiError> {
+    StoredWalletConfig::load(&get_seed_path()?).context("Failed to load config")
+}
+
+/// Delete wallet config file
+fn delete_wallet_config() -> Result<(), IkkiError> {
     let path = get_seed_path()?;
-    let mut file =
-        std::fs::File::create(&path).map_err(|e| format!("Failed to create config file: {e}"))?;
-    file.write_all(json.as_bytes())
-        .map_err(|e| format!("Failed to write config: {e}"))?;
+    if path.exists() {
+        std::fs::remove_file(&path).context("Failed to delete config")?;
+    }
+    Ok(())
+}
 
-    // Set restrictive permissions on Unix
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let permissions = std::fs::Permissions::from_mode(0o600);
-        std::fs::set_permissions(&path, permissions)
-            .map_err(|e| format!("Failed to set permissions: {e}"))?;
+/// Delete the wallet database and its companion files, keeping the stored config
+fn remove_wallet_db_files() -> Result<(), IkkiError> {
+    let data_dir = default_data_dir();
+
+    let wallet_db = data_dir.join("wallet.db");
+    if wallet_db.exists() {
+        std::fs::remove_file(&wallet_db).context("Failed to delete wallet.db")?;
+    }
+
+    // Remove any other wallet-related files
+    let files_to_remove = ["wallet.db-shm", "wallet.db-wal", "wallet_cache.db"];
+    for file in files_to_remove {
+        let path = data_dir.join(file);
+        if path.exists() {
+            let _ = std::fs::remove_file(&path);
+        }
     }
 
     Ok(())
 }
 
-/// Load wallet config from file
-fn load_wallet_config() -> Result<Option<StoredWalletConfig>, String> {
-    let path = get_seed_path()?;
-    if !path.exists() {
-        return Ok(None);
-    }
+/// Wallet database files set aside while the database is rebuilt
+const WALLET_DB_FILES: [&str; 3] = ["wallet.db", "wallet.db-wal", "wallet.db-shm"];
 
-    let mut file =
-        std::fs::File::open(&path).map_err(|e| format!("Failed to open config file: {e}"))?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)
-        .map_err(|e| format!("Failed to read config: {e}"))?;
+/// Suffix of the backup copies made by [`backup_wallet_db_files`]
+const WALLET_DB_BACKUP_SUFFIX: &str = ".rebuild-backup";
 
-    let config: StoredWalletConfig =
-        serde_json::from_str(&contents).map_err(|e| format!("Failed to parse config: {e}"))?;
+/// Move the wallet database files aside so a failed rebuild can be undone
+fn backup_wallet_db_files() -> Result<(), IkkiError> {
+    let data_dir = default_data_dir();
+    for file in WALLET_DB_FILES {
+        let path = data_dir.join(file);
+        if path.exists() {
+            std::fs::rename(&path, data_dir.join(format!("{file}{WALLET_DB_BACKUP_SUFFIX}")))
+                .context("Failed to back up wallet database")?;
+        }
+    }
+    Ok(())
+}
 
-    Ok(Some(config))
+/// Replace the wallet database files with the backups made before a rebuild
+fn restore_wallet_db_files() -> Result<(), IkkiError> {
+    remove_wallet_db_files()?;
+    let data_dir = default_data_dir();
+    for file in WALLET_DB_FILES {
+        let backup = data_dir.join(format!("{file}{WALLET_DB_BACKUP_SUFFIX}"));
+        if backup.exists() {
+            std::fs::rename(&backup, data_dir.join(file))
+                .context("Failed to restore wallet database")?;
+        }
+    }
+    Ok(())
 }
 
-/// Delete wallet config file
-fn delete_wallet_config() -> Result<(), String> {
-    let path = get_seed_path()?;
-    if path.exists() {
-        std::fs::remove_file(&path).map_err(|e| format!("Failed to delete config: {e}"))?;
+/// Delete the backups made before a rebuild once it has succeeded
+fn remove_wallet_db_backup() -> Result<(), IkkiError> {
+    let data_dir = default_data_dir();
+    for file in WALLET_DB_FILES {
+        let backup = data_dir.join(format!("{file}{WALLET_DB_BACKUP_SUFFIX}"));
+        if backup.exists() {
+            let _ = std::fs::remove_file(&backup);
+        }
     }
     Ok(())
 }
 
 /// Check if a wallet exists (both database and config)
 #[tauri::command]
-pub async fn check_wallet_exists() -> Result<bool, String> {
-    let data_dir = get_data_dir()?;
+pub async fn check_wallet_exists() -> Result<bool, IkkiError> {
+    let data_dir = default_data_dir();
     let wallet_db = data_dir.join("wallet.db");
     let config_exists = get_seed_path()?.exists();
 
@@ -119,7 +276,7 @@ pub async fn check_wallet_exists() -> Result<bool, String> {
 
 /// Generate a new seed phrase
 #[tauri::command]
-pub async fn generate_seed() -> Result<String, String> {
+pub async fn generate_seed() -> Result<String, IkkiError> {
     use bip0039::{Count, English, Mnemonic};
 
     let mnemonic = Mnemonic::<English>::generate(Count::Words24);
@@ -128,28 +285,20 @@ pub async fn generate_seed() -> Result<String, String> {
 
 /// Delete all wallet data (reset wallet)
 #[tauri::command]
-pub async fn reset_wallet(state: State<'_, AppState>) -> Result<(), String> {
+pub async fn reset_wallet(state: State<'_, AppState>) -> Result<(), IkkiError> {
     // Clear wallet from state first
     {
         let mut wallet_lock = state.wallet.lock().await;
         *wallet_lock = None;
     }
 
-    let data_dir = get_data_dir()?;
-
     // Remove wallet database
-    let wallet_db = data_dir.join("wallet.db");
-    if wallet_db.exists() {
-        std::fs::remove_file(&wallet_db).map_err(|e| format!("Failed to delete wallet.db: {e}"))?;
-    }
+    remove_wallet_db_files()?;
 
-    // Remove any other wallet-related files
-    let files_to_remove = ["wallet.db-shm", "wallet.db-wal", "wallet_cache.db"];
-    for file in files_to_remove {
-        let path = data_dir.join(file);
-        if path.exists() {
-            let _ = std::fs::remove_file(&path);
-        }
+    // Remove address book
+    let address_book = default_data_dir().join("address_book.db");
+    if address_book.exists() {
+        let _ = std::fs::remove_file(&address_book);
     }
 
     // Remove stored config (seed)
@@ -164,34 +313,38 @@ pub async fn init_wallet(
     state: State<'_, AppState>,
     seed: String,
     birthday_height: Option<u64>,
-) -> Result<WalletInfo, String> {
-    let config = ZcashConfig::from_seed_with_birthday(&seed, birthday_height)
-        .map_err(|e| format!("Invalid seed phrase: {e}"))?;
+    password: Option<String>,
+) -> Result<WalletInfo, IkkiError> {
+    let mut stored_config = wallet_config_for(&seed, birthday_height)?;
+    let mut config = stored_config
+        .to_config(default_data_dir(), None)
+        .context("Invalid seed phrase")?;
+    config.db_key = new_wallet_db_key(&mut stored_config, password.as_deref())?;
 
     let mut wallet = IkkiWallet::new(config)
         .await
-        .map_err(|e| format!("Failed to create wallet: {e}"))?;
+        .context("Failed to create wallet")?;
 
     wallet
         .init_account()
         .await
-        .map_err(|e| format!("Failed to initialize account: {e}"))?;
+        .context("Failed to initialize account")?;
 
     let address = wallet
         .get_address()
-        .map_err(|e| format!("Failed to get address: {e}"))?;
+        .context("Failed to get address")?;
 
     let breakdown = wallet
         .get_balance_breakdown()
-        .map_err(|e| format!("Failed to get balance: {e}"))?;
+        .context("Failed to get balance")?;
 
     let block_height = wallet
         .get_block_height()
         .await
-        .map_err(|e| format!("Failed to get block height: {e}"))?;
+        .context("Failed to get block height")?;
 
     // Store seed for persistence
-    store_wallet_config(&seed, birthday_height)?;
+    write_wallet_config(&stored_config)?;
 
     // Store wallet in state
     let mut wallet_lock = state.wallet.lock().await;
@@ -199,11 +352,7 @@ pub async fn init_wallet(
 
     Ok(WalletInfo {
         address,
-        balance: BalanceInfo {
-            total: breakdown.sapling + breakdown.orchard + breakdown.transparent,
-            shielded: breakdown.sapling + breakdown.orchard,
-            transparent: breakdown.transparent,
-        },
+        balance: BalanceInfo::from(&breakdown),
         block_height,
     })
 }
@@ -214,35 +363,39 @@ pub async fn load_wallet(
     state: State<'_, AppState>,
     seed: String,
     birthday_height: Option<u64>,
-) -> Result<WalletInfo, String> {
-    let config = ZcashConfig::from_seed_with_birthday(&seed, birthday_height)
-        .map_err(|e| format!("Invalid seed phrase: {e}"))?;
+    password: Option<String>,
+) -> Result<WalletInfo, IkkiError> {
+    let mut stored_config = wallet_config_for(&seed, birthday_height)?;
+    let mut config = stored_config
+        .to_config(default_data_dir(), None)
+        .context("Invalid seed phrase")?;
+    config.db_key = new_wallet_db_key(&mut stored_config, password.as_deref())?;
 
     let mut wallet = IkkiWallet::new(config)
         .await
-        .map_err(|e| format!("Failed to load wallet: {e}"))?;
+        .context("Failed to load wallet")?;
 
     // Initialize account to ensure we have an address
     wallet
         .init_account()
         .await
-        .map_err(|e| format!("Failed to initialize account: {e}"))?;
+        .context("Failed to initialize account")?;
 
     let address = wallet
         .get_address()
-        .map_err(|e| format!("Failed to get address: {e}"))?;
+        .context("Failed to get address")?;
 
     let breakdown = wallet
         .get_balance_breakdown()
-        .map_err(|e| format!("Failed to get balance: {e}"))?;
+        .context("Failed to get balance")?;
 
     let block_height = wallet
         .get_block_height()
         .await
-        .map_err(|e| format!("Failed to get block height: {e}"))?;
+        .context("Failed to get block height")?;
 
     // Store seed for persistence
-    store_wallet_config(&seed, birthday_height)?;
+    write_wallet_config(&stored_config)?;
 
     // Store wallet in state
     let mut wallet_lock = state.wallet.lock().await;
@@ -250,18 +403,85 @@ pub async fn load_wallet(
 
     Ok(WalletInfo {
         address,
-        balance: BalanceInfo {
-            total: breakdown.sapling + breakdown.orchard + breakdown.transparent,
-            shielded: breakdown.sapling + breakdown.orchard,
-            transparent: breakdown.transparent,
-        },
+        balance: BalanceInfo::from(&breakdown),
+        block_height,
+    })
+}
+
+/// Load a watch-only wallet from a viewing key exported by an offline wallet
+///
+/// The wallet can create PCZTs but not sign them.
+#[tauri::command]
+pub async fn load_watch_only_wallet(
+    state: State<'_, AppState>,
+    viewing_key: WatchOnlyKey,
+    birthday_height: Option<u64>,
+    password: Option<String>,
+) -> Result<WalletInfo, IkkiError> {
+    let mut stored_config = StoredWalletConfig {
+        viewing_key: Some(viewing_key),
+        ..wallet_config_for("", birthday_height)?
+    };
+    let mut config = stored_config
+        .to_config(default_data_dir(), None)
+        .context("Invalid viewing key")?;
+    config.db_key = new_wallet_db_key(&mut stored_config, password.as_deref())?;
+
+    let mut wallet = IkkiWallet::new(config)
+        .await
+        .context("Failed to load wallet")?;
+
+    wallet
+        .init_account()
+        .await
+        .context("Failed to initialize account")?;
+
+    let address = wallet
+        .get_address()
+        .context("Failed to get address")?;
+
+    let breakdown = wallet
+        .get_balance_breakdown()
+        .context("Failed to get balance")?;
+
+    let block_height = wallet
+        .get_block_height()
+        .await
+        .context("Failed to get block height")?;
+
+    // Store viewing key for persistence
+    write_wallet_config(&stored_config)?;
+
+    // Store wallet in state
+    let mut wallet_lock = state.wallet.lock().await;
+    *wallet_lock = Some(wallet);
+
+    Ok(WalletInfo {
+        address,
+        balance: BalanceInfo::from(&breakdown),
         block_height,
     })
 }
 
+/// Export the viewing key for setting up a watch-only copy of the wallet
+#[tauri::command]
+pub async fn export_viewing_key(state: State<'_, AppState>) -> Result<WatchOnlyKey, IkkiError> {
+    let wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_ref().ok_or(IkkiError::NotInitialized)?;
+
+    wallet
+        .export_viewing_key()
+        .context("Failed to export viewing key")
+}
+
 /// Auto-load wallet from stored config (called on app startup)
+///
+/// Wallets with a password fail with `WalletLocked` until it is given.
 #[tauri::command]
-pub async fn auto_load_wallet(state: State<'_, AppState>) -> Result<Option<WalletInfo>, String> {
+pub async fn auto_load_wallet(
+    state: State<'_, AppState>,
+    password: Option<String>,
+) -> Result<Option<WalletInfo>, IkkiError> {
     // Check if config exists
     let stored_config = match load_wallet_config()? {
         Some(c) => c,
@@ -269,33 +489,37 @@ pub async fn auto_load_wallet(state: State<'_, AppState>) -> Result<Option<Walle
     };
 
     // Check if wallet database exists
-    let data_dir = get_data_dir()?;
+    let data_dir = default_data_dir();
     let wallet_db = data_dir.join("wallet.db");
     if !wallet_db.exists() {
         return Ok(None);
     }
 
     // Load wallet with stored config
-    let config =
-        ZcashConfig::from_seed_with_birthday(&stored_config.seed, stored_config.birthday_height)
-            .map_err(|e| format!("Invalid stored seed: {e}"))?;
+    let db_key = stored_config
+        .db_key(password.as_deref())
+        .context("Failed to unlock wallet")?;
+    let mut config = stored_config
+        .to_config(data_dir, db_key.as_ref())
+        .context("Invalid stored seed")?;
+    config.db_key = db_key;
 
     let mut wallet = IkkiWallet::new(config)
         .await
-        .map_err(|e| format!("Failed to load wallet: {e}"))?;
+        .context("Failed to load wallet")?;
 
     let address = wallet
         .get_address()
-        .map_err(|e| format!("Failed to get address: {e}"))?;
+        .context("Failed to get address")?;
 
     let breakdown = wallet
         .get_balance_breakdown()
-        .map_err(|e| format!("Failed to get balance: {e}"))?;
+        .context("Failed to get balance")?;
 
     let block_height = wallet
         .get_block_height()
         .await
-        .map_err(|e| format!("Failed to get block height: {e}"))?;
+        .context("Failed to get block height")?;
 
     // Store wallet in state
     let mut wallet_lock = state.wallet.lock().await;
@@ -303,91 +527,521 @@ pub async fn auto_load_wallet(state: State<'_, AppState>) -> Result<Option<Walle
 
     Ok(Some(WalletInfo {
         address,
-        balance: BalanceInfo {
-            total: breakdown.sapling + breakdown.orchard + breakdown.transparent,
-            shielded: breakdown.sapling + breakdown.orchard,
-            transparent: breakdown.transparent,
-        },
+        balance: BalanceInfo::from(&breakdown),
         block_height,
     }))
 }
 
-/// Get current balance
+/// Set or change the unlock password that encrypts the wallet databases
+///
+/// The databases are encrypted with a random key that the config keeps sealed
+/// under the password, so changing the password needs the current one and
+/// only rewrites the config. Setting the first password saves the config
+/// before the wallet is reopened, which encrypts the plaintext databases; if
+/// that is interrupted, the next load finishes it.
+#[tauri::command]
+pub async fn set_wallet_password(
+    state: State<'_, AppState>,
+    current_password: Option<String>,
+    new_password: String,
+) -> Result<(), IkkiError> {
+    if state.sync_state.is_syncing() {
+        return Err("Cannot change the password while syncing".into());
+    }
+
+    let mut wallet_lock = state.wallet.lock().await;
+    if wallet_lock.is_none() {
+        return Err(IkkiError::NotInitialized);
+    }
+    let mut stored_config = load_wallet_config()?.ok_or(IkkiError::NotInitialized)?;
+
+    let old_key = stored_config
+        .db_key(current_password.as_deref())
+        .context("Failed to unlock wallet")?;
+    let changing = old_key.is_some();
+    let key = stored_config
+        .set_password(&new_password, old_key)
+        .context("Failed to set password")?;
+    write_wallet_config(&stored_config)?;
+    if changing {
+        info!("Wallet password changed");
+        return Ok(());
+    }
+
+    let mut config = stored_config
+        .to_config(default_data_dir(), Some(&key))
+        .context("Invalid stored seed")?;
+    config.db_key = Some(key);
+
+    // Close the databases so they can be encrypted, keeping the connection.
+    // A wallet without a password cannot be locked, so there is no lock
+    // state to restore.
+    let Some(wallet) = wallet_lock.take() else {
+        return Err(IkkiError::NotInitialized);
+    };
+    let client = wallet.into_client();
+
+    let wallet = match IkkiWallet::with_client(config.clone(), client.clone()) {
+        Ok(wallet) => wallet,
+        Err(e) => {
+            // wallet.db is encrypted first, so while it is still plaintext
+            // nothing was encrypted and the wallet can go back to no password
+            if is_plaintext_db(&config.wallet_db_path()).unwrap_or(false) {
+                if let Some(key) = config.db_key.take() {
+                    stored_config
+                        .remove_password(&key)
+                        .context("Failed to remove password")?;
+                }
+                write_wallet_config(&stored_config)?;
+                let wallet =
+                    IkkiWallet::with_client(config, client).context("Failed to load wallet")?;
+                *wallet_lock = Some(wallet);
+            }
+            return Err(e).context("Failed to encrypt wallet database");
+        }
+    };
+    *wallet_lock = Some(wallet);
+    info!("Wallet password set");
+
+    Ok(())
+}
+
+/// Lock state of the wallet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockStatus {
+    /// Whether spending is disabled until the wallet is unlocked
+    pub locked: bool,
+    /// Whether unlocking needs the wallet password
+    pub has_password: bool,
+    /// Idle minutes after which the wallet locks, 0 if it never does
+    pub auto_lock_minutes: u32,
+}
+
+fn lock_status(wallet: &IkkiWallet) -> LockStatus {
+    LockStatus {
+        locked: wallet.is_locked(),
+        has_password: wallet.db_key().is_some(),
+        auto_lock_minutes: wallet.auto_lock_minutes(),
+    }
+}
+
+/// Get the lock state of the wallet
 #[tauri::command]
-pub async fn get_balance(state: State<'_, AppState>) -> Result<BalanceInfo, String> {
+pub async fn get_lock_status(state: State<'_, AppState>) -> Result<LockStatus, IkkiError> {
     let wallet_lock = state.wallet.lock().await;
-    let wallet = wallet_lock.as_ref().ok_or("Wallet not initialized")?;
+    let wallet = wallet_lock.as_ref().ok_or(IkkiError::NotInitialized)?;
+    Ok(lock_status(wallet))
+}
+
+/// Lock the wallet, zeroizing its seed
+///
+/// Sync and balances keep working; sends fail with `WalletLocked` until the
+/// wallet is unlocked. Wallets without a password cannot be locked.
+#[tauri::command]
+pub async fn lock_wallet(state: State<'_, AppState>) -> Result<LockStatus, IkkiError> {
+    let mut wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_mut().ok_or(IkkiError::NotInitialized)?;
+
+    wallet.lock().context("Failed to lock wallet")?;
+    Ok(lock_status(wallet))
+}
+
+/// Unlock the wallet, unsealing its seed from the stored config
+///
+/// The password unseals the database key, which unseals the seed phrase.
+#[tauri::command]
+pub async fn unlock_wallet(
+    state: State<'_, AppState>,
+    password: Option<String>,
+) -> Result<LockStatus, IkkiError> {
+    let mut wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_mut().ok_or(IkkiError::NotInitialized)?;
+    if !wallet.is_locked() {
+        return Ok(lock_status(wallet));
+    }
+
+    let stored_config = load_wallet_config()?.ok_or(IkkiError::NotInitialized)?;
+    let key = stored_config
+        .db_key(password.as_deref())
+        .context("Failed to unlock wallet")?;
+    let seed_phrase = stored_config
+        .seed_phrase(key.as_ref())
+        .context("Failed to unlock wallet")?;
+    wallet
+        .unlock(&seed_phrase)
+        .context("Failed to unlock wallet")?;
+    Ok(lock_status(wallet))
+}
+
+/// Change the idle minutes after which the wallet locks, 0 to never lock
+#[tauri::command]
+pub async fn set_auto_lock_timeout(
+    state: State<'_, AppState>,
+    minutes: u32,
+) -> Result<LockStatus, IkkiError> {
+    let mut wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_mut().ok_or(IkkiError::NotInitialized)?;
+
+    wallet.set_auto_lock_minutes(minutes);
+
+    let mut stored_config = load_wallet_config()?.ok_or(IkkiError::NotInitialized)?;
+    stored_config.auto_lock_minutes = minutes;
+    write_wallet_config(&stored_config)?;
+
+    Ok(lock_status(wallet))
+}
+
+/// Start the backend task that locks the wallet once it has been idle past
+/// its auto-lock timeout, emitting `wallet-locked`
+pub fn spawn_auto_lock(app: AppHandle) {
+    let wallet = app.state::<AppState>().wallet.clone();
+
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(AUTO_LOCK_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            let mut wallet_lock = wallet.lock().await;
+            let Some(wallet) = wallet_lock.as_mut() else {
+                continue;
+            };
+            match wallet.lock_if_idle() {
+                Ok(true) => {
+                    info!("Wallet auto-locked after {} idle minutes", wallet.auto_lock_minutes());
+                    let _ = app.emit("wallet-locked", ());
+                }
+                Ok(false) => {}
+                Err(e) => warn!("Failed to auto-lock wallet: {e:#}"),
+            }
+        }
+    });
+}
+
+/// Get the complete balance: per-pool spendability, totals and scan heights
+#[tauri::command]
+pub async fn get_balance(state: State<'_, AppState>) -> Result<BalanceInfo, IkkiError> {
+    let wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_ref().ok_or(IkkiError::NotInitialized)?;
 
     let breakdown = wallet
         .get_balance_breakdown()
-        .map_err(|e| format!("Failed to get balance: {e}"))?;
+        .context("Failed to get balance")?;
+
+    Ok(BalanceInfo::from(&breakdown))
+}
+
+/// Confirmation depths used for balances and spends
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmationSettingsInfo {
+    pub trusted: u32,
+    pub untrusted: u32,
+}
+
+/// Get the confirmation settings
+#[tauri::command]
+pub async fn get_confirmation_settings(
+    state: State<'_, AppState>,
+) -> Result<ConfirmationSettingsInfo, IkkiError> {
+    let wallet_lock = state.wallet.lock().await;
+    let settings = match wallet_lock.as_ref() {
+        Some(wallet) => wallet.confirmation_settings(),
+        None => stored_confirmation_settings()?,
+    };
 
-    Ok(BalanceInfo {
-        total: breakdown.sapling + breakdown.orchard + breakdown.transparent,
-        shielded: breakdown.sapling + breakdown.orchard,
-        transparent: breakdown.transparent,
+    Ok(ConfirmationSettingsInfo {
+        trusted: settings.trusted,
+        untrusted: settings.untrusted,
     })
 }
 
+/// Change and persist the confirmation settings, returning the resulting balance
+#[tauri::command]
+pub async fn set_confirmation_settings(
+    state: State<'_, AppState>,
+    trusted: u32,
+    untrusted: u32,
+) -> Result<BalanceInfo, IkkiError> {
+    let settings = ConfirmationSettings { trusted, untrusted };
+
+    let mut wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_mut().ok_or(IkkiError::NotInitialized)?;
+
+    wallet
+        .set_confirmation_settings(settings)
+        .context("Invalid confirmation settings")?;
+
+    let mut stored_config = load_wallet_config()?.ok_or(IkkiError::NotInitialized)?;
+    stored_config.confirmations = settings;
+    write_wallet_config(&stored_config)?;
+
+    let breakdown = wallet
+        .get_balance_breakdown()
+        .context("Failed to get balance")?;
+    Ok(BalanceInfo::from(&breakdown))
+}
+
 /// Get wallet address
 #[tauri::command]
-pub async fn get_address(state: State<'_, AppState>) -> Result<String, String> {
+pub async fn get_address(state: State<'_, AppState>) -> Result<String, IkkiError> {
     let wallet_lock = state.wallet.lock().await;
-    let wallet = wallet_lock.as_ref().ok_or("Wallet not initialized")?;
+    let wallet = wallet_lock.as_ref().ok_or(IkkiError::NotInitialized)?;
 
     wallet
         .get_address()
-        .map_err(|e| format!("Failed to get address: {e}"))
+        .context("Failed to get address")
+}
+
+/// Address type requested from `get_new_address`
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AddressKind {
+    /// Unified address with Orchard and Sapling receivers
+    #[default]
+    Shielded,
+    /// Unified address with Orchard, Sapling and transparent receivers
+    Full,
+    /// Unified address with only an Orchard receiver
+    Orchard,
+    /// Legacy Sapling z-address
+    Sapling,
+    /// Transparent address
+    Transparent,
+}
+
+impl From<AddressKind> for AddressType {
+    fn from(kind: AddressKind) -> Self {
+        match kind {
+            AddressKind::Shielded => AddressType::Shielded,
+            AddressKind::Full => AddressType::Full,
+            AddressKind::Orchard => AddressType::Orchard,
+            AddressKind::Sapling => AddressType::Sapling,
+            AddressKind::Transparent => AddressType::Transparent,
+        }
+    }
 }
 
-/// Generate a new diversified address
+impl From<AddressType> for AddressKind {
+    fn from(address_type: AddressType) -> Self {
+        match address_type {
+            AddressType::Shielded => AddressKind::Shielded,
+            AddressType::Full => AddressKind::Full,
+            AddressType::Orchard => AddressKind::Orchard,
+            AddressType::Sapling => AddressKind::Sapling,
+            AddressType::Transparent => AddressKind::Transparent,
+        }
+    }
+}
+
+/// Newly generated address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewAddressInfo {
+    /// Address to hand out, encoded for the requested type
+    pub address: String,
+    pub address_type: AddressKind,
+    pub unified_address: String,
+    pub diversifier_index: String,
+    pub orchard: Option<String>,
+    pub sapling: Option<String>,
+    pub transparent: Option<String>,
+}
+
+/// Generate a new diversified address of the requested type, optionally labelled
 #[tauri::command]
-pub async fn get_new_address(state: State<'_, AppState>) -> Result<String, String> {
+pub async fn get_new_address(
+    state: State<'_, AppState>,
+    address_type: Option<AddressKind>,
+    label: Option<String>,
+) -> Result<NewAddressInfo, IkkiError> {
     let mut wallet_lock = state.wallet.lock().await;
-    let wallet = wallet_lock.as_mut().ok_or("Wallet not initialized")?;
+    let wallet = wallet_lock.as_mut().ok_or(IkkiError::NotInitialized)?;
+
+    let new_address = wallet
+        .get_new_address(address_type.unwrap_or_default().into(), label.as_deref())
+        .context("Failed to generate new address")?;
+
+    Ok(NewAddressInfo {
+        address: new_address.address,
+        address_type: new_address.address_type.into(),
+        unified_address: new_address.unified_address,
+        diversifier_index: new_address.diversifier_index.to_string(),
+        orchard: new_address.orchard,
+        sapling: new_address.sapling,
+        transparent: new_address.transparent,
+    })
+}
 
-    wallet
-        .get_new_address()
-        .map_err(|e| format!("Failed to generate new address: {e}"))
+/// Receivers contained in an address
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReceiverInfo {
+    pub orchard: bool,
+    pub sapling: bool,
+    pub transparent: bool,
 }
 
-/// Get all wallet addresses
+/// One of the wallet's receiving addresses
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressInfo {
+    pub address: String,
+    /// Diversifier index as a decimal string (it can exceed JavaScript's safe integer range)
+    pub diversifier_index: String,
+    pub receivers: ReceiverInfo,
+    pub created_at: Option<u64>,
+    pub exposed_at_height: Option<u32>,
+    pub label: Option<String>,
+    pub address_type: Option<AddressKind>,
+    pub issued_address: Option<String>,
+    pub received_total: u64,
+}
+
+/// Get all wallet addresses with their metadata
 #[tauri::command]
-pub async fn get_all_addresses(state: State<'_, AppState>) -> Result<Vec<String>, String> {
+pub async fn get_all_addresses(state: State<'_, AppState>) -> Result<Vec<AddressInfo>, IkkiError> {
     let wallet_lock = state.wallet.lock().await;
-    let wallet = wallet_lock.as_ref().ok_or("Wallet not initialized")?;
+    let wallet = wallet_lock.as_ref().ok_or(IkkiError::NotInitialized)?;
 
-    wallet
+    let records = wallet
         .get_all_addresses()
-        .map_err(|e| format!("Failed to get addresses: {e}"))
+        .context("Failed to get addresses")?;
+
+    Ok(records
+        .into_iter()
+        .map(|r| AddressInfo {
+            address: r.address,
+            diversifier_index: r.diversifier_index.to_string(),
+            receivers: ReceiverInfo {
+                orchard: r.receivers.orchard,
+                sapling: r.receivers.sapling,
+                transparent: r.receivers.transparent,
+            },
+            created_at: r.created_at,
+            exposed_at_height: r.exposed_at_height,
+            label: r.label,
+            address_type: r.address_type.map(AddressKind::from),
+            issued_address: r.issued_address,
+            received_total: r.received_total,
+        })
+        .collect())
+}
+
+/// Set or clear the label of one of the wallet's addresses
+#[tauri::command]
+pub async fn set_address_label(
+    state: State<'_, AppState>,
+    address: String,
+    label: Option<String>,
+) -> Result<(), IkkiError> {
+    let wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_ref().ok_or(IkkiError::NotInitialized)?;
+
+    wallet
+        .set_address_label(&address, label.as_deref())
+        .context("Failed to set address label")
+}
+
+/// Encoding of an inspected address
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AddressEncodingInfo {
+    Unified,
+    Sapling,
+    TransparentP2pkh,
+    TransparentP2sh,
+    Tex,
+    Sprout,
+}
+
+impl From<AddressEncoding> for AddressEncodingInfo {
+    fn from(encoding: AddressEncoding) -> Self {
+        match encoding {
+            AddressEncoding::Unified => AddressEncodingInfo::Unified,
+            AddressEncoding::Sapling => AddressEncodingInfo::Sapling,
+            AddressEncoding::TransparentP2pkh => AddressEncodingInfo::TransparentP2pkh,
+            AddressEncoding::TransparentP2sh => AddressEncodingInfo::TransparentP2sh,
+            AddressEncoding::Tex => AddressEncodingInfo::Tex,
+            AddressEncoding::Sprout => AddressEncodingInfo::Sprout,
+        }
+    }
+}
+
+/// Result of `inspect_address`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressInspectionInfo {
+    pub is_valid: bool,
+    /// "mainnet", "testnet" or "regtest"
+    pub network: Option<String>,
+    pub encoding: Option<AddressEncodingInfo>,
+    /// Receiver names: "orchard", "sapling", "p2pkh", "p2sh" or "unknown:<typecode>"
+    pub receivers: Vec<String>,
+    pub is_mine: bool,
+    pub warnings: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// Validate an address and describe its network, kind and receivers
+#[tauri::command]
+pub async fn inspect_address(
+    state: State<'_, AppState>,
+    address: String,
+) -> Result<AddressInspectionInfo, IkkiError> {
+    let wallet_lock = state.wallet.lock().await;
+    let wallet = wallet_lock.as_ref().ok_or(IkkiError::NotInitialized)?;
+
+    let inspection = wallet
+        .inspect_address(&address)
+        .context("Failed to inspect address")?;
+
+    Ok(AddressInspectionInfo {
+        is_valid: inspection.is_valid,
+        network: inspection.network.map(|network| {
+            match network {
+                NetworkType::Main => "mainnet",
+                NetworkType::Test => "testnet",
+                NetworkType::Regtest => "regtest",
+            }
+            .to_string()
+        }),
+        encoding: inspection.encoding.map(AddressEncodingInfo::from),
+        receivers: inspection
+            .receivers
+            .iter()
+            .map(|receiver| match receiver {
+                ReceiverKind::Orchard => "orchard".to_string(),
+                ReceiverKind::Sapling => "sapling".to_string(),
+                ReceiverKind::TransparentP2pkh => "p2pkh".to_string(),
+                ReceiverKind::TransparentP2sh => "p2sh".to_string(),
+                ReceiverKind::Unknown(typecode) => format!("unknown:{typecode}"),
+            })
+            .collect(),
+        is_mine: inspection.is_mine,
+        warnings: inspection.warnings,
+        error: inspection.error,
+    })
 }
 
 /// Sync wallet with blockchain (blocking - kept for compatibility)
 #[tauri::command]
-pub async fn sync_wallet(state: State<'_, AppState>) -> Result<SyncResult, String> {
+pub async fn sync_wallet(app: AppHandle, state: State<'_, AppState>) -> Result<SyncResult, IkkiError> {
     let mut wallet_lock = state.wallet.lock().await;
-    let wallet = wallet_lock.as_mut().ok_or("Wallet not initialized")?;
+    let wallet = wallet_lock.as_mut().ok_or(IkkiError::NotInitialized)?;
 
-    wallet
+    let reorg = wallet
         .sync()
         .await
-        .map_err(|e| format!("Sync failed: {e}"))?;
+        .context("Sync failed")?;
+    if let Some(reorg) = reorg {
+        report_reorg(&app, &state.sync_state, reorg);
+    }
 
     let breakdown = wallet
         .get_balance_breakdown()
-        .map_err(|e| format!("Failed to get balance: {e}"))?;
+        .context("Failed to get balance")?;
     let block_height = wallet
         .get_block_height()
         .await
-        .map_err(|e| format!("Failed to get block height: {e}"))?;
+        .context("Failed to get block height")?;
 
     Ok(SyncResult {
         block_height,
-        balance: BalanceInfo {
-            total: breakdown.sapling + breakdown.orchard + breakdown.transparent,
-            shielded: breakdown.sapling + breakdown.orchard,
-            transparent: breakdown.transparent,
-        },
+        balance: BalanceInfo::from(&breakdown),
     })
 }
 
@@ -401,6 +1055,24 @@ pub struct SyncProgress {
     pub status: String,
 }
 
+/// Chain reorganization event payload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainReorgInfo {
+    pub old_height: u64,
+    pub new_height: u64,
+    pub affected_txids: Vec<String>,
+}
+
+impl From<ChainReorg> for ChainReorgInfo {
+    fn from(reorg: ChainReorg) -> Self {
+        Self {
+            old_height: reorg.old_height,
+            new_height: reorg.new_height,
+            affected_txids: reorg.affected_txids,
+        }
+    }
+}
+
 /// Sync status response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SyncStatus {
@@ -409,11 +1081,19 @@ pub struct SyncStatus {
     pub current_block: u64,
     pub target_block: u64,
     pub percentage: f64,
+    pub last_reorg: Option<ChainReorgInfo>,
+}
+
+/// Remember a detected reorg and notify the frontend so it refreshes history
+fn report_reorg(app: &AppHandle, sync_state: &crate::state::SyncState, reorg: ChainReorg) {
+    sync_state.record_reorg(reorg.clone());
+    let emit_result = app.emit("chain-reorg", ChainReorgInfo::from(reorg));
+    info!("chain-reorg emit result: {:?}", emit_result);
 }
 
 /// Get current sync status
 #[tauri::command]
-pub async fn get_sync_status(state: State<'_, AppState>) -> Result<SyncStatus, String> {
+pub async fn get_sync_status(state: State<'_, AppState>) -> Result<SyncStatus, IkkiError> {
     let sync_state = &state.sync_state;
     let (current, target) = sync_state.get_progress();
     let percentage = if target > 0 {
@@ -428,6 +1108,7 @@ pub async fn get_sync_status(state: State<'_, AppState>) -> Result<SyncStatus, S
         current_block: current,
         target_block: target,
         percentage,
+        last_reorg: sync_state.last_reorg().map(ChainReorgInfo::from),
     })
 }
 
@@ -437,12 +1118,19 @@ pub async fn start_background_sync(
     app: AppHandle,
     state: State<'_, AppState>,
     is_first_sync: bool,
-) -> Result<(), String> {
+) -> Result<(), IkkiError> {
     // Check if already syncing
     if state.sync_state.is_syncing() {
-        return Err("Sync already in progress".to_string());
+        return Err("Sync already in progress".into());
     }
 
+    spawn_background_sync(app, &state, is_first_sync);
+
+    Ok(())
+}
+
+/// Mark sync as started and run it in a background task
+fn spawn_background_sync(app: AppHandle, state: &AppState, is_first_sync: bool) {
     // Mark sync as started
     state.sync_state.start_sync(is_first_sync);
 
@@ -474,41 +1162,191 @@ pub async fn start_background_sync(
         }
         info!("Sync task finished");
     });
-
-    Ok(())
 }
 
 /// Cancel ongoing sync
 #[tauri::command]
-pub async fn cancel_sync(state: State<'_, AppState>) -> Result<(), String> {
+pub async fn cancel_sync(state: State<'_, AppState>) -> Result<(), IkkiError> {
     state.sync_state.request_cancel();
     Ok(())
 }
 
 /// Force reset sync state (for recovery from stuck states)
 #[tauri::command]
-pub async fn reset_sync_state(state: State<'_, AppState>) -> Result<(), String> {
+pub async fn reset_sync_state(state: State<'_, AppState>) -> Result<(), IkkiError> {
     info!("Force resetting sync state");
     state.sync_state.end_sync();
     Ok(())
 }
 
+/// Rescan result
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RescanResult {
+    /// Height the wallet was rewound to (sync resumes from here)
+    pub rescan_height: u64,
+    /// Whether the wallet database was rebuilt instead of rewound
+    pub rebuilt: bool,
+}
+
+/// Rescan the wallet from an arbitrary height
+///
+/// If the height is at or above the account birthday, the wallet database is
+/// rewound in place. Otherwise, or if there is no checkpoint to rewind to, the
+/// database is rebuilt from the stored seed with the birthday moved to the
+/// height if it is earlier, and the addresses issued before are exposed again.
+/// The stored config and pending transactions are kept in both cases, and a
+/// background sync is started once the rewind is done.
+#[tauri::command]
+pub async fn rescan_from(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    height: u64,
+) -> Result<RescanResult, IkkiError> {
+    if state.sync_state.is_syncing() {
+        return Err("Sync already in progress".into());
+    }
+
+    let result = {
+        let mut wallet_lock = state.wallet.lock().await;
+        let wallet = wallet_lock.as_mut().ok_or(IkkiError::NotInitialized)?;
+
+        let birthday_height = wallet
+            .get_birthday_height()
+            .context("Failed to get wallet birthday")?;
+
+        let rewound = if height >= birthday_height {
+            match wallet.rewind_to_height(height) {
+                Ok(rescan_height) => Some(rescan_height),
+                // Without a checkpoint to truncate to, the wallet can still be
+                // rebuilt and scanned again from its birthday
+                Err(e) => {
+                    warn!(
+                        "Rewind to height {} failed, rebuilding wallet: {:#}",
+                        height, e
+                    );
+                    None
+                }
+            }
+        } else {
+            info!(
+                "Rescan height {} is below birthday {}, rebuilding wallet",
+                height, birthday_height
+            );
+            None
+        };
+
+        if let Some(rescan_height) = rewound {
+            RescanResult {
+                rescan_height,
+                rebuilt: false,
+            }
+        } else {
+            let rebuild_height = height.min(birthday_height);
+            let stored_config = load_wallet_config()?.ok_or(IkkiError::NotInitialized)?;
+            let db_key = wallet.db_key().cloned();
+            let was_locked = wallet.is_locked();
+
+            // Keep the diversifier indices of issued addresses, which only the
+            // wallet database knows for addresses recorded before they were kept
+            wallet
+                .record_address_indices()
+                .context("Failed to record issued addresses")?;
+
+            let rebuilt_config = StoredWalletConfig {
+                birthday_height: Some(rebuild_height),
+                ..stored_config.clone()
+            };
+            let mut config = rebuilt_config
+                .to_config(default_data_dir(), db_key.as_ref())
+                .context("Invalid stored seed")?;
+            config.db_key = db_key.clone();
+
+            // Close the database and set it aside until the rebuild succeeds
+            *wallet_lock = None;
+            backup_wallet_db_files()?;
+
+            let mut wallet = match rebuild_wallet(config, &rebuilt_config).await {
+                Ok(wallet) => wallet,
+                Err(e) => {
+                    warn!("Wallet rebuild failed, restoring the previous database: {}", e);
+                    restore_wallet_db_files()?;
+                    let mut config = stored_config
+                        .to_config(default_data_dir(), db_key.as_ref())
+                        .context("Invalid stored seed")?;
+                    config.db_key = db_key;
+                    match IkkiWallet::new(config).await {
+                        Ok(mut wallet) => {
+                            if was_locked {
+                                wallet.lock().context("Failed to lock wallet")?;
+                            }
+                            *wallet_lock = Some(wallet);
+                        }
+                        Err(reload) => warn!("Failed to reload the previous wallet: {reload:#}"),
+                    }
+                    return Err(e);
+                }
+            };
+            remove_wallet_db_backup()?;
+
+            // Rebuilding from the stored seed must not unlock a locked wallet
+            if was_locked {
+                wallet.lock().context("Failed to lock wallet")?;
+            }
+
+            *wallet_lock = Some(wallet);
+
+            RescanResult {
+                rescan_height: rebuild_height,
+                rebuilt: true,
+            }
+        }
+    };
+
+    spawn_background_sync(app, &state, false);
+
+    Ok(result)
+}
+
+/// Create the wallet database for an earlier birthday and persist the birthday
+async fn rebuild_wallet(
+    config: crate::wallet::ZcashConfig,
+    rebuilt_config: &StoredWalletConfig,
+) -> Result<IkkiWallet, IkkiError> {
+    let mut wallet = IkkiWallet::new(config)
+        .await
+        .context("Failed to load wallet")?;
+
+    wallet
+        .init_account()
+        .await
+        .context("Failed to initialize account")?;
+
+    // Issued addresses must stay exposed so their indices are not handed out again
+    wallet
+        .restore_issued_addresses()
+        .context("Failed to restore issued addresses")?;
+
+    // Persist the earlier birthday so future rebuilds start from it
+    write_wallet_config(rebuilt_config)?;
+    Ok(wallet)
+}
+
 /// Run the background sync with progress updates
 async fn run_background_sync(
     wallet: Arc<tokio::sync::Mutex<Option<IkkiWallet>>>,
     sync_state: Arc<crate::state::SyncState>,
     app: AppHandle,
-) -> Result<SyncResult, String> {
+) -> Result<SyncResult, IkkiError> {
     info!("Background sync: acquiring wallet lock...");
     let mut wallet_lock = wallet.lock().await;
-    let wallet_ref = wallet_lock.as_mut().ok_or("Wallet not initialized")?;
+    let wallet_ref = wallet_lock.as_mut().ok_or(IkkiError::NotInitialized)?;
     info!("Background sync: wallet lock acquired");
 
     // Get target block height first
     let target_height = wallet_ref
         .get_block_height()
         .await
-        .map_err(|e| format!("Failed to get block height: {e}"))?;
+        .context("Failed to get block height")?;
     info!("Background sync: target height is {}", target_height);
 
     // Emit initial progress
@@ -527,21 +1365,24 @@ async fn run_background_sync(
 
     // Run sync
     info!("Background sync: starting sync to block {}", target_height);
-    wallet_ref
+    let reorg = wallet_ref
         .sync()
         .await
-        .map_err(|e| format!("Sync failed: {e}"))?;
+        .context("Sync failed")?;
     info!("Background sync: sync completed!");
+    if let Some(reorg) = reorg {
+        report_reorg(&app, &sync_state, reorg);
+    }
 
     // Get final state
     info!("Background sync: getting final balance...");
     let breakdown = wallet_ref
         .get_balance_breakdown()
-        .map_err(|e| format!("Failed to get balance: {e}"))?;
+        .context("Failed to get balance")?;
     let final_height = wallet_ref
         .get_block_height()
         .await
-        .map_err(|e| format!("Failed to get block height: {e}"))?;
+        .context("Failed to get block height")?;
     info!("Background sync: final height is {}, balance updated", final_height);
 
     // Emit 100% progress
@@ -561,10 +1402,6 @@ async fn run_background_sync(
     info!("Background sync: returning result");
     Ok(SyncResult {
         block_height: final_height,
-        balance: BalanceInfo {
-            total: breakdown.sapling + breakdown.orchard + breakdown.transparent,
-            shielded: breakdown.sapling + breakdown.orchard,
-            transparent: breakdown.transparent,
-        },
+        balance: BalanceInfo::from(&breakdown),
     })
 }
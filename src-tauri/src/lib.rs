@@ -3,6 +3,7 @@
 //! This is the Tauri application library providing wallet functionality.
 
 mod commands;
+pub mod rpc;
 mod state;
 pub mod wallet;
 
@@ -22,17 +23,32 @@ pub fn run() {
 
     tauri::Builder::default()
         .manage(AppState::new())
+        .setup(|app| {
+            commands::wallet::spawn_auto_lock(app.handle().clone());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             // Wallet commands
             commands::wallet::check_wallet_exists,
             commands::wallet::init_wallet,
             commands::wallet::load_wallet,
             commands::wallet::auto_load_wallet,
+            commands::wallet::load_watch_only_wallet,
+            commands::wallet::set_wallet_password,
+            commands::wallet::get_lock_status,
+            commands::wallet::lock_wallet,
+            commands::wallet::unlock_wallet,
+            commands::wallet::set_auto_lock_timeout,
+            commands::wallet::export_viewing_key,
             commands::wallet::reset_wallet,
             commands::wallet::get_balance,
+            commands::wallet::get_confirmation_settings,
+            commands::wallet::set_confirmation_settings,
             commands::wallet::get_address,
             commands::wallet::get_new_address,
             commands::wallet::get_all_addresses,
+            commands::wallet::set_address_label,
+            commands::wallet::inspect_address,
             commands::wallet::sync_wallet,
             commands::wallet::generate_seed,
             // Sync commands
@@ -40,13 +56,40 @@ pub fn run() {
             commands::wallet::get_sync_status,
             commands::wallet::cancel_sync,
             commands::wallet::reset_sync_state,
+            commands::wallet::rescan_from,
             // Transaction commands
             commands::transactions::send_transaction,
+            commands::transactions::propose_max_transfer,
+            commands::transactions::send_max,
+            commands::transactions::create_pczt,
+            commands::transactions::inspect_pczt,
+            commands::transactions::sign_pczt,
+            commands::transactions::broadcast_pczt,
             commands::transactions::get_transactions,
+            commands::transactions::query_transactions,
+            commands::transactions::get_transaction_details,
+            commands::transactions::get_transaction_memos,
+            commands::transactions::export_transactions,
             commands::transactions::send_transaction_background,
             commands::transactions::get_pending_transactions,
             commands::transactions::get_pending_transaction,
             commands::transactions::dismiss_pending_transaction,
+            // RPC server commands
+            commands::rpc::start_rpc_server,
+            commands::rpc::stop_rpc_server,
+            commands::rpc::get_rpc_server_status,
+            // Address book commands
+            commands::contacts::list_contacts,
+            commands::contacts::add_contact,
+            commands::contacts::update_contact,
+            commands::contacts::delete_contact,
+            commands::contacts::export_contacts,
+            commands::contacts::import_contacts,
+            // Animated QR commands
+            commands::qr::encode_ur,
+            commands::qr::scan_ur_frame,
+            commands::qr::finish_ur_scan,
+            commands::qr::reset_ur_scan,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,571 @@
+//! zcashd-compatible RPC methods
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::sync::Mutex;
+use tracing::info;
+use uuid::Uuid;
+
+use super::SharedWallet;
+use crate::wallet::{
+    receiver_encodings, AddressEncoding, AddressType, ConfirmationSettings, IkkiError, IkkiWallet,
+    MemoContent, ValuePool,
+};
+
+/// zcashd error codes
+const RPC_INVALID_REQUEST: i32 = -32600;
+const RPC_METHOD_NOT_FOUND: i32 = -32601;
+const RPC_PARSE_ERROR: i32 = -32700;
+const RPC_WALLET_ERROR: i32 = -4;
+const RPC_INVALID_ADDRESS_OR_KEY: i32 = -5;
+const RPC_WALLET_INSUFFICIENT_FUNDS: i32 = -6;
+const RPC_INVALID_PARAMETER: i32 = -8;
+const RPC_WALLET_UNLOCK_NEEDED: i32 = -13;
+const RPC_IN_WARMUP: i32 = -28;
+
+/// Default `maxconf` of `z_listunspent`, as in zcashd
+const DEFAULT_MAX_CONFIRMATIONS: u32 = 9_999_999;
+
+const ZATOSHIS_PER_ZEC: f64 = 100_000_000.0;
+
+/// JSON-RPC error object
+#[derive(Debug, Clone, Serialize)]
+pub struct RpcError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn parse(message: impl Into<String>) -> Self {
+        Self::new(RPC_PARSE_ERROR, message)
+    }
+
+    pub fn invalid_request(message: impl Into<String>) -> Self {
+        Self::new(RPC_INVALID_REQUEST, message)
+    }
+
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(RPC_INVALID_PARAMETER, message)
+    }
+
+    fn invalid_address(message: impl Into<String>) -> Self {
+        Self::new(RPC_INVALID_ADDRESS_OR_KEY, message)
+    }
+
+    /// Map a wallet failure to the closest zcashd error code
+    fn wallet(error: anyhow::Error) -> Self {
+        let error = IkkiError::from(error);
+        let code = match error {
+            IkkiError::InsufficientFunds { .. } => RPC_WALLET_INSUFFICIENT_FUNDS,
+            IkkiError::InvalidAddress(_) => RPC_INVALID_ADDRESS_OR_KEY,
+            IkkiError::InvalidMemo(_) => RPC_INVALID_PARAMETER,
+            IkkiError::WalletLocked => RPC_WALLET_UNLOCK_NEEDED,
+            _ => RPC_WALLET_ERROR,
+        };
+        Self::new(code, error.to_string())
+    }
+}
+
+/// Asynchronous operations started by `z_sendmany`
+#[derive(Clone, Default)]
+pub(super) struct Operations(Arc<Mutex<HashMap<String, Operation>>>);
+
+#[derive(Debug, Clone)]
+struct Operation {
+    id: String,
+    creation_time: u64,
+    status: &'static str,
+    result: Option<Value>,
+    error: Option<RpcError>,
+    params: Value,
+}
+
+impl Operation {
+    fn to_json(&self) -> Value {
+        let mut value = json!({
+            "id": self.id,
+            "status": self.status,
+            "creation_time": self.creation_time,
+            "method": "z_sendmany",
+            "params": self.params,
+        });
+        if let Some(result) = &self.result {
+            value["result"] = result.clone();
+        }
+        if let Some(error) = &self.error {
+            value["error"] = json!(error);
+        }
+        value
+    }
+}
+
+/// Handle one JSON-RPC call object
+pub(super) async fn handle_call(
+    wallet: &SharedWallet,
+    operations: &Operations,
+    call: Value,
+) -> Value {
+    let id = call.get("id").cloned().unwrap_or(Value::Null);
+    let result = match call.get("method").and_then(Value::as_str) {
+        Some(method) => {
+            let params = match call.get("params") {
+                Some(Value::Array(params)) => params.clone(),
+                None | Some(Value::Null) => Vec::new(),
+                Some(_) => {
+                    return json!({
+                        "result": null,
+                        "error": RpcError::invalid_request("Params must be an array"),
+                        "id": id,
+                    })
+                }
+            };
+            dispatch(wallet, operations, method, &params).await
+        }
+        None => Err(RpcError::invalid_request("Missing method")),
+    };
+
+    match result {
+        Ok(result) => json!({ "result": result, "error": null, "id": id }),
+        Err(error) => json!({ "result": null, "error": error, "id": id }),
+    }
+}
+
+async fn dispatch(
+    wallet: &SharedWallet,
+    operations: &Operations,
+    method: &str,
+    params: &[Value],
+) -> Result<Value, RpcError> {
+    match method {
+        "getblockcount" => {
+            let mut guard = wallet.lock().await;
+            let height = loaded(&mut guard)?
+                .get_block_height()
+                .await
+                .map_err(RpcError::wallet)?;
+            Ok(json!(height))
+        }
+        "z_getbalance" => {
+            let address =
+                str_param(params, 0)?.ok_or_else(|| RpcError::invalid_params("Missing address"))?;
+            let min_confirmations = u32_param(params, 1)?.unwrap_or(1);
+            let mut guard = wallet.lock().await;
+            let wallet = loaded(&mut guard)?;
+            let address = own_address(wallet, address)?;
+
+            let targets = receiver_encodings([address]);
+            let total: u64 = wallet
+                .list_unspent_notes(min_confirmations, DEFAULT_MAX_CONFIRMATIONS)
+                .map_err(RpcError::wallet)?
+                .into_iter()
+                .filter(|note| note_matches(note.address.as_deref(), &targets))
+                .map(|note| note.value)
+                .sum();
+            Ok(zec(total))
+        }
+        "z_gettotalbalance" => {
+            let mut guard = wallet.lock().await;
+            let breakdown = loaded(&mut guard)?
+                .get_balance_breakdown()
+                .map_err(RpcError::wallet)?;
+            Ok(json!({
+                "transparent": zec_string(breakdown.transparent.spendable),
+                "private": zec_string(breakdown.shielded_spendable()),
+                "total": zec_string(breakdown.spendable()),
+            }))
+        }
+        "z_listunspent" => {
+            let min_confirmations = u32_param(params, 0)?.unwrap_or(1);
+            let max_confirmations = u32_param(params, 1)?.unwrap_or(DEFAULT_MAX_CONFIRMATIONS);
+            let addresses: Vec<&str> = match param(params, 3) {
+                Some(Value::Array(addresses)) => addresses
+                    .iter()
+                    .map(|a| {
+                        a.as_str()
+                            .ok_or_else(|| RpcError::invalid_params("Addresses must be strings"))
+                    })
+                    .collect::<Result<_, _>>()?,
+                Some(_) => return Err(RpcError::invalid_params("Addresses must be an array")),
+                None => Vec::new(),
+            };
+
+            let mut guard = wallet.lock().await;
+            let wallet = loaded(&mut guard)?;
+            let targets = receiver_encodings(addresses.iter().copied());
+            let notes = wallet
+                .list_unspent_notes(min_confirmations, max_confirmations)
+                .map_err(RpcError::wallet)?;
+            // Watch-only and locked wallets cannot sign a spend of these notes
+            let spendable = wallet.can_spend();
+
+            Ok(Value::Array(
+                notes
+                    .into_iter()
+                    .filter(|note| {
+                        addresses.is_empty() || note_matches(note.address.as_deref(), &targets)
+                    })
+                    .map(|note| {
+                        let index_key = match note.pool {
+                            ValuePool::Orchard => "actionindex",
+                            _ => "outindex",
+                        };
+                        let mut value = json!({
+                            "txid": note.txid,
+                            "pool": pool_name(note.pool),
+                            index_key: note.output_index,
+                            "confirmations": note.confirmations,
+                            "spendable": spendable,
+                            "account": 0,
+                            "address": note.address,
+                            "amount": zec(note.value),
+                            "amountZat": note.value,
+                            "memo": note.memo_bytes.as_deref().map(hex::encode).unwrap_or_default(),
+                            "change": note.is_change,
+                        });
+                        if let Some(MemoContent::Text(text)) = &note.memo {
+                            value["memoStr"] = json!(text);
+                        }
+                        value
+                    })
+                    .collect(),
+            ))
+        }
+        "z_sendmany" => z_sendmany(wallet, operations, params).await,
+        "z_getoperationstatus" | "z_getoperationresult" => {
+            let filter: Option<Vec<String>> = match param(params, 0) {
+                Some(Value::Array(ids)) => Some(
+                    ids.iter()
+                        .filter_map(|id| id.as_str().map(str::to_string))
+                        .collect(),
+                ),
+                Some(_) => return Err(RpcError::invalid_params("Operation ids must be an array")),
+                None => None,
+            };
+            let remove_finished = method == "z_getoperationresult";
+
+            let mut ops = operations.0.lock().await;
+            let mut selected: Vec<Operation> = ops
+                .values()
+                .filter(|op| filter.as_ref().map_or(true, |ids| ids.contains(&op.id)))
+                .filter(|op| !remove_finished || op.status != "executing")
+                .cloned()
+                .collect();
+            selected.sort_by_key(|op| op.creation_time);
+            if remove_finished {
+                for op in &selected {
+                    ops.remove(&op.id);
+                }
+            }
+            Ok(Value::Array(
+                selected.iter().map(Operation::to_json).collect(),
+            ))
+        }
+        "z_getnewaddress" => {
+            let address_type = match str_param(params, 0)? {
+                None => AddressType::Sapling,
+                Some(name) => AddressType::parse(name).ok_or_else(|| {
+                    RpcError::invalid_params(format!("Invalid address type: {name}"))
+                })?,
+            };
+            let mut guard = wallet.lock().await;
+            let address = loaded(&mut guard)?
+                .get_new_address(address_type, None)
+                .map_err(RpcError::wallet)?;
+            Ok(json!(address.address))
+        }
+        "z_listaddresses" => {
+            let mut guard = wallet.lock().await;
+            let records = loaded(&mut guard)?
+                .get_all_addresses()
+                .map_err(RpcError::wallet)?;
+            let mut addresses = Vec::new();
+            for record in records {
+                if let Some(issued) = record.issued_address {
+                    if issued != record.address {
+                        addresses.push(issued);
+                    }
+                }
+                addresses.push(record.address);
+            }
+            Ok(json!(addresses))
+        }
+        "z_validateaddress" => {
+            let address =
+                str_param(params, 0)?.ok_or_else(|| RpcError::invalid_params("Missing address"))?;
+            let mut guard = wallet.lock().await;
+            let inspection = loaded(&mut guard)?
+                .inspect_address(address)
+                .map_err(RpcError::wallet)?;
+            if !inspection.is_valid {
+                return Ok(json!({ "isvalid": false }));
+            }
+            let address_type = match inspection.encoding {
+                Some(AddressEncoding::Unified) => "unified",
+                Some(AddressEncoding::Sapling) => "sapling",
+                Some(AddressEncoding::Sprout) => "sprout",
+                Some(AddressEncoding::Tex) => "tex",
+                _ => "transparent",
+            };
+            Ok(json!({
+                "isvalid": true,
+                "address": address,
+                "address_type": address_type,
+                "ismine": inspection.is_mine,
+            }))
+        }
+        _ => Err(RpcError::new(
+            RPC_METHOD_NOT_FOUND,
+            format!("Method not found: {method}"),
+        )),
+    }
+}
+
+/// `z_sendmany fromaddress amounts [minconf] [fee] [privacyPolicy]`
+///
+/// Ikki spends from its single account and builds one payment per call, so
+/// `amounts` must hold exactly one recipient. `minconf` applies to every note
+/// spent, overriding the wallet's confirmation settings for this send. The
+/// fee is always ZIP-317, so `fee` must be null, and only the default
+/// `privacyPolicy` (or `NoPrivacy`, which allows anything) is accepted since
+/// the stricter policies are not enforced.
+async fn z_sendmany(
+    wallet: &SharedWallet,
+    operations: &Operations,
+    params: &[Value],
+) -> Result<Value, RpcError> {
+    let from_address =
+        str_param(params, 0)?.ok_or_else(|| RpcError::invalid_params("Missing fromaddress"))?;
+    let amounts = match param(params, 1) {
+        Some(Value::Array(amounts)) if !amounts.is_empty() => amounts,
+        _ => {
+            return Err(RpcError::invalid_params(
+                "Amounts must be a non-empty array",
+            ))
+        }
+    };
+    if amounts.len() > 1 {
+        return Err(RpcError::invalid_params(
+            "Ikki supports a single recipient per z_sendmany",
+        ));
+    }
+
+    let recipient = &amounts[0];
+    let to_address = recipient
+        .get("address")
+        .and_then(Value::as_str)
+        .ok_or_else(|| RpcError::invalid_params("Recipient is missing an address"))?
+        .to_string();
+    let amount = recipient
+        .get("amount")
+        .ok_or_else(|| RpcError::invalid_params("Recipient is missing an amount"))
+        .and_then(amount_from_value)?;
+    let memo = match recipient.get("memo").and_then(Value::as_str) {
+        Some(memo) => Some(
+            hex::decode(memo).map_err(|_| RpcError::invalid_params("Memo must be hexadecimal"))?,
+        ),
+        None => None,
+    };
+    let min_confirmations = u32_param(params, 2)?;
+    if min_confirmations == Some(0) {
+        return Err(RpcError::invalid_params("Minconf must be at least 1"));
+    }
+    if !matches!(param(params, 3), None | Some(Value::Null)) {
+        return Err(RpcError::invalid_params(
+            "Ikki always pays the ZIP-317 fee; fee must be null",
+        ));
+    }
+    match str_param(params, 4)? {
+        None | Some("LegacyCompat") | Some("NoPrivacy") => {}
+        Some(policy) => {
+            return Err(RpcError::invalid_params(format!(
+                "Unsupported privacyPolicy: {policy}"
+            )))
+        }
+    }
+
+    {
+        let mut guard = wallet.lock().await;
+        let wallet = loaded(&mut guard)?;
+        if from_address != "ANY_TADDR" && from_address != "ANY_ZADDR" {
+            own_address(wallet, from_address)?;
+        }
+        let inspection = wallet
+            .inspect_address(&to_address)
+            .map_err(RpcError::wallet)?;
+        if !inspection.is_valid {
+            return Err(RpcError::invalid_address(format!(
+                "Invalid recipient address: {to_address}"
+            )));
+        }
+    }
+
+    let creation_time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let id = format!("opid-{}", Uuid::new_v4());
+    operations.0.lock().await.insert(
+        id.clone(),
+        Operation {
+            id: id.clone(),
+            creation_time,
+            status: "executing",
+            result: None,
+            error: None,
+            params: json!({ "fromaddress": from_address, "amounts": amounts }),
+        },
+    );
+
+    let wallet = wallet.clone();
+    let operations = operations.clone();
+    let op_id = id.clone();
+    tokio::spawn(async move {
+        let result = {
+            let mut guard = wallet.lock().await;
+            match guard.as_mut() {
+                Some(wallet) => {
+                    // minconf applies to this send only; the wallet's own
+                    // settings are restored whether or not it succeeds
+                    let saved = wallet.confirmation_settings();
+                    let result = async {
+                        if let Some(min_confirmations) = min_confirmations {
+                            wallet.set_confirmation_settings(ConfirmationSettings {
+                                trusted: min_confirmations,
+                                untrusted: min_confirmations,
+                            })?;
+                        }
+                        wallet.send_to_address(&to_address, amount, memo).await
+                    }
+                    .await;
+                    let _ = wallet.set_confirmation_settings(saved);
+                    result.map_err(RpcError::wallet)
+                }
+                None => Err(not_loaded()),
+            }
+        };
+
+        let mut ops = operations.0.lock().await;
+        if let Some(op) = ops.get_mut(&op_id) {
+            match result {
+                Ok(sent) => {
+                    info!("RPC operation {} sent {}", op_id, sent.txid);
+                    op.status = "success";
+                    op.result = Some(json!({ "txid": sent.txid, "txids": sent.txids }));
+                }
+                Err(error) => {
+                    op.status = "failed";
+                    op.error = Some(error);
+                }
+            }
+        }
+    });
+
+    Ok(json!(id))
+}
+
+fn not_loaded() -> RpcError {
+    RpcError::new(RPC_IN_WARMUP, "Wallet is not loaded")
+}
+
+fn loaded(wallet: &mut Option<IkkiWallet>) -> Result<&mut IkkiWallet, RpcError> {
+    wallet.as_mut().ok_or_else(not_loaded)
+}
+
+/// Check that an address belongs to the wallet
+fn own_address<'a>(wallet: &IkkiWallet, address: &'a str) -> Result<&'a str, RpcError> {
+    let inspection = wallet.inspect_address(address).map_err(RpcError::wallet)?;
+    if !inspection.is_valid {
+        return Err(RpcError::invalid_address(format!(
+            "Invalid address: {address}"
+        )));
+    }
+    if !inspection.is_mine {
+        return Err(RpcError::invalid_address(
+            "From address does not belong to this wallet",
+        ));
+    }
+    Ok(address)
+}
+
+/// Whether a note received at `address` shares a receiver with `targets`
+fn note_matches(address: Option<&str>, targets: &std::collections::HashSet<String>) -> bool {
+    address.is_some_and(|address| {
+        receiver_encodings([address])
+            .iter()
+            .any(|receiver| targets.contains(receiver))
+    })
+}
+
+fn pool_name(pool: ValuePool) -> &'static str {
+    match pool {
+        ValuePool::Transparent => "transparent",
+        ValuePool::Sapling => "sapling",
+        ValuePool::Orchard => "orchard",
+    }
+}
+
+fn zec(zatoshis: u64) -> Value {
+    json!(zatoshis as f64 / ZATOSHIS_PER_ZEC)
+}
+
+fn zec_string(zatoshis: u64) -> String {
+    format!("{}.{:08}", zatoshis / 100_000_000, zatoshis % 100_000_000)
+}
+
+/// Parse a ZEC amount given as a JSON number or string into zatoshis
+fn amount_from_value(value: &Value) -> Result<u64, RpcError> {
+    let amount = match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| RpcError::invalid_params("Amount is not a number"))?;
+
+    if !(0.0..=21_000_000.0).contains(&amount) {
+        return Err(RpcError::invalid_params("Amount out of range"));
+    }
+    Ok((amount * ZATOSHIS_PER_ZEC).round() as u64)
+}
+
+/// Positional parameter, treating JSON null as absent
+fn param(params: &[Value], index: usize) -> Option<&Value> {
+    params.get(index).filter(|value| !value.is_null())
+}
+
+fn str_param(params: &[Value], index: usize) -> Result<Option<&str>, RpcError> {
+    match param(params, index) {
+        Some(Value::String(s)) => Ok(Some(s)),
+        Some(_) => Err(RpcError::invalid_params(format!(
+            "Parameter {} must be a string",
+            index + 1
+        ))),
+        None => Ok(None),
+    }
+}
+
+fn u32_param(params: &[Value], index: usize) -> Result<Option<u32>, RpcError> {
+    match param(params, index) {
+        Some(value) => value
+            .as_u64()
+            .and_then(|v| u32::try_from(v).ok())
+            .map(Some)
+            .ok_or_else(|| {
+                RpcError::invalid_params(format!(
+                    "Parameter {} must be a non-negative integer",
+                    index + 1
+                ))
+            }),
+        None => Ok(None),
+    }
+}
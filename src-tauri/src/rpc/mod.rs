@@ -0,0 +1,334 @@
+//! Local JSON-RPC server
+//!
+//! Exposes wallet operations under zcashd-compatible method names so scripts
+//! written against a zcashd wallet can drive Ikki. The server only listens on
+//! localhost or a Unix socket and authenticates requests with HTTP Basic auth
+//! against a cookie file, like zcashd's `.cookie`.
+
+mod methods;
+
+use std::convert::Infallible;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use base64::Engine;
+use http_body_util::{BodyExt, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper::service::service_fn;
+use hyper::{header, Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use rand::RngCore;
+use serde_json::{json, Value};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::sync::{oneshot, Mutex};
+use tracing::{debug, error, info, warn};
+
+use crate::wallet::IkkiWallet;
+
+pub use methods::RpcError;
+
+/// Wallet shared between the RPC server and the rest of the application
+pub type SharedWallet = Arc<Mutex<Option<IkkiWallet>>>;
+
+/// User name written to the cookie file, matching zcashd
+const COOKIE_USER: &str = "__cookie__";
+
+/// Name of the cookie file in the data directory
+pub const COOKIE_FILE: &str = ".cookie";
+
+/// Where the RPC server listens
+#[derive(Debug, Clone)]
+pub enum RpcEndpoint {
+    /// HTTP on 127.0.0.1 at the given port
+    Tcp(u16),
+    /// HTTP over a Unix domain socket
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl std::fmt::Display for RpcEndpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RpcEndpoint::Tcp(port) => write!(f, "http://127.0.0.1:{port}"),
+            #[cfg(unix)]
+            RpcEndpoint::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// Handle to a running RPC server; dropping it does not stop the server
+pub struct RpcServerHandle {
+    endpoint: RpcEndpoint,
+    cookie_path: PathBuf,
+    shutdown: oneshot::Sender<()>,
+}
+
+impl RpcServerHandle {
+    pub fn endpoint(&self) -> &RpcEndpoint {
+        &self.endpoint
+    }
+
+    pub fn cookie_path(&self) -> &Path {
+        &self.cookie_path
+    }
+
+    /// Stop accepting connections and remove the cookie file
+    pub fn stop(self) {
+        let _ = self.shutdown.send(());
+        let _ = std::fs::remove_file(&self.cookie_path);
+        #[cfg(unix)]
+        if let RpcEndpoint::Unix(path) = &self.endpoint {
+            let _ = std::fs::remove_file(path);
+        }
+        info!("RPC server on {} stopped", self.endpoint);
+    }
+}
+
+/// State shared by all connections
+struct RpcContext {
+    wallet: SharedWallet,
+    /// Expected value of the `Authorization` header
+    authorization: String,
+    operations: methods::Operations,
+}
+
+/// Write a fresh cookie file and return the expected `Authorization` header
+fn write_cookie(path: &Path) -> anyhow::Result<String> {
+    let mut secret = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut secret);
+    let credentials = format!("{COOKIE_USER}:{}", hex::encode(secret));
+
+    // Create the file with its final permissions so the secret is never
+    // readable by other users, even briefly
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    std::io::Write::write_all(&mut options.open(path)?, credentials.as_bytes())?;
+
+    Ok(format!(
+        "Basic {}",
+        base64::engine::general_purpose::STANDARD.encode(credentials)
+    ))
+}
+
+/// Start the RPC server, writing its cookie file into `data_dir`
+pub async fn start(
+    wallet: SharedWallet,
+    endpoint: RpcEndpoint,
+    data_dir: &Path,
+) -> anyhow::Result<RpcServerHandle> {
+    std::fs::create_dir_all(data_dir)?;
+    let cookie_path = data_dir.join(COOKIE_FILE);
+    let authorization = write_cookie(&cookie_path)?;
+
+    let context = Arc::new(RpcContext {
+        wallet,
+        authorization,
+        operations: methods::Operations::default(),
+    });
+    let (shutdown, shutdown_rx) = oneshot::channel();
+
+    match &endpoint {
+        RpcEndpoint::Tcp(port) => {
+            let listener = tokio::net::TcpListener::bind(("127.0.0.1", *port)).await?;
+            tokio::spawn(accept_loop(listener, shutdown_rx, context));
+        }
+        #[cfg(unix)]
+        RpcEndpoint::Unix(path) => {
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+            let listener = bind_private_socket(path)?;
+            tokio::spawn(accept_loop(listener, shutdown_rx, context));
+        }
+    }
+
+    info!("RPC server listening on {}", endpoint);
+    Ok(RpcServerHandle {
+        endpoint,
+        cookie_path,
+        shutdown,
+    })
+}
+
+/// Bind a Unix socket that only the current user can connect to
+///
+/// The socket is bound inside a fresh 0700 directory next to `path`,
+/// restricted to 0600 and only then moved into place, so there is no window
+/// in which another user can connect to it.
+#[cfg(unix)]
+fn bind_private_socket(path: &Path) -> anyhow::Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+
+    let parent = path
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let mut suffix = [0u8; 8];
+    rand::thread_rng().fill_bytes(&mut suffix);
+    let private_dir = parent.join(format!(".ikki-rpc-{}", hex::encode(suffix)));
+    std::fs::DirBuilder::new()
+        .mode(0o700)
+        .create(&private_dir)?;
+
+    let staged = private_dir.join("rpc.sock");
+    let result = tokio::net::UnixListener::bind(&staged)
+        .map_err(anyhow::Error::from)
+        .and_then(|listener| {
+            std::fs::set_permissions(&staged, std::fs::Permissions::from_mode(0o600))?;
+            std::fs::rename(&staged, path)?;
+            Ok(listener)
+        });
+    let _ = std::fs::remove_file(&staged);
+    let _ = std::fs::remove_dir(&private_dir);
+    result
+}
+
+/// Listener the server can accept connections from
+trait Listener: Send + 'static {
+    type Stream: AsyncRead + AsyncWrite + Unpin + Send + 'static;
+
+    fn accept_stream(&self) -> impl Future<Output = std::io::Result<Self::Stream>> + Send;
+}
+
+impl Listener for tokio::net::TcpListener {
+    type Stream = tokio::net::TcpStream;
+
+    async fn accept_stream(&self) -> std::io::Result<Self::Stream> {
+        self.accept().await.map(|(stream, _)| stream)
+    }
+}
+
+#[cfg(unix)]
+impl Listener for tokio::net::UnixListener {
+    type Stream = tokio::net::UnixStream;
+
+    async fn accept_stream(&self) -> std::io::Result<Self::Stream> {
+        self.accept().await.map(|(stream, _)| stream)
+    }
+}
+
+/// Accept connections until shutdown is requested
+async fn accept_loop<L: Listener>(
+    listener: L,
+    mut shutdown: oneshot::Receiver<()>,
+    context: Arc<RpcContext>,
+) {
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            accepted = listener.accept_stream() => {
+                let stream = match accepted {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        warn!("RPC accept failed: {}", e);
+                        continue;
+                    }
+                };
+                let context = context.clone();
+                tokio::spawn(async move {
+                    let service = service_fn(move |req| handle_request(context.clone(), req));
+                    if let Err(e) = hyper::server::conn::http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await
+                    {
+                        debug!("RPC connection closed: {}", e);
+                    }
+                });
+            }
+        }
+    }
+}
+
+fn http_response(status: StatusCode, body: Value) -> Response<Full<Bytes>> {
+    Response::builder()
+        .status(status)
+        .header(header::CONTENT_TYPE, "application/json")
+        .body(Full::new(Bytes::from(body.to_string())))
+        .unwrap_or_default()
+}
+
+/// Constant-time comparison of the provided credentials
+fn is_authorized(provided: Option<&[u8]>, expected: &str) -> bool {
+    let Some(provided) = provided else {
+        return false;
+    };
+    let expected = expected.as_bytes();
+    provided.len() == expected.len()
+        && provided
+            .iter()
+            .zip(expected)
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
+
+async fn handle_request(
+    context: Arc<RpcContext>,
+    req: Request<Incoming>,
+) -> Result<Response<Full<Bytes>>, Infallible> {
+    let provided = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .map(|value| value.as_bytes());
+    if !is_authorized(provided, &context.authorization) {
+        warn!("Rejected unauthenticated RPC request");
+        let mut response = http_response(StatusCode::UNAUTHORIZED, Value::Null);
+        response.headers_mut().insert(
+            header::WWW_AUTHENTICATE,
+            header::HeaderValue::from_static("Basic realm=\"jsonrpc\""),
+        );
+        return Ok(response);
+    }
+
+    if req.method() != Method::POST {
+        return Ok(http_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            json!({ "result": null, "error": RpcError::invalid_request("JSON-RPC requests must use POST"), "id": null }),
+        ));
+    }
+
+    let body = match req.into_body().collect().await {
+        Ok(body) => body.to_bytes(),
+        Err(e) => {
+            error!("Failed to read RPC request body: {}", e);
+            return Ok(http_response(StatusCode::BAD_REQUEST, Value::Null));
+        }
+    };
+
+    let request: Value = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => {
+            return Ok(http_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                json!({ "result": null, "error": RpcError::parse(e.to_string()), "id": null }),
+            ));
+        }
+    };
+
+    // zcashd accepts batches as JSON arrays and answers each call in order
+    let response = match request {
+        Value::Array(calls) => {
+            let mut responses = Vec::with_capacity(calls.len());
+            for call in calls {
+                responses
+                    .push(methods::handle_call(&context.wallet, &context.operations, call).await);
+            }
+            Value::Array(responses)
+        }
+        call => methods::handle_call(&context.wallet, &context.operations, call).await,
+    };
+
+    // Like zcashd, failed single calls are reported with an HTTP error status
+    let status = match &response {
+        Value::Object(object) if object.get("error").is_some_and(|e| !e.is_null()) => {
+            StatusCode::INTERNAL_SERVER_ERROR
+        }
+        _ => StatusCode::OK,
+    };
+    Ok(http_response(status, response))
+}
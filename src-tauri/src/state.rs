@@ -2,10 +2,11 @@
 
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, RwLock};
 use tokio::sync::Mutex;
 use serde::{Deserialize, Serialize};
-use crate::wallet::IkkiWallet;
+use crate::rpc::RpcServerHandle;
+use crate::wallet::{ChainReorg, IkkiError, IkkiWallet, UrScanner};
 
 /// Status of a pending transaction
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -17,27 +18,37 @@ pub enum PendingTxStatus {
     Broadcasting,
     /// Transaction was broadcast successfully
     Broadcast,
+    /// Some transactions of the payment were broadcast before a later one failed
+    Partial,
     /// Transaction failed
     Failed,
 }
 
 /// A pending transaction being processed in the background
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct PendingTransaction {
     /// Unique ID for tracking (generated before txid is known)
     pub id: String,
     /// Transaction ID (available after building)
     pub txid: Option<String>,
+    /// All transaction IDs of the payment in broadcast order (two for TEX recipients)
+    #[serde(default)]
+    pub txids: Vec<String>,
+    /// Whether the recipient is a ZIP 320 TEX address
+    #[serde(default)]
+    pub is_tex: bool,
+    /// Total fee across all transactions (available after building)
+    pub fee: Option<u64>,
     /// Recipient address
     pub to_address: String,
-    /// Amount in zatoshis
+    /// Amount in zatoshis (an estimate for max sends until broadcast)
     pub amount: u64,
     /// Optional memo
     pub memo: Option<String>,
     /// Current status
     pub status: PendingTxStatus,
-    /// Error message if failed
-    pub error: Option<String>,
+    /// Why the transaction failed, serialized like a command error
+    pub error: Option<IkkiError>,
     /// Timestamp when initiated
     pub created_at: u64,
 }
@@ -61,7 +72,13 @@ impl PendingTxState {
         txs.insert(tx.id.clone(), tx);
     }
 
-    pub async fn update_status(&self, id: &str, status: PendingTxStatus, txid: Option<String>, error: Option<String>) {
+    pub async fn update_status(
+        &self,
+        id: &str,
+        status: PendingTxStatus,
+        txid: Option<String>,
+        error: Option<IkkiError>,
+    ) {
         let mut txs = self.transactions.lock().await;
         if let Some(tx) = txs.get_mut(id) {
             tx.status = status;
@@ -72,6 +89,40 @@ impl PendingTxState {
         }
     }
 
+    /// Mark a transaction as broadcast, recording every txid of the payment, the amount
+    /// paid and the combined fee
+    pub async fn mark_broadcast(
+        &self,
+        id: &str,
+        txid: String,
+        txids: Vec<String>,
+        amount: u64,
+        fee: u64,
+    ) {
+        let mut txs = self.transactions.lock().await;
+        if let Some(tx) = txs.get_mut(id) {
+            tx.status = PendingTxStatus::Broadcast;
+            tx.txid = Some(txid);
+            tx.txids = txids;
+            tx.amount = amount;
+            tx.fee = Some(fee);
+            tx.error = None;
+        }
+    }
+
+    /// Mark a payment whose later transaction failed, recording the txids already broadcast
+    pub async fn mark_partially_broadcast(&self, id: &str, error: IkkiError) {
+        let mut txs = self.transactions.lock().await;
+        if let Some(tx) = txs.get_mut(id) {
+            if let IkkiError::PartialBroadcast { txids, .. } = &error {
+                tx.txid = txids.last().cloned();
+                tx.txids = txids.clone();
+            }
+            tx.status = PendingTxStatus::Partial;
+            tx.error = Some(error);
+        }
+    }
+
     pub async fn remove(&self, id: &str) {
         let mut txs = self.transactions.lock().await;
         txs.remove(id);
@@ -107,6 +158,8 @@ pub struct SyncState {
     pub is_first_sync: AtomicBool,
     /// Cancel flag for stopping sync
     pub cancel_requested: AtomicBool,
+    /// Most recent chain reorganization detected by sync
+    last_reorg: RwLock<Option<ChainReorg>>,
 }
 
 impl SyncState {
@@ -117,6 +170,7 @@ impl SyncState {
             target_block: AtomicU64::new(0),
             is_first_sync: AtomicBool::new(false),
             cancel_requested: AtomicBool::new(false),
+            last_reorg: RwLock::new(None),
         }
     }
 
@@ -154,6 +208,16 @@ impl SyncState {
             self.target_block.load(Ordering::SeqCst),
         )
     }
+
+    pub fn record_reorg(&self, reorg: ChainReorg) {
+        if let Ok(mut last) = self.last_reorg.write() {
+            *last = Some(reorg);
+        }
+    }
+
+    pub fn last_reorg(&self) -> Option<ChainReorg> {
+        self.last_reorg.read().ok().and_then(|last| last.clone())
+    }
 }
 
 impl Default for SyncState {
@@ -167,6 +231,10 @@ pub struct AppState {
     pub wallet: Arc<Mutex<Option<IkkiWallet>>>,
     pub sync_state: Arc<SyncState>,
     pub pending_tx_state: Arc<PendingTxState>,
+    /// Local JSON-RPC server, when enabled
+    pub rpc_server: Mutex<Option<RpcServerHandle>>,
+    /// Animated QR code being scanned
+    pub ur_scanner: Mutex<UrScanner>,
 }
 
 impl AppState {
@@ -175,6 +243,8 @@ impl AppState {
             wallet: Arc::new(Mutex::new(None)),
             sync_state: Arc::new(SyncState::new()),
             pending_tx_state: Arc::new(PendingTxState::new()),
+            rpc_server: Mutex::new(None),
+            ur_scanner: Mutex::new(UrScanner::new()),
         }
     }
 }
@@ -0,0 +1,407 @@
+//! Address book stored next to the wallet database
+//!
+//! Contacts, and the labels given to the wallet's own addresses, live in their
+//! own SQLite file so that rebuilding or rescanning `wallet.db` never loses them.
+//! Once an unlock password is set it is encrypted with the same key as
+//! `wallet.db`.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use zcash_address::ZcashAddress;
+
+use super::{open_wallet_db, AddressMetadata, AddressType, DbKey, IkkiError, Network};
+
+/// A saved contact
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Contact {
+    /// Database identifier
+    pub id: i64,
+    /// Display name shown in history
+    pub name: String,
+    /// Zcash address (unified, Sapling, transparent or TEX)
+    pub address: String,
+    /// Free-form notes
+    pub notes: Option<String>,
+    /// User-defined tags
+    pub tags: Vec<String>,
+    /// Unix timestamp when the contact was created
+    pub created_at: u64,
+    /// Unix timestamp of the last update
+    pub updated_at: u64,
+}
+
+/// Contact fields supplied when creating, updating or importing a contact
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContactInput {
+    pub name: String,
+    pub address: String,
+    #[serde(default)]
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+/// Result of importing contacts from a JSON file
+#[derive(Debug, Clone, Default)]
+pub struct ImportSummary {
+    /// Contacts added or updated
+    pub imported: usize,
+    /// Entries skipped because they were invalid
+    pub skipped: usize,
+}
+
+/// Check that an address parses and belongs to the given network
+pub fn validate_address(address: &str, network: Network) -> Result<(), IkkiError> {
+    let parsed: ZcashAddress = address
+        .trim()
+        .parse()
+        .map_err(|e| IkkiError::InvalidAddress(e.to_string()))?;
+
+    parsed
+        .convert_if_network::<zcash_keys::address::Address>(network.network_type())
+        .map_err(|e| IkkiError::InvalidAddress(format!("not valid for {network}: {e}")))?;
+
+    Ok(())
+}
+
+/// Diversifier index as stored in SQLite, which only has signed 64-bit integers
+fn index_to_sql(index: u128) -> anyhow::Result<i64> {
+    i64::try_from(index).map_err(|_| anyhow::anyhow!("Diversifier index {index} is too large"))
+}
+
+/// Current Unix timestamp in seconds
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// Persistent address book
+pub struct AddressBook {
+    conn: Connection,
+    network: Network,
+}
+
+impl AddressBook {
+    /// Open (creating if needed) the address book database at the given path
+    ///
+    /// `key` is the wallet database key when an unlock password is set.
+    pub fn open(path: &Path, network: Network, key: Option<&DbKey>) -> anyhow::Result<Self> {
+        let conn = open_wallet_db(path, key, false)?;
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS contacts (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                address TEXT NOT NULL UNIQUE,
+                notes TEXT,
+                tags TEXT NOT NULL DEFAULT '[]',
+                created_at INTEGER NOT NULL,
+                updated_at INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS own_addresses (
+                address TEXT PRIMARY KEY,
+                label TEXT,
+                created_at INTEGER
+            );",
+        )?;
+        Self::migrate(&conn)?;
+
+        Ok(Self { conn, network })
+    }
+
+    /// Apply schema changes made after the initial tables
+    fn migrate(conn: &Connection) -> anyhow::Result<()> {
+        let version: i64 = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+
+        if version < 1 {
+            conn.execute_batch(
+                "ALTER TABLE own_addresses ADD COLUMN address_type TEXT;
+                ALTER TABLE own_addresses ADD COLUMN issued_address TEXT;
+                PRAGMA user_version = 1;",
+            )?;
+        }
+
+        if version < 2 {
+            conn.execute_batch(
+                "ALTER TABLE own_addresses ADD COLUMN diversifier_index INTEGER;
+                PRAGMA user_version = 2;",
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Read a contact from a `SELECT *` row
+    fn contact_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Contact> {
+        let tags: String = row.get("tags")?;
+        Ok(Contact {
+            id: row.get("id")?,
+            name: row.get("name")?,
+            address: row.get("address")?,
+            notes: row.get("notes")?,
+            tags: serde_json::from_str(&tags).unwrap_or_default(),
+            created_at: row.get("created_at")?,
+            updated_at: row.get("updated_at")?,
+        })
+    }
+
+    /// Validate and normalize contact input
+    fn check_input(&self, input: &ContactInput) -> anyhow::Result<(String, String, String)> {
+        let name = input.name.trim();
+        if name.is_empty() {
+            anyhow::bail!("Contact name cannot be empty");
+        }
+        let address = input.address.trim();
+        validate_address(address, self.network)?;
+        let tags = serde_json::to_string(&input.tags)?;
+        Ok((name.to_string(), address.to_string(), tags))
+    }
+
+    /// List all contacts, sorted by name
+    pub fn list(&self) -> anyhow::Result<Vec<Contact>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT * FROM contacts ORDER BY name COLLATE NOCASE")?;
+        let contacts = stmt
+            .query_map([], Self::contact_from_row)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(contacts)
+    }
+
+    /// Get a contact by id
+    pub fn get(&self, id: i64) -> anyhow::Result<Option<Contact>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT * FROM contacts WHERE id = ?",
+                [id],
+                Self::contact_from_row,
+            )
+            .optional()?)
+    }
+
+    /// Find the contact saved for an address
+    pub fn find_by_address(&self, address: &str) -> anyhow::Result<Option<Contact>> {
+        Ok(self
+            .conn
+            .query_row(
+                "SELECT * FROM contacts WHERE address = ?",
+                [address.trim()],
+                Self::contact_from_row,
+            )
+            .optional()?)
+    }
+
+    /// Add a new contact
+    pub fn add(&self, input: &ContactInput) -> anyhow::Result<Contact> {
+        let (name, address, tags) = self.check_input(input)?;
+        if self.find_by_address(&address)?.is_some() {
+            anyhow::bail!("A contact with this address already exists");
+        }
+
+        let timestamp = now();
+        self.conn.execute(
+            "INSERT INTO contacts (name, address, notes, tags, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?)",
+            params![name, address, input.notes, tags, timestamp, timestamp],
+        )?;
+
+        self.get(self.conn.last_insert_rowid())?
+            .ok_or_else(|| anyhow::anyhow!("Contact not found after insert"))
+    }
+
+    /// Update an existing contact
+    pub fn update(&self, id: i64, input: &ContactInput) -> anyhow::Result<Contact> {
+        let (name, address, tags) = self.check_input(input)?;
+        if let Some(existing) = self.find_by_address(&address)? {
+            if existing.id != id {
+                anyhow::bail!("A contact with this address already exists");
+            }
+        }
+
+        let updated = self.conn.execute(
+            "UPDATE contacts
+            SET name = ?, address = ?, notes = ?, tags = ?, updated_at = ?
+            WHERE id = ?",
+            params![name, address, input.notes, tags, now(), id],
+        )?;
+        if updated == 0 {
+            return Err(IkkiError::NotFound(format!("Contact {id}")).into());
+        }
+
+        self.get(id)?
+            .ok_or_else(|| IkkiError::NotFound(format!("Contact {id}")).into())
+    }
+
+    /// Delete a contact
+    pub fn delete(&self, id: i64) -> anyhow::Result<()> {
+        let deleted = self
+            .conn
+            .execute("DELETE FROM contacts WHERE id = ?", [id])?;
+        if deleted == 0 {
+            return Err(IkkiError::NotFound(format!("Contact {id}")).into());
+        }
+        Ok(())
+    }
+
+    /// Map of address to contact name, for annotating history
+    pub fn names_by_address(&self) -> anyhow::Result<HashMap<String, String>> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .map(|c| (c.address, c.name))
+            .collect())
+    }
+
+    /// Write all contacts to a JSON file
+    pub fn export_json(&self, path: &Path) -> anyhow::Result<usize> {
+        let contacts: Vec<ContactInput> = self
+            .list()?
+            .into_iter()
+            .map(|c| ContactInput {
+                name: c.name,
+                address: c.address,
+                notes: c.notes,
+                tags: c.tags,
+            })
+            .collect();
+
+        let json = serde_json::to_string_pretty(&contacts)?;
+        std::fs::write(path, json)
+            .map_err(|e| anyhow::anyhow!("Failed to write address book: {e}"))?;
+        Ok(contacts.len())
+    }
+
+    /// Import contacts from a JSON file, updating contacts with matching addresses
+    pub fn import_json(&self, path: &Path) -> anyhow::Result<ImportSummary> {
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read address book: {e}"))?;
+        let entries: Vec<ContactInput> = serde_json::from_str(&json)
+            .map_err(|e| anyhow::anyhow!("Invalid address book file: {e}"))?;
+
+        let mut summary = ImportSummary::default();
+        for entry in entries {
+            let result = match self.find_by_address(&entry.address)? {
+                Some(existing) => self.update(existing.id, &entry).map(|_| ()),
+                None => self.add(&entry).map(|_| ()),
+            };
+            match result {
+                Ok(()) => summary.imported += 1,
+                Err(e) => {
+                    tracing::warn!("Skipping address book entry {}: {}", entry.address, e);
+                    summary.skipped += 1;
+                }
+            }
+        }
+
+        Ok(summary)
+    }
+
+    /// Record that the wallet issued one of its own addresses
+    ///
+    /// `address` is the unified address stored in the wallet database,
+    /// derived at `diversifier_index`, and `issued_address` the encoding
+    /// handed out for the requested type.
+    pub fn record_issued_address(
+        &self,
+        address: &str,
+        address_type: AddressType,
+        issued_address: &str,
+        diversifier_index: u128,
+        label: Option<&str>,
+    ) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT INTO own_addresses
+                (address, label, created_at, address_type, issued_address, diversifier_index)
+            VALUES (?, ?, ?, ?, ?, ?)
+            ON CONFLICT (address) DO UPDATE SET
+                label = COALESCE(excluded.label, label),
+                address_type = excluded.address_type,
+                issued_address = excluded.issued_address,
+                diversifier_index = excluded.diversifier_index",
+            params![
+                address,
+                label,
+                now(),
+                address_type.as_str(),
+                issued_address,
+                index_to_sql(diversifier_index)?
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Record the diversifier index of an own address whose index is not
+    /// known yet, e.g. one recorded before indices were kept
+    pub fn record_diversifier_index(
+        &self,
+        address: &str,
+        diversifier_index: u128,
+    ) -> anyhow::Result<()> {
+        self.conn.execute(
+            "UPDATE own_addresses SET diversifier_index = ?
+            WHERE address = ? AND diversifier_index IS NULL",
+            params![index_to_sql(diversifier_index)?, address],
+        )?;
+        Ok(())
+    }
+
+    /// Own addresses with a known diversifier index, in index order
+    ///
+    /// Used to expose them again after the wallet database is rebuilt.
+    pub fn own_address_indices(&self) -> anyhow::Result<Vec<(String, u128)>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT address, diversifier_index FROM own_addresses
+                WHERE diversifier_index IS NOT NULL
+                ORDER BY diversifier_index",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+        })?;
+
+        let mut indices = Vec::new();
+        for row in rows {
+            let (address, index) = row?;
+            let index = u128::try_from(index)
+                .map_err(|_| anyhow::anyhow!("Invalid diversifier index {index}"))?;
+            indices.push((address, index));
+        }
+        Ok(indices)
+    }
+
+    /// Set or clear the label of one of the wallet's own addresses
+    pub fn set_address_label(&self, address: &str, label: Option<&str>) -> anyhow::Result<()> {
+        let label = label.map(str::trim).filter(|l| !l.is_empty());
+        self.conn.execute(
+            "INSERT INTO own_addresses (address, label) VALUES (?, ?)
+            ON CONFLICT (address) DO UPDATE SET label = excluded.label",
+            params![address, label],
+        )?;
+        Ok(())
+    }
+
+    /// Labels and issue times of the wallet's own addresses, by address
+    pub fn own_address_metadata(&self) -> anyhow::Result<HashMap<String, AddressMetadata>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT address, label, created_at, address_type, issued_address
+                FROM own_addresses",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            let address_type: Option<String> = row.get(3)?;
+            Ok((
+                row.get::<_, String>(0)?,
+                AddressMetadata {
+                    label: row.get(1)?,
+                    created_at: row.get(2)?,
+                    address_type: address_type.as_deref().and_then(AddressType::parse),
+                    issued_address: row.get(4)?,
+                },
+            ))
+        })?;
+        Ok(rows.collect::<Result<HashMap<_, _>, _>>()?)
+    }
+}
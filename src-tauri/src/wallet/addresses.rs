@@ -0,0 +1,264 @@
+//! Records for the wallet's own receiving addresses
+//!
+//! Combines the derivation data stored in the wallet database with the
+//! labels and issue times kept in the address book database.
+
+use std::collections::HashMap;
+
+use rusqlite::Connection;
+use zcash_address::ZcashAddress;
+use zcash_keys::address::Address;
+use zcash_keys::keys::{ReceiverRequirement, UnifiedAddressRequest};
+
+use super::Network;
+
+/// Key scope of externally visible (receiving) addresses in the wallet database
+const EXTERNAL_KEY_SCOPE: i64 = 0;
+
+/// Receivers contained in an address
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReceiverSet {
+    pub orchard: bool,
+    pub sapling: bool,
+    pub transparent: bool,
+}
+
+impl ReceiverSet {
+    /// Determine the receivers of an encoded address
+    pub fn of_address(address: &str, network: Network) -> Option<Self> {
+        let parsed: ZcashAddress = address.parse().ok()?;
+        let receivers = match parsed
+            .convert_if_network::<Address>(network.network_type())
+            .ok()?
+        {
+            Address::Unified(ua) => ReceiverSet {
+                orchard: ua.has_orchard(),
+                sapling: ua.has_sapling(),
+                transparent: ua.has_transparent(),
+            },
+            Address::Sapling(_) => ReceiverSet {
+                sapling: true,
+                ..Default::default()
+            },
+            Address::Transparent(_) | Address::Tex(_) => ReceiverSet {
+                transparent: true,
+                ..Default::default()
+            },
+        };
+        Some(receivers)
+    }
+
+    /// Request for a unified address with exactly these receivers
+    pub fn address_request(self) -> UnifiedAddressRequest {
+        let requirement = |required: bool| {
+            if required {
+                ReceiverRequirement::Require
+            } else {
+                ReceiverRequirement::Omit
+            }
+        };
+        UnifiedAddressRequest::unsafe_custom(
+            requirement(self.orchard),
+            requirement(self.sapling),
+            requirement(self.transparent),
+        )
+    }
+}
+
+/// Kind of address issued by `get_new_address`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AddressType {
+    /// Unified address with Orchard and Sapling receivers
+    #[default]
+    Shielded,
+    /// Unified address with Orchard, Sapling and transparent receivers
+    Full,
+    /// Unified address with only an Orchard receiver
+    Orchard,
+    /// Legacy Sapling z-address
+    Sapling,
+    /// Transparent P2PKH address
+    Transparent,
+}
+
+impl AddressType {
+    /// Name stored in the address book database
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AddressType::Shielded => "shielded",
+            AddressType::Full => "full",
+            AddressType::Orchard => "orchard",
+            AddressType::Sapling => "sapling",
+            AddressType::Transparent => "transparent",
+        }
+    }
+
+    /// Parse a name stored in the address book database
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "shielded" => Some(AddressType::Shielded),
+            "full" => Some(AddressType::Full),
+            "orchard" => Some(AddressType::Orchard),
+            "sapling" => Some(AddressType::Sapling),
+            "transparent" => Some(AddressType::Transparent),
+            _ => None,
+        }
+    }
+
+    /// Receivers the wallet must derive for this address type
+    ///
+    /// Unified addresses need at least one shielded receiver, so a transparent
+    /// address is taken from a unified address that also has an Orchard receiver.
+    pub fn required_receivers(self) -> ReceiverSet {
+        match self {
+            AddressType::Shielded => ReceiverSet {
+                orchard: true,
+                sapling: true,
+                transparent: false,
+            },
+            AddressType::Full => ReceiverSet {
+                orchard: true,
+                sapling: true,
+                transparent: true,
+            },
+            AddressType::Orchard => ReceiverSet {
+                orchard: true,
+                ..Default::default()
+            },
+            AddressType::Sapling => ReceiverSet {
+                sapling: true,
+                ..Default::default()
+            },
+            AddressType::Transparent => ReceiverSet {
+                orchard: true,
+                transparent: true,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// A freshly issued address and the encodings of its individual receivers
+#[derive(Debug, Clone)]
+pub struct NewAddress {
+    /// Address to hand out, encoded for the requested type
+    pub address: String,
+    /// Requested address type
+    pub address_type: AddressType,
+    /// Unified address as stored in the wallet database
+    pub unified_address: String,
+    /// Diversifier index the address was derived at
+    pub diversifier_index: u128,
+    /// Orchard-only unified address, if the address has an Orchard receiver
+    pub orchard: Option<String>,
+    /// Sapling z-address, if the address has a Sapling receiver
+    pub sapling: Option<String>,
+    /// Transparent address, if the address has a transparent receiver
+    pub transparent: Option<String>,
+}
+
+/// User-defined metadata for one of the wallet's addresses
+#[derive(Debug, Clone, Default)]
+pub struct AddressMetadata {
+    /// User label, e.g. the customer the address was handed to
+    pub label: Option<String>,
+    /// Unix timestamp when Ikki issued the address
+    pub created_at: Option<u64>,
+    /// Type the address was issued as
+    pub address_type: Option<AddressType>,
+    /// Encoding handed out when the address was issued
+    pub issued_address: Option<String>,
+}
+
+/// One of the wallet's receiving addresses
+#[derive(Debug, Clone)]
+pub struct AddressRecord {
+    /// Encoded address
+    pub address: String,
+    /// Diversifier index the address was derived at
+    pub diversifier_index: u128,
+    /// Receivers contained in the address
+    pub receivers: ReceiverSet,
+    /// Unix timestamp when Ikki issued the address (None if unknown)
+    pub created_at: Option<u64>,
+    /// Block height at which the address was first exposed
+    pub exposed_at_height: Option<u32>,
+    /// User label
+    pub label: Option<String>,
+    /// Type the address was issued as (None if not issued through Ikki)
+    pub address_type: Option<AddressType>,
+    /// Encoding handed out when the address was issued
+    pub issued_address: Option<String>,
+    /// Total value received at this address in zatoshis
+    pub received_total: u64,
+}
+
+/// Convert a diversifier index to an integer
+pub(crate) fn diversifier_index_to_u128(index: &zip32::DiversifierIndex) -> u128 {
+    // DiversifierIndex bytes are little-endian
+    index
+        .as_bytes()
+        .iter()
+        .rev()
+        .fold(0u128, |acc, b| (acc << 8) | u128::from(*b))
+}
+
+/// Decode the big-endian diversifier index stored in the wallet database
+fn diversifier_index_from_be(bytes: &[u8]) -> u128 {
+    bytes
+        .iter()
+        .fold(0u128, |acc, b| (acc << 8) | u128::from(*b))
+}
+
+/// List the wallet's receiving addresses in derivation order
+pub fn list_address_records(
+    conn: &Connection,
+    network: Network,
+    metadata: &HashMap<String, AddressMetadata>,
+) -> anyhow::Result<Vec<AddressRecord>> {
+    let mut received = HashMap::new();
+    let mut stmt = conn.prepare(
+        "SELECT to_address, SUM(value)
+        FROM v_tx_outputs
+        WHERE to_account_uuid IS NOT NULL AND is_change = 0 AND to_address IS NOT NULL
+        GROUP BY to_address",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?))
+    })?;
+    for (address, total) in rows.flatten() {
+        received.insert(address, total.max(0) as u64);
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT address, diversifier_index_be, exposed_at_height
+        FROM addresses
+        WHERE key_scope = ?
+        ORDER BY diversifier_index_be",
+    )?;
+    let rows = stmt.query_map([EXTERNAL_KEY_SCOPE], |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, Vec<u8>>(1)?,
+            row.get::<_, Option<u32>>(2)?,
+        ))
+    })?;
+
+    let mut records = Vec::new();
+    for (address, diversifier_index_be, exposed_at_height) in rows.flatten() {
+        let meta = metadata.get(&address).cloned().unwrap_or_default();
+        records.push(AddressRecord {
+            receivers: ReceiverSet::of_address(&address, network).unwrap_or_default(),
+            diversifier_index: diversifier_index_from_be(&diversifier_index_be),
+            created_at: meta.created_at,
+            exposed_at_height,
+            label: meta.label,
+            address_type: meta.address_type,
+            issued_address: meta.issued_address,
+            received_total: received.get(&address).copied().unwrap_or(0),
+            address,
+        });
+    }
+
+    Ok(records)
+}
@@ -0,0 +1,364 @@
+//! Animated QR codes for exchanging data with air-gapped devices
+//!
+//! Payloads are CBOR-encoded and split into fountain-coded multi-part UR
+//! frames (BCR-2020-005), using the UR types Keystone uses for Zcash. Frames
+//! can be scanned in any order and a missed frame is covered by later ones.
+
+use std::collections::BTreeSet;
+
+use ciborium::Value;
+use serde::{Deserialize, Serialize};
+
+use crate::wallet::WatchOnlyKey;
+
+/// Default maximum bytes per frame, small enough for phone cameras to scan reliably
+pub const DEFAULT_FRAGMENT_LEN: usize = 200;
+
+/// CBOR tag of a `zcash-unified-full-viewing-key` entry in `zcash-accounts`
+const UFVK_TAG: u64 = 49203;
+
+/// Kind of data carried by a UR
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UrKind {
+    /// PCZT, unsigned or signed (`zcash-pczt`)
+    Pczt,
+    /// Viewing key of the wallet's account (`zcash-accounts`)
+    ViewingKey,
+    /// Serialized transaction (`bytes`)
+    Transaction,
+}
+
+impl UrKind {
+    /// UR type name used in frames
+    pub fn ur_type(&self) -> &'static str {
+        match self {
+            UrKind::Pczt => "zcash-pczt",
+            UrKind::ViewingKey => "zcash-accounts",
+            UrKind::Transaction => "bytes",
+        }
+    }
+
+    fn from_ur_type(ur_type: &str) -> Option<Self> {
+        match ur_type {
+            "zcash-pczt" => Some(UrKind::Pczt),
+            "zcash-accounts" => Some(UrKind::ViewingKey),
+            "bytes" => Some(UrKind::Transaction),
+            _ => None,
+        }
+    }
+}
+
+/// Data exchanged through animated QR codes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UrPayload {
+    /// Serialized PCZT
+    Pczt(Vec<u8>),
+    /// Viewing key for setting up a watch-only wallet
+    ViewingKey(WatchOnlyKey),
+    /// Serialized transaction
+    Transaction(Vec<u8>),
+}
+
+impl UrPayload {
+    /// Kind of this payload
+    pub fn kind(&self) -> UrKind {
+        match self {
+            UrPayload::Pczt(_) => UrKind::Pczt,
+            UrPayload::ViewingKey(_) => UrKind::ViewingKey,
+            UrPayload::Transaction(_) => UrKind::Transaction,
+        }
+    }
+
+    /// Encode the payload as UR frames to display in a loop
+    ///
+    /// Payloads that fit in one fragment produce a single-part UR. Longer ones
+    /// produce twice as many fountain parts as fragments, so a scanner that
+    /// misses some frames usually finishes within one pass of the sequence.
+    pub fn encode_frames(&self, max_fragment_len: usize) -> anyhow::Result<Vec<String>> {
+        let message = self.to_cbor()?;
+        let ur_type = self.kind().ur_type();
+
+        let mut encoder = ur::Encoder::new(&message, max_fragment_len, ur_type)
+            .map_err(|e| anyhow::anyhow!("Failed to encode UR: {e:?}"))?;
+        if encoder.fragment_count() == 1 {
+            return Ok(vec![ur::encode(&message, &ur::Type::Custom(ur_type))]);
+        }
+
+        (0..encoder.fragment_count() * 2)
+            .map(|_| {
+                encoder
+                    .next_part()
+                    .map_err(|e| anyhow::anyhow!("Failed to encode UR: {e:?}"))
+            })
+            .collect()
+    }
+
+    fn to_cbor(&self) -> anyhow::Result<Vec<u8>> {
+        let value = match self {
+            UrPayload::Pczt(data) => Value::Map(vec![(1.into(), Value::Bytes(data.clone()))]),
+            UrPayload::ViewingKey(key) => {
+                let account = Value::Map(vec![
+                    (1.into(), Value::Text(key.ufvk.clone())),
+                    (2.into(), 0.into()),
+                ]);
+                let mut accounts = Vec::new();
+                if let Some(fingerprint) = &key.seed_fingerprint {
+                    let fingerprint = hex::decode(fingerprint)
+                        .map_err(|_| anyhow::anyhow!("Invalid seed fingerprint"))?;
+                    accounts.push((1.into(), Value::Bytes(fingerprint)));
+                }
+                accounts.push((
+                    2.into(),
+                    Value::Array(vec![Value::Tag(UFVK_TAG, Box::new(account))]),
+                ));
+                Value::Map(accounts)
+            }
+            UrPayload::Transaction(data) => Value::Bytes(data.clone()),
+        };
+
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&value, &mut cbor)
+            .map_err(|e| anyhow::anyhow!("Failed to encode CBOR: {e}"))?;
+        Ok(cbor)
+    }
+
+    fn from_cbor(kind: UrKind, cbor: &[u8]) -> anyhow::Result<Self> {
+        let value: Value = ciborium::from_reader(cbor)
+            .map_err(|e| anyhow::anyhow!("Invalid CBOR in UR: {e}"))?;
+        let invalid = || anyhow::anyhow!("Unexpected {} contents", kind.ur_type());
+
+        match kind {
+            UrKind::Pczt => {
+                let data = map_get(&value, 1).and_then(Value::as_bytes).ok_or_else(invalid)?;
+                Ok(UrPayload::Pczt(data.clone()))
+            }
+            UrKind::ViewingKey => {
+                let account = map_get(&value, 2)
+                    .and_then(Value::as_array)
+                    .and_then(|accounts| accounts.first())
+                    .map(|account| match account {
+                        Value::Tag(_, inner) => inner.as_ref(),
+                        other => other,
+                    })
+                    .ok_or_else(invalid)?;
+                let ufvk = map_get(account, 1)
+                    .and_then(Value::as_text)
+                    .ok_or_else(invalid)?;
+                let seed_fingerprint = map_get(&value, 1)
+                    .and_then(Value::as_bytes)
+                    .map(hex::encode);
+                Ok(UrPayload::ViewingKey(WatchOnlyKey {
+                    ufvk: ufvk.to_string(),
+                    seed_fingerprint,
+                }))
+            }
+            UrKind::Transaction => {
+                let data = value.as_bytes().ok_or_else(invalid)?;
+                Ok(UrPayload::Transaction(data.clone()))
+            }
+        }
+    }
+}
+
+/// Look up an integer key in a CBOR map
+fn map_get(value: &Value, key: u64) -> Option<&Value> {
+    value.as_map()?.iter().find_map(|(k, v)| match k {
+        Value::Integer(i) if u64::try_from(*i).ok() == Some(key) => Some(v),
+        _ => None,
+    })
+}
+
+/// Split a lowercase UR frame into its type and, for multi-part frames,
+/// its sequence number and fragment count
+fn parse_frame_header(frame: &str) -> anyhow::Result<(&str, Option<(usize, usize)>)> {
+    let rest = frame
+        .strip_prefix("ur:")
+        .ok_or_else(|| anyhow::anyhow!("Not a UR frame"))?;
+    match rest.split('/').collect::<Vec<_>>().as_slice() {
+        [ur_type, _] => Ok((*ur_type, None)),
+        [ur_type, sequence, _] => {
+            let (index, count) = sequence
+                .split_once('-')
+                .and_then(|(index, count)| Some((index.parse().ok()?, count.parse().ok()?)))
+                .ok_or_else(|| anyhow::anyhow!("Invalid UR sequence: {sequence}"))?;
+            Ok((*ur_type, Some((index, count))))
+        }
+        _ => anyhow::bail!("Malformed UR frame"),
+    }
+}
+
+/// Progress of scanning an animated QR code
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct UrScanProgress {
+    /// Kind of data being scanned
+    pub kind: UrKind,
+    /// Estimated completion from 0 to 100
+    pub percent: u8,
+    /// Whether the payload has been fully received
+    pub complete: bool,
+}
+
+/// Incrementally decodes scanned UR frames
+#[derive(Default)]
+pub struct UrScanner {
+    decoder: ur::Decoder,
+    kind: Option<UrKind>,
+    fragment_count: usize,
+    received: BTreeSet<usize>,
+    message: Option<Vec<u8>>,
+}
+
+impl UrScanner {
+    /// Create a scanner with no frames received
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a scanned frame, returning the scan progress
+    ///
+    /// Frames may arrive in any order and repeats are ignored. Frames of a
+    /// different kind than the first one are rejected.
+    pub fn receive(&mut self, frame: &str) -> anyhow::Result<UrScanProgress> {
+        // QR codes carry URs in uppercase alphanumeric mode
+        let frame = frame.trim().to_ascii_lowercase();
+        let (ur_type, sequence) = parse_frame_header(&frame)?;
+        let kind = UrKind::from_ur_type(ur_type)
+            .ok_or_else(|| anyhow::anyhow!("Unsupported UR type: {ur_type}"))?;
+        if let Some(current) = self.kind {
+            if current != kind {
+                anyhow::bail!(
+                    "Scanned a {ur_type} frame while scanning a {}",
+                    current.ur_type()
+                );
+            }
+        }
+        self.kind = Some(kind);
+
+        if self.message.is_none() {
+            match sequence {
+                None => {
+                    let (_, message) = ur::decode(&frame)
+                        .map_err(|e| anyhow::anyhow!("Invalid UR frame: {e:?}"))?;
+                    self.message = Some(message);
+                }
+                Some((index, count)) => {
+                    self.decoder
+                        .receive(&frame)
+                        .map_err(|e| anyhow::anyhow!("Invalid UR frame: {e:?}"))?;
+                    self.fragment_count = count;
+                    self.received.insert(index);
+                    if self.decoder.complete() {
+                        self.message = self
+                            .decoder
+                            .message()
+                            .map_err(|e| anyhow::anyhow!("Failed to decode UR: {e:?}"))?;
+                    }
+                }
+            }
+        }
+
+        Ok(self.progress(kind))
+    }
+
+    fn progress(&self, kind: UrKind) -> UrScanProgress {
+        let complete = self.message.is_some();
+        // Mixed fountain parts carry less than a full fragment, so the count
+        // of distinct frames only estimates progress until decoding succeeds
+        let percent = if complete {
+            100
+        } else if self.fragment_count == 0 {
+            0
+        } else {
+            (self.received.len() * 100 / self.fragment_count).min(99) as u8
+        };
+        UrScanProgress {
+            kind,
+            percent,
+            complete,
+        }
+    }
+
+    /// The decoded payload, once every fragment has been received
+    pub fn payload(&self) -> anyhow::Result<Option<UrPayload>> {
+        match (self.kind, &self.message) {
+            (Some(kind), Some(message)) => Ok(Some(UrPayload::from_cbor(kind, message)?)),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(frames: &[String]) -> (UrScanner, UrScanProgress) {
+        let mut scanner = UrScanner::new();
+        let mut progress = None;
+        for frame in frames {
+            progress = Some(scanner.receive(frame).unwrap());
+        }
+        (scanner, progress.unwrap())
+    }
+
+    #[test]
+    fn viewing_key_round_trips_in_a_single_frame() {
+        let key = WatchOnlyKey {
+            ufvk: "uviewtest1example".to_string(),
+            seed_fingerprint: Some("ab".repeat(32)),
+        };
+        let frames = UrPayload::ViewingKey(key.clone())
+            .encode_frames(DEFAULT_FRAGMENT_LEN)
+            .unwrap();
+        assert_eq!(frames.len(), 1);
+        assert!(frames[0].starts_with("ur:zcash-accounts/"));
+
+        let (scanner, progress) = scan(&frames);
+        assert!(progress.complete);
+        assert_eq!(progress.kind, UrKind::ViewingKey);
+        assert_eq!(scanner.payload().unwrap(), Some(UrPayload::ViewingKey(key)));
+    }
+
+    #[test]
+    fn pczt_round_trips_from_uppercase_frames_in_any_order() {
+        let pczt: Vec<u8> = (0..2000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let frames = UrPayload::Pczt(pczt.clone()).encode_frames(200).unwrap();
+        assert!(frames.len() > 2);
+
+        // The first half of the sequence holds each fragment once; scan those
+        // backwards, as a camera joining mid-loop would
+        let mut scanner = UrScanner::new();
+        let fragments = frames.len() / 2;
+        for (i, frame) in frames[..fragments].iter().rev().enumerate() {
+            let progress = scanner.receive(&frame.to_ascii_uppercase()).unwrap();
+            assert_eq!(progress.complete, i + 1 == fragments);
+        }
+        assert_eq!(scanner.payload().unwrap(), Some(UrPayload::Pczt(pczt)));
+    }
+
+    #[test]
+    fn transaction_round_trips_with_repeated_frames() {
+        let tx = vec![0x5a; 900];
+        let mut frames = UrPayload::Transaction(tx.clone())
+            .encode_frames(100)
+            .unwrap();
+        frames.insert(1, frames[0].clone());
+
+        let (scanner, progress) = scan(&frames);
+        assert!(progress.complete);
+        assert_eq!(scanner.payload().unwrap(), Some(UrPayload::Transaction(tx)));
+    }
+
+    #[test]
+    fn frames_of_another_kind_are_rejected() {
+        let pczt = UrPayload::Pczt(vec![1; 1000]).encode_frames(100).unwrap();
+        let tx = UrPayload::Transaction(vec![2; 10])
+            .encode_frames(DEFAULT_FRAGMENT_LEN)
+            .unwrap();
+
+        let mut scanner = UrScanner::new();
+        scanner.receive(&pczt[0]).unwrap();
+        assert!(scanner.receive(&tx[0]).is_err());
+        assert_eq!(scanner.payload().unwrap(), None);
+    }
+}
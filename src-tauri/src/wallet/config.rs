@@ -1,19 +1,201 @@
 //! Configuration for Zcash wallet operations.
 
-use std::path::PathBuf;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use zcash_client_backend::data_api::wallet::ConfirmationsPolicy;
+use zcash_protocol::consensus::{
+    BlockHeight, NetworkType, NetworkUpgrade, Parameters, MAIN_NETWORK, TEST_NETWORK,
+};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::wallet::{DbKey, IkkiError, WrappedDbKey};
+
+/// Default birthday for new testnet wallets
+const TESTNET_DEFAULT_BIRTHDAY: u64 = 3717528;
+
+/// Network upgrade activation heights of a local regtest chain
+///
+/// Upgrades without a height never activate. The defaults activate every
+/// upgrade at height 1, matching a zebrad regtest node with default settings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ActivationHeights {
+    pub overwinter: Option<u32>,
+    pub sapling: Option<u32>,
+    pub blossom: Option<u32>,
+    pub heartwood: Option<u32>,
+    pub canopy: Option<u32>,
+    pub nu5: Option<u32>,
+    pub nu6: Option<u32>,
+}
+
+impl Default for ActivationHeights {
+    fn default() -> Self {
+        Self {
+            overwinter: Some(1),
+            sapling: Some(1),
+            blossom: Some(1),
+            heartwood: Some(1),
+            canopy: Some(1),
+            nu5: Some(1),
+            nu6: Some(1),
+        }
+    }
+}
+
+impl ActivationHeights {
+    /// Parse `upgrade=height` pairs separated by commas, e.g. `canopy=1,nu5=100`
+    ///
+    /// Upgrades that are not listed keep their default height of 1; a height
+    /// of `none` disables an upgrade.
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let mut heights = Self::default();
+        for pair in spec.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (name, height) = pair
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Expected upgrade=height, got {pair}"))?;
+            let height = match height.trim() {
+                "none" => None,
+                h => Some(h.parse().map_err(|_| anyhow::anyhow!("Invalid height: {h}"))?),
+            };
+            let slot = match name.trim().to_ascii_lowercase().as_str() {
+                "overwinter" => &mut heights.overwinter,
+                "sapling" => &mut heights.sapling,
+                "blossom" => &mut heights.blossom,
+                "heartwood" => &mut heights.heartwood,
+                "canopy" => &mut heights.canopy,
+                "nu5" => &mut heights.nu5,
+                "nu6" => &mut heights.nu6,
+                other => anyhow::bail!("Unknown network upgrade: {other}"),
+            };
+            *slot = height;
+        }
+        Ok(heights)
+    }
+
+    fn activation_height(&self, nu: NetworkUpgrade) -> Option<BlockHeight> {
+        let height = match nu {
+            NetworkUpgrade::Overwinter => self.overwinter,
+            NetworkUpgrade::Sapling => self.sapling,
+            NetworkUpgrade::Blossom => self.blossom,
+            NetworkUpgrade::Heartwood => self.heartwood,
+            NetworkUpgrade::Canopy => self.canopy,
+            NetworkUpgrade::Nu5 => self.nu5,
+            NetworkUpgrade::Nu6 => self.nu6,
+            // Later upgrades are not supported on regtest yet
+            #[allow(unreachable_patterns)]
+            _ => None,
+        };
+        height.map(BlockHeight::from_u32)
+    }
+}
 
 /// Network type
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// Also serves as the consensus parameters for the wallet database, sync and
+/// transaction building.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Network {
     Mainnet,
     Testnet,
+    /// Local regtest chain with configurable upgrade activation heights
+    Regtest(ActivationHeights),
+}
+
+impl Network {
+    /// Address encoding network for this network
+    pub fn network_type(&self) -> NetworkType {
+        match self {
+            Network::Mainnet => NetworkType::Main,
+            Network::Testnet => NetworkType::Test,
+            Network::Regtest(_) => NetworkType::Regtest,
+        }
+    }
+
+    /// Birthday used for new wallets when none is given
+    pub fn default_birthday_height(&self) -> u64 {
+        match self {
+            Network::Testnet => TESTNET_DEFAULT_BIRTHDAY,
+            // Wallets created before the Sapling activation scan from it
+            _ => self
+                .activation_height(NetworkUpgrade::Sapling)
+                .map_or(1, |h| u64::from(u32::from(h))),
+        }
+    }
+}
+
+impl std::fmt::Display for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet => "testnet",
+            Network::Regtest(_) => "regtest",
+        })
+    }
+}
+
+impl Parameters for Network {
+    fn network_type(&self) -> NetworkType {
+        Network::network_type(self)
+    }
+
+    fn activation_height(&self, nu: NetworkUpgrade) -> Option<BlockHeight> {
+        match self {
+            Network::Mainnet => MAIN_NETWORK.activation_height(nu),
+            Network::Testnet => TEST_NETWORK.activation_height(nu),
+            Network::Regtest(heights) => heights.activation_height(nu),
+        }
+    }
+}
+
+/// Confirmation depths required before received funds become spendable
+///
+/// Trusted funds are change and other outputs of the wallet's own transactions;
+/// untrusted funds are received from third parties. Defaults follow ZIP 315.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConfirmationSettings {
+    /// Confirmations required for trusted (wallet-internal) funds
+    pub trusted: u32,
+    /// Confirmations required for untrusted (externally received) funds
+    pub untrusted: u32,
+}
+
+impl Default for ConfirmationSettings {
+    fn default() -> Self {
+        Self {
+            trusted: 3,
+            untrusted: 10,
+        }
+    }
+}
+
+impl ConfirmationSettings {
+    /// Convert to the policy used for balance and note selection
+    pub fn policy(&self) -> anyhow::Result<ConfirmationsPolicy> {
+        let trusted = NonZeroU32::new(self.trusted)
+            .ok_or_else(|| anyhow::anyhow!("Trusted confirmations must be at least 1"))?;
+        let untrusted = NonZeroU32::new(self.untrusted)
+            .ok_or_else(|| anyhow::anyhow!("Untrusted confirmations must be at least 1"))?;
+        ConfirmationsPolicy::new(trusted, untrusted, false).map_err(|_| {
+            anyhow::anyhow!(
+                "Trusted confirmations ({}) cannot exceed untrusted confirmations ({})",
+                self.trusted,
+                self.untrusted
+            )
+        })
+    }
 }
 
 /// Configuration for Zcash wallet and network operations
 #[derive(Debug, Clone)]
 pub struct ZcashConfig {
-    /// BIP-39 seed phrase (24 words)
-    pub seed_phrase: String,
+    /// BIP-39 seed phrase (24 words), `None` for watch-only wallets
+    pub seed_phrase: Option<String>,
+    /// Viewing key of a watch-only wallet
+    pub viewing_key: Option<WatchOnlyKey>,
     /// Wallet birthday height for faster sync
     pub birthday_height: u64,
     /// Lightwalletd server URL
@@ -22,6 +204,12 @@ pub struct ZcashConfig {
     pub data_dir: PathBuf,
     /// Network (mainnet or testnet)
     pub network: Network,
+    /// Confirmation depths for balances and spends
+    pub confirmations: ConfirmationSettings,
+    /// Key of the encrypted wallet database, `None` if it is not encrypted
+    pub db_key: Option<DbKey>,
+    /// Idle minutes after which the seed is locked away, 0 to never lock
+    pub auto_lock_minutes: u32,
 }
 
 impl ZcashConfig {
@@ -30,6 +218,16 @@ impl ZcashConfig {
         self.data_dir.join("wallet.db")
     }
 
+    /// Get the path to the stored wallet config file
+    pub fn wallet_config_path(&self) -> PathBuf {
+        self.data_dir.join(WALLET_CONFIG_FILE)
+    }
+
+    /// Get the path to the address book database file
+    pub fn address_book_db_path(&self) -> PathBuf {
+        self.data_dir.join("address_book.db")
+    }
+
     /// Get the path to the data directory, creating it if needed
     pub fn ensure_data_dir(&self) -> anyhow::Result<PathBuf> {
         std::fs::create_dir_all(&self.data_dir)?;
@@ -51,16 +249,314 @@ impl ZcashConfig {
             anyhow::bail!("Seed phrase must be 24 words, got {}", words.len());
         }
 
-        let data_dir = dirs::home_dir()
-            .unwrap_or_else(|| PathBuf::from("."))
-            .join(".ikki");
+        let data_dir = default_data_dir();
 
         Ok(Self {
-            seed_phrase: seed_phrase.to_string(),
-            birthday_height: birthday_height.unwrap_or(3717528),
-            lightwalletd_url: "https://testnet.zec.rocks:443".to_string(),
+            seed_phrase: Some(seed_phrase.to_string()),
+            viewing_key: None,
+            birthday_height: birthday_height.unwrap_or(TESTNET_DEFAULT_BIRTHDAY),
+            lightwalletd_url: DEFAULT_LIGHTWALLETD_URL.to_string(),
             data_dir,
             network: Network::Testnet,
+            confirmations: ConfirmationSettings::default(),
+            db_key: None,
+            auto_lock_minutes: DEFAULT_AUTO_LOCK_MINUTES,
         })
     }
+
+    /// Create configuration for a watch-only wallet
+    ///
+    /// The wallet can sync, show balances and create PCZTs, but transactions
+    /// must be signed by an offline instance holding the seed.
+    pub fn watch_only(viewing_key: WatchOnlyKey, birthday_height: Option<u64>) -> Self {
+        Self {
+            seed_phrase: None,
+            viewing_key: Some(viewing_key),
+            birthday_height: birthday_height.unwrap_or(TESTNET_DEFAULT_BIRTHDAY),
+            lightwalletd_url: DEFAULT_LIGHTWALLETD_URL.to_string(),
+            data_dir: default_data_dir(),
+            network: Network::Testnet,
+            confirmations: ConfirmationSettings::default(),
+            db_key: None,
+            auto_lock_minutes: DEFAULT_AUTO_LOCK_MINUTES,
+        }
+    }
+
+    /// Whether the wallet holds no spending key
+    pub fn is_watch_only(&self) -> bool {
+        self.seed_phrase.is_none()
+    }
+}
+
+/// Viewing key exported from a seed wallet to set up a watch-only wallet
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WatchOnlyKey {
+    /// Unified full viewing key encoding
+    pub ufvk: String,
+    /// Hex-encoded ZIP 32 fingerprint of the seed the key was derived from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed_fingerprint: Option<String>,
+}
+
+/// Idle minutes before a wallet locks unless configured otherwise
+pub const DEFAULT_AUTO_LOCK_MINUTES: u32 = 15;
+
+/// lightwalletd server used unless configured otherwise
+pub const DEFAULT_LIGHTWALLETD_URL: &str = "https://testnet.zec.rocks:443";
+
+/// Name of the stored wallet config file in the data directory
+pub const WALLET_CONFIG_FILE: &str = "wallet_config.json";
+
+/// Default data directory (`~/.ikki`), shared by the app and the CLI
+pub fn default_data_dir() -> PathBuf {
+    dirs::home_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join(".ikki")
+}
+
+/// Wallet configuration persisted in `wallet_config.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredWalletConfig {
+    /// Seed phrase of a wallet without a password, empty for watch-only
+    /// wallets and once a password seals it into `encrypted_seed`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub seed: String,
+    /// Seed phrase sealed under the database key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encrypted_seed: Option<String>,
+    pub birthday_height: Option<u64>,
+    #[serde(default)]
+    pub confirmations: ConfirmationSettings,
+    /// Network to use instead of testnet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<Network>,
+    /// lightwalletd server to use instead of the default
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lightwalletd_url: Option<String>,
+    /// Viewing key of a watch-only wallet, used instead of the seed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub viewing_key: Option<WatchOnlyKey>,
+    /// Database key sealed under the unlock password, set once a password is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub wrapped_db_key: Option<WrappedDbKey>,
+    /// Idle minutes after which the wallet locks, 0 to never lock
+    #[serde(default = "default_auto_lock_minutes")]
+    pub auto_lock_minutes: u32,
+}
+
+fn default_auto_lock_minutes() -> u32 {
+    DEFAULT_AUTO_LOCK_MINUTES
+}
+
+impl StoredWalletConfig {
+    /// Load the config file, returning `None` if it does not exist
+    pub fn load(path: &Path) -> anyhow::Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let contents = std::fs::read_to_string(path)?;
+        Ok(Some(serde_json::from_str(&contents)?))
+    }
+
+    /// Write the config file, readable only by the current user
+    ///
+    /// The new contents are written to a temporary file that then replaces the
+    /// old one, so a crash never leaves a partially written config.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let json = serde_json::to_string_pretty(self)?;
+        let mut temp_name = path.as_os_str().to_owned();
+        temp_name.push(".tmp");
+        let temp_path = PathBuf::from(temp_name);
+        if temp_path.exists() {
+            std::fs::remove_file(&temp_path)?;
+        }
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        // Set restrictive permissions on Unix
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(0o600);
+        }
+        let mut file = options.open(&temp_path)?;
+        std::io::Write::write_all(&mut file, json.as_bytes())?;
+        file.sync_all()?;
+        drop(file);
+
+        std::fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    /// Whether the wallet databases are encrypted with an unlock password
+    pub fn has_password(&self) -> bool {
+        self.wrapped_db_key.is_some()
+    }
+
+    /// Recover the wallet database key with the unlock password
+    ///
+    /// Returns `None` for wallets without a password, `WalletLocked` if the
+    /// wallet has one but none was given, and `InvalidPassword` if it is wrong.
+    pub fn db_key(&self, password: Option<&str>) -> anyhow::Result<Option<DbKey>> {
+        let Some(wrapped) = &self.wrapped_db_key else {
+            return Ok(None);
+        };
+        let password = password.ok_or(IkkiError::WalletLocked)?;
+        Ok(Some(wrapped.unwrap_with(password)?))
+    }
+
+    /// Seal the database key under a new unlock password
+    ///
+    /// Pass the current key when changing the password; the databases stay
+    /// encrypted with it. Without one a new key is generated, and once the
+    /// config is saved the plaintext databases must be encrypted with the
+    /// returned key.
+    pub fn set_password(
+        &mut self,
+        password: &str,
+        current: Option<DbKey>,
+    ) -> anyhow::Result<DbKey> {
+        let key = current.unwrap_or_else(DbKey::generate);
+        self.wrapped_db_key = Some(key.wrap(password)?);
+        self.seal_seed(&key)?;
+        Ok(key)
+    }
+
+    /// Go back to no password, storing the seed phrase in plaintext again
+    ///
+    /// Only valid while the databases are still plaintext.
+    pub fn remove_password(&mut self, key: &DbKey) -> anyhow::Result<()> {
+        self.seed = self.seed_phrase(Some(key))?.to_string();
+        self.encrypted_seed = None;
+        self.wrapped_db_key = None;
+        Ok(())
+    }
+
+    /// Replace a plaintext seed phrase with one sealed under the database key
+    pub fn seal_seed(&mut self, key: &DbKey) -> anyhow::Result<()> {
+        if self.seed.is_empty() {
+            return Ok(());
+        }
+        self.encrypted_seed = Some(key.seal(self.seed.as_bytes())?);
+        self.seed.zeroize();
+        Ok(())
+    }
+
+    /// The seed phrase, unsealing it with the database key if it is encrypted
+    ///
+    /// A sealed seed fails with `WalletLocked` without the key.
+    pub fn seed_phrase(&self, key: Option<&DbKey>) -> anyhow::Result<Zeroizing<String>> {
+        let Some(sealed) = &self.encrypted_seed else {
+            return Ok(Zeroizing::new(self.seed.clone()));
+        };
+        let key = key.ok_or(IkkiError::WalletLocked)?;
+        let phrase = key.open(sealed)?;
+        let phrase = std::str::from_utf8(&phrase).map_err(|_| anyhow::anyhow!("Invalid seed"))?;
+        Ok(Zeroizing::new(phrase.to_string()))
+    }
+
+    /// Build the wallet configuration this stored config describes
+    ///
+    /// A sealed seed phrase needs the database key.
+    pub fn to_config(&self, data_dir: PathBuf, key: Option<&DbKey>) -> anyhow::Result<ZcashConfig> {
+        let mut config = match &self.viewing_key {
+            Some(viewing_key) => ZcashConfig::watch_only(viewing_key.clone(), self.birthday_height),
+            None => {
+                let seed = self.seed_phrase(key)?;
+                ZcashConfig::from_seed_with_birthday(&seed, self.birthday_height)?
+            }
+        };
+        config.data_dir = data_dir;
+        config.confirmations = self.confirmations;
+        config.auto_lock_minutes = self.auto_lock_minutes;
+        if let Some(network) = self.network {
+            config.network = network;
+            config.birthday_height = self
+                .birthday_height
+                .unwrap_or_else(|| network.default_birthday_height());
+        }
+        if let Some(url) = &self.lightwalletd_url {
+            config.lightwalletd_url = url.clone();
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PHRASE: &str = "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo wrong";
+
+    fn stored_config() -> StoredWalletConfig {
+        serde_json::from_value(serde_json::json!({
+            "seed": PHRASE,
+            "birthday_height": null,
+        }))
+        .unwrap()
+    }
+
+    fn is_locked(error: anyhow::Error) -> bool {
+        matches!(
+            error.downcast_ref::<IkkiError>(),
+            Some(IkkiError::WalletLocked)
+        )
+    }
+
+    fn is_invalid_password(error: anyhow::Error) -> bool {
+        matches!(
+            error.downcast_ref::<IkkiError>(),
+            Some(IkkiError::InvalidPassword)
+        )
+    }
+
+    #[test]
+    fn setting_a_password_seals_the_seed() {
+        let mut stored = stored_config();
+        stored.set_password("hunter2", None).unwrap();
+        assert!(stored.seed.is_empty());
+
+        let json = serde_json::to_string(&stored).unwrap();
+        assert!(!json.contains("zoo"));
+
+        let stored: StoredWalletConfig = serde_json::from_str(&json).unwrap();
+        assert!(is_locked(stored.seed_phrase(None).unwrap_err()));
+        assert!(is_locked(stored.db_key(None).unwrap_err()));
+        assert!(is_invalid_password(
+            stored.db_key(Some("hunter3")).unwrap_err()
+        ));
+        assert!(is_invalid_password(
+            stored.seed_phrase(Some(&DbKey::generate())).unwrap_err()
+        ));
+
+        let key = stored.db_key(Some("hunter2")).unwrap();
+        assert_eq!(stored.seed_phrase(key.as_ref()).unwrap().as_str(), PHRASE);
+    }
+
+    #[test]
+    fn changing_the_password_keeps_the_sealed_seed() {
+        let mut stored = stored_config();
+        stored.set_password("old", None).unwrap();
+        let sealed = stored.encrypted_seed.clone();
+
+        let current = stored.db_key(Some("old")).unwrap();
+        stored.set_password("new", current).unwrap();
+        assert_eq!(stored.encrypted_seed, sealed);
+        assert!(is_invalid_password(stored.db_key(Some("old")).unwrap_err()));
+
+        let key = stored.db_key(Some("new")).unwrap();
+        assert_eq!(stored.seed_phrase(key.as_ref()).unwrap().as_str(), PHRASE);
+    }
+
+    #[test]
+    fn removing_the_password_restores_the_plaintext_seed() {
+        let mut stored = stored_config();
+        let key = stored.set_password("hunter2", None).unwrap();
+
+        stored.remove_password(&key).unwrap();
+        assert!(!stored.has_password());
+        assert!(stored.encrypted_seed.is_none());
+        assert_eq!(stored.seed_phrase(None).unwrap().as_str(), PHRASE);
+    }
 }
@@ -2,93 +2,205 @@
 //!
 //! Provides wallet initialization, sync, and transaction operations.
 
-use bip0039::{English, Mnemonic};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
 use rand_core::OsRng;
-use tonic::transport::{Channel, ClientTlsConfig};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 use zcash_client_backend::data_api::wallet::{
-    ConfirmationsPolicy, SpendingKeys, create_proposed_transactions,
+    ConfirmationsPolicy, SpendingKeys, create_pczt_from_proposal, create_proposed_transactions,
+    extract_and_store_transaction_from_pczt, propose_send_max_transfer,
     propose_standard_transfer_to_address,
 };
-use zcash_client_backend::data_api::{AccountBirthday, AccountPurpose, WalletRead, WalletWrite};
-use zcash_client_backend::fees::StandardFeeRule;
-use zcash_client_backend::keys::UnifiedSpendingKey;
-use zcash_client_backend::proto::service::{
-    self, ChainSpec, RawTransaction, compact_tx_streamer_client::CompactTxStreamerClient,
+use zcash_client_backend::data_api::{
+    Account as _, AccountBirthday, AccountPurpose, MaxSpendMode, WalletRead, WalletWrite,
+    Zip32Derivation,
 };
+use zcash_client_backend::fees::StandardFeeRule;
+use zcash_client_backend::keys::{UnifiedFullViewingKey, UnifiedSpendingKey};
 use zcash_client_backend::sync::run as sync_run;
 use zcash_client_backend::wallet::OvkPolicy;
 use zcash_client_memory::MemBlockCache;
-use zcash_client_sqlite::WalletDb;
+use zcash_client_sqlite::{AccountUuid, WalletDb};
 use zcash_client_sqlite::error::SqliteClientError;
 use zcash_client_sqlite::util::SystemClock;
 use zcash_client_sqlite::wallet::init::init_wallet_db;
 use zcash_proofs::prover::LocalTxProver;
 use zcash_protocol::ShieldedProtocol;
-use zcash_protocol::consensus::TEST_NETWORK;
+use zcash_protocol::consensus::BlockHeight;
 use zcash_protocol::memo::MemoBytes;
 use zcash_protocol::value::Zatoshis;
+use zeroize::{Zeroize, Zeroizing};
 use zip32::AccountId;
-
-use crate::wallet::ZcashConfig;
+use zip32::fingerprint::SeedFingerprint;
+
+use crate::wallet::history::{self, txid_to_hex};
+use crate::wallet::offline::{account_spending_key, parse_seed_fingerprint, seed_from_phrase};
+use crate::wallet::{addresses, database, export, inspect, notes, offline};
+use crate::wallet::{
+    AddressBook, AddressInspection, AddressRecord, AddressType, ConfirmationSettings, DbKey,
+    IkkiError, ExportFormat, ExportRange, GrpcClient, Lightwalletd, Network, NewAddress,
+    PcztSummary, TransactionDetails, TransactionMemo, TransactionPage, TransactionQuery,
+    TransactionRecord, UnspentNote, WatchOnlyKey, ZcashConfig, connect_lightwalletd,
+    receiver_encodings,
+};
 
 const SYNC_BATCH_SIZE: u32 = 1000;
 
+/// Number of most recent scanned blocks compared across a sync to detect reorgs
+const REORG_WINDOW: u32 = 100;
+
 /// Result of sending a transaction
 pub struct SendResult {
-    /// Transaction ID as string
+    /// ID of the transaction paying the recipient
     pub txid: String,
-    /// Fee paid in zatoshis
+    /// IDs of all transactions created for the payment, in broadcast order.
+    /// TEX payments produce two: the transfer to an ephemeral transparent
+    /// address, then the payment from it to the recipient.
+    pub txids: Vec<String>,
+    /// Amount paid to the recipient, in zatoshis
+    pub amount: u64,
+    /// Total fee paid across all transactions, in zatoshis
     pub fee: u64,
 }
 
-/// Balance breakdown by shielded pool
-#[derive(Default)]
+/// Unsigned PCZT created by a watch-only wallet
+pub struct PcztProposal {
+    /// Serialized PCZT, ready for an offline signer
+    pub pczt: Vec<u8>,
+    /// Amount paid to the recipient, in zatoshis
+    pub amount: u64,
+    /// ZIP-317 fee, in zatoshis
+    pub fee: u64,
+}
+
+/// Maximum amount that can be sent in a single payment
+#[derive(Debug, Clone, Copy)]
+pub struct MaxTransfer {
+    /// Amount the recipient receives, in zatoshis
+    pub amount: u64,
+    /// ZIP-317 fee, in zatoshis
+    pub fee: u64,
+}
+
+/// Balance of a single value pool, split by spendability
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PoolBalance {
+    /// Value spendable under the wallet's confirmation policy
+    pub spendable: u64,
+    /// Change from the wallet's own transactions awaiting confirmations
+    pub change_pending_confirmation: u64,
+    /// Received value awaiting confirmations or scanning before it can be spent
+    pub value_pending_spendability: u64,
+}
+
+impl PoolBalance {
+    fn add(&mut self, balance: &zcash_client_backend::data_api::Balance) {
+        self.spendable += u64::from(balance.spendable_value());
+        self.change_pending_confirmation += u64::from(balance.change_pending_confirmation());
+        self.value_pending_spendability += u64::from(balance.value_pending_spendability());
+    }
+
+    /// All value in the pool, spendable or not
+    pub fn total(&self) -> u64 {
+        self.spendable + self.change_pending_confirmation + self.value_pending_spendability
+    }
+}
+
+/// Balance breakdown by value pool
+#[derive(Debug, Clone, Copy, Default)]
 pub struct BalanceBreakdown {
-    /// Transparent pool balance in zatoshis
-    pub transparent: u64,
-    /// Sapling pool balance in zatoshis
-    pub sapling: u64,
-    /// Orchard pool balance in zatoshis
-    pub orchard: u64,
+    /// Transparent pool balance
+    pub transparent: PoolBalance,
+    /// Sapling pool balance
+    pub sapling: PoolBalance,
+    /// Orchard pool balance
+    pub orchard: PoolBalance,
+    /// Height up to which the wallet has scanned every block
+    pub fully_scanned_height: Option<u32>,
+    /// Chain tip height known to the wallet
+    pub chain_tip_height: Option<u32>,
 }
 
-/// Transaction record for display
+impl BalanceBreakdown {
+    /// All value in the shielded pools, spendable or not
+    pub fn shielded_total(&self) -> u64 {
+        self.sapling.total() + self.orchard.total()
+    }
+
+    /// All value across all pools, spendable or not
+    pub fn total(&self) -> u64 {
+        self.shielded_total() + self.transparent.total()
+    }
+
+    /// Spendable value across the shielded pools
+    pub fn shielded_spendable(&self) -> u64 {
+        self.sapling.spendable + self.orchard.spendable
+    }
+
+    /// Spendable value across all pools
+    pub fn spendable(&self) -> u64 {
+        self.shielded_spendable() + self.transparent.spendable
+    }
+}
+
+/// Chain reorganization detected during sync
 #[derive(Debug, Clone)]
-pub struct TransactionRecord {
-    /// Transaction ID as hex string
-    pub txid: String,
-    /// Amount in zatoshis (negative for sent)
-    pub amount: i64,
-    /// Unix timestamp (approximate from block height)
-    pub timestamp: u64,
-    /// Whether this is a sent transaction
-    pub is_sent: bool,
-    /// Memo text if available
-    pub memo: Option<String>,
-    /// Whether this transaction is pending (unmined)
-    pub is_pending: bool,
-    /// Block height where transaction was mined (None if pending)
-    pub mined_height: Option<u32>,
+pub struct ChainReorg {
+    /// Scanned chain tip before the sync
+    pub old_height: u64,
+    /// Last block shared by the old and new chains (blocks above it were replaced)
+    pub new_height: u64,
+    /// Wallet transactions that were un-mined or moved to another height
+    pub affected_txids: Vec<String>,
+}
+
+/// Scanned chain state captured before a sync, used to detect reorgs
+struct ChainSnapshot {
+    /// Block hashes of the most recent scanned blocks, by height
+    block_hashes: HashMap<u32, Vec<u8>>,
+    /// Mined height of every mined wallet transaction, by raw txid
+    mined_heights: HashMap<Vec<u8>, u32>,
+    /// Highest scanned block height
+    tip_height: Option<u32>,
 }
 
-type IkkiWalletDb =
-    WalletDb<rusqlite::Connection, zcash_protocol::consensus::TestNetwork, SystemClock, OsRng>;
+type IkkiWalletDb = WalletDb<rusqlite::Connection, Network, SystemClock, OsRng>;
+
+/// Name of the wallet's own account in the wallet database
+///
+/// Each wallet holds a single account. A FROST multisig account is not added
+/// here; its coordinator runs a separate watch-only wallet created with
+/// `import-viewing-key < group.json`. Accounts without this name, e.g. added
+/// to `wallet.db` by another tool, are never included in the balance or spent
+/// from.
+const PRIMARY_ACCOUNT_NAME: &str = "ikki-wallet";
+
+/// Find the wallet's own account among the accounts in the database
+fn find_primary_account(db: &IkkiWalletDb) -> anyhow::Result<Option<AccountUuid>> {
+    for account_id in db.get_account_ids()? {
+        let account = db.get_account(account_id)?;
+        if account.is_some_and(|account| account.name() == Some(PRIMARY_ACCOUNT_NAME)) {
+            return Ok(Some(account_id));
+        }
+    }
+    Ok(None)
+}
+
+type IkkiProposal =
+    zcash_client_backend::proposal::Proposal<StandardFeeRule, zcash_client_sqlite::ReceivedNoteId>;
 
 /// Helper to build and sign transaction with proper type annotations
 fn build_and_sign_transaction(
     db: &mut IkkiWalletDb,
-    params: &zcash_protocol::consensus::TestNetwork,
+    params: &Network,
     prover: &LocalTxProver,
     spending_keys: &SpendingKeys,
-    proposal: &zcash_client_backend::proposal::Proposal<
-        StandardFeeRule,
-        zcash_client_sqlite::ReceivedNoteId,
-    >,
+    proposal: &IkkiProposal,
 ) -> anyhow::Result<::nonempty::NonEmpty<zcash_protocol::TxId>> {
     create_proposed_transactions::<
         IkkiWalletDb,
-        zcash_protocol::consensus::TestNetwork,
+        Network,
         zcash_client_backend::data_api::wallet::input_selection::GreedyInputSelectorError,
         StandardFeeRule,
         zcash_client_backend::fees::ChangeError<
@@ -108,12 +220,114 @@ fn build_and_sign_transaction(
     .map_err(|e| anyhow::anyhow!("Failed to create transaction: {e:?}"))
 }
 
+/// Helper to create a PCZT with proper type annotations
+fn build_pczt(
+    db: &mut IkkiWalletDb,
+    params: &Network,
+    account_id: <IkkiWalletDb as WalletRead>::AccountId,
+    proposal: &IkkiProposal,
+) -> anyhow::Result<pczt::Pczt> {
+    create_pczt_from_proposal::<
+        IkkiWalletDb,
+        Network,
+        zcash_client_backend::data_api::wallet::input_selection::GreedyInputSelectorError,
+        StandardFeeRule,
+        zcash_client_backend::fees::ChangeError<
+            SqliteClientError,
+            zcash_client_sqlite::ReceivedNoteId,
+        >,
+        zcash_client_sqlite::ReceivedNoteId,
+    >(db, params, account_id, OvkPolicy::Sender, proposal)
+    .map_err(|e| anyhow::anyhow!("Failed to create PCZT: {e:?}"))
+}
+
+/// Parse a recipient address for the wallet's network
+fn parse_recipient(
+    to_address: &str,
+    network: Network,
+) -> anyhow::Result<(zcash_address::ZcashAddress, zcash_keys::address::Address)> {
+    let recipient: zcash_address::ZcashAddress = to_address
+        .parse()
+        .map_err(|e| IkkiError::InvalidAddress(format!("{e}")))?;
+    let address = recipient
+        .clone()
+        .convert_if_network::<zcash_keys::address::Address>(network.network_type())
+        .map_err(|e| IkkiError::InvalidAddress(format!("{e}")))?;
+    Ok((recipient, address))
+}
+
+/// Encode an optional memo, rejecting memos for transparent recipients
+fn parse_memo(memo: Option<Vec<u8>>, is_transparent: bool) -> anyhow::Result<Option<MemoBytes>> {
+    let Some(data) = memo else {
+        return Ok(None);
+    };
+    if is_transparent {
+        return Err(IkkiError::InvalidMemo(
+            "Memos cannot be sent to transparent or TEX addresses".to_string(),
+        )
+        .into());
+    }
+    let memo = MemoBytes::from_bytes(&data)
+        .map_err(|_| IkkiError::InvalidMemo(format!("Memo is {} bytes, the limit is 512", data.len())))?;
+    Ok(Some(memo))
+}
+
+/// Map a proposal failure, keeping the amounts of insufficient-funds errors
+fn proposal_error<DE, TE, SE, FE, CE, N>(
+    error: zcash_client_backend::data_api::error::Error<DE, TE, SE, FE, CE, N>,
+) -> IkkiError
+where
+    zcash_client_backend::data_api::error::Error<DE, TE, SE, FE, CE, N>: std::fmt::Debug,
+{
+    match error {
+        zcash_client_backend::data_api::error::Error::InsufficientFunds {
+            available,
+            required,
+        } => IkkiError::InsufficientFunds {
+            required: u64::from(required),
+            available: u64::from(available),
+        },
+        zcash_client_backend::data_api::error::Error::ScanRequired => {
+            IkkiError::NotSynced("finish syncing before sending".to_string())
+        }
+        error => IkkiError::Proposal(format!("{error:?}")),
+    }
+}
+
+/// Total fee across all steps of a proposal
+fn proposal_fee(proposal: &IkkiProposal) -> u64 {
+    proposal
+        .steps()
+        .iter()
+        .map(|step| u64::from(step.balance().fee_required()))
+        .sum()
+}
+
+/// Amount paid to the recipient by the final step of a proposal
+fn proposal_amount(proposal: &IkkiProposal) -> anyhow::Result<u64> {
+    let total = proposal
+        .steps()
+        .last()
+        .transaction_request()
+        .total()
+        .map_err(|e| anyhow::anyhow!("Invalid proposal amount: {e:?}"))?;
+    Ok(u64::from(total))
+}
+
 /// Zcash wallet for Ikki
 pub struct IkkiWallet {
     config: ZcashConfig,
     db: IkkiWalletDb,
-    client: CompactTxStreamerClient<Channel>,
-    seed: [u8; 64],
+    client: GrpcClient,
+    /// The wallet's own account, `None` until `init_account` has run
+    account_id: Option<AccountUuid>,
+    /// BIP-39 seed, `None` for watch-only and locked wallets
+    seed: Option<Zeroizing<[u8; 64]>>,
+    /// Whether the seed has been zeroized until the wallet is unlocked
+    locked: bool,
+    /// When the wallet was last unlocked or spent from
+    last_used: Instant,
+    address_book: AddressBook,
 }
 
 impl IkkiWallet {
@@ -121,125 +335,435 @@ impl IkkiWallet {
     ///
     /// Initializes the wallet database and connects to lightwalletd.
     pub async fn new(config: ZcashConfig) -> anyhow::Result<Self> {
+        let client = connect_lightwalletd(&config.lightwalletd_url).await?;
+        Self::with_client(config, client)
+    }
+
+    /// Create a wallet instance that uses an existing lightwalletd connection
+    ///
+    /// Initializes the wallet database.
+    pub fn with_client(config: ZcashConfig, client: GrpcClient) -> anyhow::Result<Self> {
         // Create data directory
         config.ensure_data_dir()?;
 
         // Parse seed phrase
-        let mnemonic = Mnemonic::<English>::from_phrase(&config.seed_phrase)
-            .map_err(|e| anyhow::anyhow!("Invalid seed phrase: {e:?}"))?;
-        let seed = mnemonic.to_seed("");
+        let seed = config
+            .seed_phrase
+            .as_deref()
+            .map(|phrase| seed_from_phrase(phrase).map(Zeroizing::new))
+            .transpose()?;
 
-        // Initialize wallet database
+        // Initialize wallet database, encrypting plaintext databases once a password is set
         let db_path = config.wallet_db_path();
-        let mut db = WalletDb::for_path(&db_path, TEST_NETWORK, SystemClock, OsRng)?;
+        if let Some(key) = &config.db_key {
+            database::encrypt_plaintext_dbs(
+                &[db_path.clone(), config.address_book_db_path()],
+                key,
+            )?;
+        }
+        let conn = database::open_wallet_db(&db_path, config.db_key.as_ref(), false)?;
+        rusqlite::vtab::array::load_module(&conn)?;
+        let mut db = WalletDb::from_connection(conn, config.network, SystemClock, OsRng);
         init_wallet_db(&mut db, None)?;
+        let account_id = find_primary_account(&db)?;
 
-        // Connect to lightwalletd with TLS
-        let tls_config = ClientTlsConfig::new().with_native_roots();
-        let channel = tonic::transport::Endpoint::from_shared(config.lightwalletd_url.clone())?
-            .tls_config(tls_config)?
-            .connect()
-            .await?;
-        let client = CompactTxStreamerClient::new(channel);
+        // Open address book stored next to the wallet database
+        let address_book = AddressBook::open(
+            &config.address_book_db_path(),
+            config.network,
+            config.db_key.as_ref(),
+        )?;
 
         Ok(Self {
             config,
             db,
             client,
+            account_id,
             seed,
+            locked: false,
+            last_used: Instant::now(),
+            address_book,
         })
     }
 
+    /// Key of the encrypted wallet database, `None` if it is not encrypted
+    pub fn db_key(&self) -> Option<&DbKey> {
+        self.config.db_key.as_ref()
+    }
+
+    /// Close the wallet databases, keeping the lightwalletd connection for
+    /// reopening them with [`Self::with_client`]
+    pub fn into_client(self) -> GrpcClient {
+        self.client
+    }
+
+    /// Whether spending is disabled until the wallet is unlocked
+    pub fn is_locked(&self) -> bool {
+        self.locked
+    }
+
+    /// Whether a send could be signed right now, i.e. the wallet holds its
+    /// seed and is not locked
+    pub fn can_spend(&self) -> bool {
+        self.unlocked_seed().is_ok()
+    }
+
+    /// Zeroize the seed, keeping only the viewing key loaded
+    ///
+    /// Sync, balances and history keep working; spending fails with
+    /// `WalletLocked` until [`Self::unlock`] is called. Spending keys are only
+    /// derived for the duration of a send, so the seed is the only secret held.
+    /// Only wallets with a password can lock, since otherwise the stored seed
+    /// would unlock them without one.
+    pub fn lock(&mut self) -> anyhow::Result<()> {
+        if self.locked || self.seed.is_none() {
+            return Ok(());
+        }
+        if self.config.db_key.is_none() {
+            anyhow::bail!("Set a password before locking the wallet");
+        }
+
+        // Keep the viewing key so the account can still be exported and scanned
+        self.config.viewing_key = Some(self.export_viewing_key()?);
+        self.seed = None;
+        if let Some(mut phrase) = self.config.seed_phrase.take() {
+            phrase.zeroize();
+        }
+        self.locked = true;
+        info!("Wallet locked");
+        Ok(())
+    }
+
+    /// Restore the seed of a locked wallet
+    ///
+    /// The seed phrase must belong to the wallet's account.
+    pub fn unlock(&mut self, seed_phrase: &str) -> anyhow::Result<()> {
+        if !self.locked {
+            return Ok(());
+        }
+
+        let seed = Zeroizing::new(seed_from_phrase(seed_phrase)?);
+        let key = offline::watch_only_key(&seed, self.config.network)?;
+        if self.config.viewing_key.as_ref().map(|k| &k.ufvk) != Some(&key.ufvk) {
+            anyhow::bail!("Seed phrase does not belong to this wallet");
+        }
+
+        self.seed = Some(seed);
+        self.config.seed_phrase = Some(seed_phrase.to_string());
+        self.config.viewing_key = None;
+        self.locked = false;
+        self.last_used = Instant::now();
+        info!("Wallet unlocked");
+        Ok(())
+    }
+
+    /// Idle minutes after which the wallet locks, 0 if it never does
+    pub fn auto_lock_minutes(&self) -> u32 {
+        self.config.auto_lock_minutes
+    }
+
+    /// Change the idle minutes after which the wallet locks, 0 to never lock
+    ///
+    /// Restarts the idle timer.
+    pub fn set_auto_lock_minutes(&mut self, minutes: u32) {
+        self.config.auto_lock_minutes = minutes;
+        self.last_used = Instant::now();
+    }
+
+    /// Lock the wallet if it has not been unlocked or spent from within the
+    /// auto-lock timeout, returning whether it was locked
+    pub fn lock_if_idle(&mut self) -> anyhow::Result<bool> {
+        if self.locked || self.seed.is_none() || !self.is_idle() {
+            return Ok(false);
+        }
+        self.lock()?;
+        Ok(true)
+    }
+
+    /// Whether a wallet that can lock has been idle past the auto-lock timeout
+    fn is_idle(&self) -> bool {
+        let timeout = Duration::from_secs(u64::from(self.config.auto_lock_minutes) * 60);
+        self.config.db_key.is_some()
+            && self.config.auto_lock_minutes > 0
+            && self.last_used.elapsed() >= timeout
+    }
+
+    /// Seed for spending, failing if the wallet is locked or has been idle
+    /// past the auto-lock timeout
+    fn unlocked_seed(&self) -> anyhow::Result<&[u8; 64]> {
+        if self.locked || (self.seed.is_some() && self.is_idle()) {
+            return Err(IkkiError::WalletLocked.into());
+        }
+        self.seed.as_deref().ok_or_else(|| IkkiError::WatchOnly.into())
+    }
+
+    /// Get the wallet's address book
+    pub fn address_book(&self) -> &AddressBook {
+        &self.address_book
+    }
+
     /// Initialize the wallet account if it doesn't exist
     pub async fn init_account(&mut self) -> anyhow::Result<()> {
         // Check if account already exists
-        let accounts = self.db.get_account_ids()?;
-        if !accounts.is_empty() {
+        if self.account_id.is_some() {
             return Ok(());
         }
 
-        // Create unified spending key from seed
+        // Derive the viewing key from the seed, or use the watch-only key
         let account_id = AccountId::ZERO;
-        let usk = UnifiedSpendingKey::from_seed(&TEST_NETWORK, &self.seed, account_id)
-            .map_err(|e| anyhow::anyhow!("Failed to derive spending key: {e:?}"))?;
-        let ufvk = usk.to_unified_full_viewing_key();
+        let (ufvk, seed_fingerprint) = match (self.seed.as_deref(), &self.config.viewing_key) {
+            (Some(seed), _) => (
+                account_spending_key(seed, self.config.network)?.to_unified_full_viewing_key(),
+                SeedFingerprint::from_seed(seed),
+            ),
+            (None, Some(key)) => (
+                UnifiedFullViewingKey::decode(&self.config.network, &key.ufvk)
+                    .map_err(|e| anyhow::anyhow!("Invalid viewing key: {e}"))?,
+                key.seed_fingerprint
+                    .as_deref()
+                    .map(parse_seed_fingerprint)
+                    .transpose()?,
+            ),
+            (None, None) => anyhow::bail!("No seed phrase or viewing key configured"),
+        };
 
         // Get birthday tree state from lightwalletd
         let birthday_height = self.config.birthday_height;
-        let request = service::BlockId {
-            height: birthday_height.saturating_sub(1),
-            ..Default::default()
-        };
-        let treestate = self.client.get_tree_state(request).await?.into_inner();
+        let treestate = self
+            .client
+            .tree_state(birthday_height.saturating_sub(1))
+            .await?;
 
         let birthday = AccountBirthday::from_treestate(treestate, None)
             .map_err(|_| anyhow::anyhow!("Failed to create birthday from tree state"))?;
 
         // Import account into wallet
-        self.db.import_account_ufvk(
-            "ikki-wallet",
+        let account = self.db.import_account_ufvk(
+            PRIMARY_ACCOUNT_NAME,
             &ufvk,
             &birthday,
-            AccountPurpose::Spending { derivation: None },
+            AccountPurpose::Spending {
+                derivation: seed_fingerprint.map(|fp| Zip32Derivation::new(fp, account_id)),
+            },
             None,
         )?;
+        self.account_id = Some(account.id());
 
         Ok(())
     }
 
+    /// The wallet's own account
+    fn primary_account_id(&self) -> anyhow::Result<AccountUuid> {
+        self.account_id
+            .ok_or_else(|| anyhow::anyhow!("No account found - run init_account first"))
+    }
+
     /// Sync wallet with the blockchain
-    pub async fn sync(&mut self) -> anyhow::Result<()> {
+    ///
+    /// Returns the chain reorganization that was handled during the sync, if any.
+    pub async fn sync(&mut self) -> anyhow::Result<Option<ChainReorg>> {
+        let snapshot = self.chain_snapshot()?;
+
         let db_cache = MemBlockCache::new();
         sync_run(
             &mut self.client,
-            &TEST_NETWORK,
+            &self.config.network,
             &db_cache,
             &mut self.db,
             SYNC_BATCH_SIZE,
         )
         .await
-        .map_err(|e| anyhow::anyhow!("Sync failed: {e:?}"))?;
+        .map_err(|e| match e {
+            zcash_client_backend::sync::Error::Server(status) => {
+                IkkiError::Network(format!("Sync failed: {}", status.message()))
+            }
+            zcash_client_backend::sync::Error::Wallet(e) => {
+                IkkiError::Database(format!("Sync failed: {e:?}"))
+            }
+            e => IkkiError::Other(format!("Sync failed: {e:?}")),
+        })?;
 
+        let reorg = self.detect_reorg(&snapshot)?;
+        if let Some(r) = &reorg {
+            info!(
+                "Chain reorg detected: rewound from {} to {}, {} transactions affected",
+                r.old_height,
+                r.new_height,
+                r.affected_txids.len()
+            );
+        }
+
+        Ok(reorg)
+    }
+
+    /// Capture recent block hashes and mined transaction heights
+    fn chain_snapshot(&self) -> anyhow::Result<ChainSnapshot> {
+        let conn = self.open_read_only()?;
+
+        let tip_height: Option<u32> =
+            conn.query_row("SELECT MAX(height) FROM blocks", [], |row| row.get(0))?;
+
+        let mut block_hashes = HashMap::new();
+        if let Some(tip) = tip_height {
+            let mut stmt = conn.prepare("SELECT height, hash FROM blocks WHERE height > ?")?;
+            let rows = stmt.query_map([tip.saturating_sub(REORG_WINDOW)], |row| {
+                Ok((row.get::<_, u32>(0)?, row.get::<_, Vec<u8>>(1)?))
+            })?;
+            for (height, hash) in rows.flatten() {
+                block_hashes.insert(height, hash);
+            }
+        }
+
+        let mut mined_heights = HashMap::new();
+        let mut stmt = conn.prepare(
+            "SELECT txid, mined_height FROM transactions WHERE mined_height IS NOT NULL",
+        )?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, Vec<u8>>(0)?, row.get::<_, u32>(1)?))
+        })?;
+        for (txid, height) in rows.flatten() {
+            mined_heights.insert(txid, height);
+        }
+
+        Ok(ChainSnapshot {
+            block_hashes,
+            mined_heights,
+            tip_height,
+        })
+    }
+
+    /// Compare the wallet state against a pre-sync snapshot to find rewinds
+    fn detect_reorg(&self, snapshot: &ChainSnapshot) -> anyhow::Result<Option<ChainReorg>> {
+        let Some(old_tip) = snapshot.tip_height else {
+            return Ok(None);
+        };
+        let after = self.chain_snapshot()?;
+
+        // Blocks still in the wallet but with a different hash were replaced
+        let replaced: Vec<u32> = snapshot
+            .block_hashes
+            .iter()
+            .filter(|(height, hash)| {
+                after
+                    .block_hashes
+                    .get(*height)
+                    .is_some_and(|new_hash| new_hash != *hash)
+            })
+            .map(|(height, _)| *height)
+            .collect();
+
+        // Transactions that are no longer mined, or are mined at another height
+        let mut affected: Vec<(u32, &Vec<u8>)> = snapshot
+            .mined_heights
+            .iter()
+            .filter(|(txid, height)| after.mined_heights.get(*txid) != Some(*height))
+            .map(|(txid, height)| (*height, txid))
+            .collect();
+
+        if replaced.is_empty() && affected.is_empty() {
+            return Ok(None);
+        }
+
+        let lowest_changed = replaced
+            .iter()
+            .copied()
+            .chain(affected.iter().map(|(height, _)| *height))
+            .min()
+            .unwrap_or(old_tip);
+        let new_height = lowest_changed.saturating_sub(1);
+
+        affected.sort();
+        Ok(Some(ChainReorg {
+            old_height: u64::from(old_tip),
+            new_height: u64::from(new_height),
+            affected_txids: affected
+                .into_iter()
+                .map(|(_, txid)| txid_to_hex(txid))
+                .collect(),
+        }))
+    }
+
+    /// Open a read-only connection to the wallet database for raw queries
+    fn open_read_only(&self) -> anyhow::Result<rusqlite::Connection> {
+        database::open_wallet_db(
+            &self.config.wallet_db_path(),
+            self.config.db_key.as_ref(),
+            true,
+        )
+    }
+
+    /// Confirmation settings used for balances and spends
+    pub fn confirmation_settings(&self) -> ConfirmationSettings {
+        self.config.confirmations
+    }
+
+    /// Change the confirmation settings used for balances and spends
+    pub fn set_confirmation_settings(
+        &mut self,
+        settings: ConfirmationSettings,
+    ) -> anyhow::Result<()> {
+        settings.policy()?;
+        self.config.confirmations = settings;
         Ok(())
     }
 
+    fn confirmations_policy(&self) -> anyhow::Result<ConfirmationsPolicy> {
+        self.config.confirmations.policy()
+    }
+
+    /// Get the birthday height of the wallet account
+    pub fn get_birthday_height(&self) -> anyhow::Result<u64> {
+        let account_id = self.primary_account_id()?;
+        let birthday = self.db.get_account_birthday(account_id)?;
+        Ok(u64::from(u32::from(birthday)))
+    }
+
+    /// Rewind the wallet database to the given height
+    ///
+    /// Scanned blocks, notes and transactions above the height are discarded so
+    /// that the next sync scans them again. The database can only be rewound to
+    /// a stored checkpoint, so the actual height may be lower than requested;
+    /// it is returned to the caller.
+    pub fn rewind_to_height(&mut self, height: u64) -> anyhow::Result<u64> {
+        let requested =
+            u32::try_from(height).map_err(|_| anyhow::anyhow!("Invalid rescan height {height}"))?;
+
+        let rewound_to = self
+            .db
+            .truncate_to_height(BlockHeight::from_u32(requested))
+            .map_err(|e| anyhow::anyhow!("Failed to rewind wallet to height {height}: {e:?}"))?;
+        info!("Wallet rewound to height {}", u32::from(rewound_to));
+
+        Ok(u64::from(u32::from(rewound_to)))
+    }
+
     /// Get current block height from lightwalletd
     pub async fn get_block_height(&mut self) -> anyhow::Result<u64> {
-        let response = self
-            .client
-            .get_latest_block(ChainSpec::default())
-            .await?
-            .into_inner();
-        Ok(response.height)
+        self.client.latest_height().await
     }
 
-    /// Get wallet balance in zatoshis
+    /// Get the total wallet balance in zatoshis, including value not yet spendable
     pub fn get_balance(&self) -> anyhow::Result<u64> {
-        let summary = self.db.get_wallet_summary(ConfirmationsPolicy::MIN)?;
-        match summary {
-            Some(s) => {
-                let mut total = Zatoshis::ZERO;
-                for balance in s.account_balances().values() {
-                    total = (total + balance.total())
-                        .ok_or_else(|| anyhow::anyhow!("Balance overflow"))?;
-                }
-                Ok(u64::from(total))
-            }
-            None => Ok(0),
-        }
+        Ok(self.get_balance_breakdown()?.total())
     }
 
     /// Get detailed balance breakdown by pool
     pub fn get_balance_breakdown(&self) -> anyhow::Result<BalanceBreakdown> {
-        let summary = self.db.get_wallet_summary(ConfirmationsPolicy::MIN)?;
+        let summary = self.db.get_wallet_summary(self.confirmations_policy()?)?;
         match summary {
             Some(s) => {
-                let mut breakdown = BalanceBreakdown::default();
-                for balance in s.account_balances().values() {
-                    breakdown.transparent +=
-                        u64::from(balance.unshielded_balance().spendable_value());
-                    breakdown.sapling += u64::from(balance.sapling_balance().spendable_value());
-                    breakdown.orchard += u64::from(balance.orchard_balance().spendable_value());
+                let mut breakdown = BalanceBreakdown {
+                    fully_scanned_height: Some(u32::from(s.fully_scanned_height())),
+                    chain_tip_height: Some(u32::from(s.chain_tip_height())),
+                    ..Default::default()
+                };
+                // Only the wallet's own account, in case another tool added
+                // accounts to wallet.db
+                let balance = self.account_id.and_then(|id| s.account_balances().get(&id));
+                if let Some(balance) = balance {
+                    breakdown.transparent.add(balance.unshielded_balance());
+                    breakdown.sapling.add(balance.sapling_balance());
+                    breakdown.orchard.add(balance.orchard_balance());
                 }
                 Ok(breakdown)
             }
@@ -249,12 +773,8 @@ impl IkkiWallet {
 
     /// Get receiving address
     pub fn get_address(&self) -> anyhow::Result<String> {
-        let accounts = self.db.get_account_ids()?;
-        let account_id = accounts
-            .first()
-            .ok_or_else(|| anyhow::anyhow!("No account found - run init_account first"))?;
-
-        let addresses = self.db.list_addresses(*account_id)?;
+        let account_id = self.primary_account_id()?;
+        let addresses = self.db.list_addresses(account_id)?;
 
         if addresses.is_empty() {
             return Err(anyhow::anyhow!("No addresses found for account"));
@@ -262,45 +782,165 @@ impl IkkiWallet {
 
         let address_record = addresses.first().unwrap();
         let address = address_record.address();
-        Ok(address.to_zcash_address(&TEST_NETWORK).to_string())
+        Ok(address.to_zcash_address(&self.config.network).to_string())
     }
 
-    /// Generate a new diversified receiving address
-    pub fn get_new_address(&mut self) -> anyhow::Result<String> {
-        use zcash_keys::keys::{ReceiverRequirement, UnifiedAddressRequest};
-        use zcash_protocol::consensus::NetworkType;
+    /// Generate a new diversified receiving address of the requested type
+    ///
+    /// The address is recorded with its type, issue time and optional label.
+    pub fn get_new_address(
+        &mut self,
+        address_type: AddressType,
+        label: Option<&str>,
+    ) -> anyhow::Result<NewAddress> {
+        use zcash_keys::address::{Address, UnifiedAddress};
 
-        let accounts = self.db.get_account_ids()?;
-        let account_id = accounts
-            .first()
-            .ok_or_else(|| anyhow::anyhow!("No account found - run init_account first"))?;
+        let account_id = self.primary_account_id()?;
 
-        let request = UnifiedAddressRequest::unsafe_custom(
-            ReceiverRequirement::Require,
-            ReceiverRequirement::Require,
-            ReceiverRequirement::Omit,
-        );
-        let (address, _diversifier_index) = self
+        let request = address_type.required_receivers().address_request();
+        let (ua, diversifier_index) = self
             .db
-            .get_next_available_address(*account_id, request)?
+            .get_next_available_address(account_id, request)?
             .ok_or_else(|| anyhow::anyhow!("Failed to generate new address"))?;
 
-        Ok(address.to_zcash_address(NetworkType::Test).to_string())
+        let network = self.config.network.network_type();
+        let unified_address = ua.to_zcash_address(network).to_string();
+        let orchard = ua
+            .orchard()
+            .and_then(|o| UnifiedAddress::from_receivers(Some(*o), None, None))
+            .map(|o| o.to_zcash_address(network).to_string());
+        let sapling = ua
+            .sapling()
+            .map(|s| Address::Sapling(*s).to_zcash_address(network).to_string());
+        let transparent = ua
+            .transparent()
+            .map(|t| Address::Transparent(*t).to_zcash_address(network).to_string());
+
+        let address = match address_type {
+            AddressType::Shielded | AddressType::Full => Some(unified_address.clone()),
+            AddressType::Orchard => orchard.clone(),
+            AddressType::Sapling => sapling.clone(),
+            AddressType::Transparent => transparent.clone(),
+        }
+        .ok_or_else(|| anyhow::anyhow!("Generated address is missing the requested receiver"))?;
+
+        let diversifier_index = addresses::diversifier_index_to_u128(&diversifier_index);
+        self.address_book.record_issued_address(
+            &unified_address,
+            address_type,
+            &address,
+            diversifier_index,
+            label,
+        )?;
+
+        Ok(NewAddress {
+            address,
+            address_type,
+            unified_address,
+            diversifier_index,
+            orchard,
+            sapling,
+            transparent,
+        })
     }
 
-    /// Get all addresses for the wallet
-    pub fn get_all_addresses(&self) -> anyhow::Result<Vec<String>> {
-        let accounts = self.db.get_account_ids()?;
-        let account_id = accounts
-            .first()
-            .ok_or_else(|| anyhow::anyhow!("No account found - run init_account first"))?;
+    /// Get all receiving addresses of the wallet with their metadata
+    pub fn get_all_addresses(&self) -> anyhow::Result<Vec<AddressRecord>> {
+        let conn = self.open_read_only()?;
+        let metadata = self.address_book.own_address_metadata()?;
+        addresses::list_address_records(&conn, self.config.network, &metadata)
+    }
 
-        let addresses = self.db.list_addresses(*account_id)?;
+    /// Record the diversifier index of every labelled or issued address in
+    /// the address book, so the addresses can be restored after a rebuild
+    pub fn record_address_indices(&self) -> anyhow::Result<()> {
+        let metadata = self.address_book.own_address_metadata()?;
+        for record in self.get_all_addresses()? {
+            if metadata.contains_key(&record.address) {
+                self.address_book
+                    .record_diversifier_index(&record.address, record.diversifier_index)?;
+            }
+        }
+        Ok(())
+    }
 
-        Ok(addresses
+    /// Expose again the addresses recorded in the address book
+    ///
+    /// After the wallet database is rebuilt only the default address exists.
+    /// Each recorded address is derived again at its diversifier index with
+    /// the same receivers, so it keeps its label and received funds show up
+    /// under it, and new addresses continue after the highest issued index
+    /// instead of handing the same ones out again. Returns the number of
+    /// addresses restored.
+    pub fn restore_issued_addresses(&mut self) -> anyhow::Result<usize> {
+        let account_id = self.primary_account_id()?;
+        let mut restored = 0;
+        for (address, index) in self.address_book.own_address_indices()? {
+            let receivers = addresses::ReceiverSet::of_address(&address, self.config.network)
+                .ok_or_else(|| anyhow::anyhow!("Invalid recorded address {address}"))?;
+            let diversifier_index = zip32::DiversifierIndex::try_from(index)
+                .map_err(|_| anyhow::anyhow!("Invalid diversifier index {index}"))?;
+            let ua = self
+                .db
+                .get_address_for_index(account_id, diversifier_index, receivers.address_request())?
+                .ok_or_else(|| anyhow::anyhow!("No address at diversifier index {index}"))?;
+
+            let network = self.config.network.network_type();
+            if ua.to_zcash_address(network).to_string() != address {
+                warn!(
+                    "Address at diversifier index {} does not match {}",
+                    index, address
+                );
+                continue;
+            }
+            restored += 1;
+        }
+
+        if restored > 0 {
+            info!("Restored {} issued addresses", restored);
+        }
+        Ok(restored)
+    }
+
+    /// Set or clear the label of one of the wallet's own addresses
+    pub fn set_address_label(&self, address: &str, label: Option<&str>) -> anyhow::Result<()> {
+        let is_own = self
+            .get_all_addresses()?
             .iter()
-            .map(|addr| addr.address().to_zcash_address(&TEST_NETWORK).to_string())
-            .collect())
+            .any(|record| record.address == address);
+        if !is_own {
+            anyhow::bail!("Address does not belong to this wallet");
+        }
+
+        self.address_book.set_address_label(address, label)
+    }
+
+    /// List unspent shielded notes within a confirmation range
+    pub fn list_unspent_notes(
+        &self,
+        min_confirmations: u32,
+        max_confirmations: u32,
+    ) -> anyhow::Result<Vec<UnspentNote>> {
+        let conn = self.open_read_only()?;
+        notes::list_unspent_notes(
+            &conn,
+            self.primary_account_id()?,
+            min_confirmations,
+            max_confirmations,
+        )
+    }
+
+    /// Validate and describe an address, including whether it belongs to this wallet
+    pub fn inspect_address(&self, address: &str) -> anyhow::Result<AddressInspection> {
+        let records = self.get_all_addresses()?;
+        let own_receivers = receiver_encodings(records.iter().flat_map(|record| {
+            std::iter::once(record.address.as_str()).chain(record.issued_address.as_deref())
+        }));
+        Ok(inspect::inspect_address(
+            address,
+            self.config.network,
+            &own_receivers,
+        ))
     }
 
     /// Send ZEC to an address
@@ -310,63 +950,34 @@ impl IkkiWallet {
         amount_zatoshi: u64,
         memo: Option<Vec<u8>>,
     ) -> anyhow::Result<SendResult> {
-        let accounts = self.db.get_account_ids()?;
-        let account_id = accounts
-            .first()
-            .ok_or_else(|| anyhow::anyhow!("No account found"))?;
+        let proposal = self.transfer_proposal(to_address, amount_zatoshi, memo)?;
+        self.execute_proposal(&proposal).await
+    }
+
+    /// Build a proposal paying an amount to an address
+    fn transfer_proposal(
+        &mut self,
+        to_address: &str,
+        amount_zatoshi: u64,
+        memo: Option<Vec<u8>>,
+    ) -> anyhow::Result<IkkiProposal> {
+        let account_id = self.primary_account_id()?;
         info!(
             "Sending {} zatoshis to {} from account {:?}",
             amount_zatoshi, to_address, account_id
         );
 
-        // Check balance
-        let summary = self.db.get_wallet_summary(ConfirmationsPolicy::MIN)?;
-        let (orchard_balance, sapling_balance) = match &summary {
-            Some(s) => {
-                let mut orchard = 0u64;
-                let mut sapling = 0u64;
-                for balance in s.account_balances().values() {
-                    orchard += u64::from(balance.orchard_balance().spendable_value());
-                    sapling += u64::from(balance.sapling_balance().spendable_value());
-                }
-                (orchard, sapling)
-            }
-            None => (0, 0),
-        };
-
-        let total_shielded = orchard_balance + sapling_balance;
-        let min_required = amount_zatoshi + 20000;
-        debug!(
-            orchard_balance,
-            sapling_balance, total_shielded, amount_zatoshi, "Balance check for send"
-        );
-
-        if total_shielded < min_required {
-            return Err(anyhow::anyhow!(
-                "Insufficient shielded funds. Need {min_required} zatoshis, have {total_shielded} zatoshis"
-            ));
-        }
-
         // Parse the destination address
-        use zcash_address::ZcashAddress;
-        let parsed_address: ZcashAddress = to_address
-            .parse()
-            .map_err(|e| anyhow::anyhow!("Invalid address: {e:?}"))?;
-        let address = parsed_address
-            .convert::<zcash_keys::address::Address>()
-            .map_err(|e| anyhow::anyhow!("Address conversion failed: {e:?}"))?;
+        let (_, address) = parse_recipient(to_address, self.config.network)?;
 
-        // Create memo if provided
-        let memo_bytes = if let Some(data) = memo {
-            MemoBytes::from_bytes(&data).map_err(|_| anyhow::anyhow!("Invalid memo"))?
-        } else {
-            MemoBytes::empty()
-        };
+        // ZIP 320: TEX addresses only accept funds from transparent inputs, so the
+        // proposal routes the payment through an ephemeral transparent address
+        let is_tex = matches!(address, zcash_keys::address::Address::Tex(_));
+        let is_transparent = is_tex
+            || matches!(address, zcash_keys::address::Address::Transparent(_));
 
-        // Derive spending key
-        debug!("Deriving unified spending key for transaction");
-        let usk = UnifiedSpendingKey::from_seed(&TEST_NETWORK, &self.seed, AccountId::ZERO)
-            .map_err(|e| anyhow::anyhow!("Failed to derive spending key: {e:?}"))?;
+        // Create memo if provided
+        let memo_bytes = parse_memo(memo, is_transparent)?.unwrap_or_else(MemoBytes::empty);
 
         // Create proposal
         let send_amount =
@@ -374,19 +985,99 @@ impl IkkiWallet {
 
         let proposal = propose_standard_transfer_to_address::<_, _, SqliteClientError>(
             &mut self.db,
-            &TEST_NETWORK,
+            &self.config.network,
             StandardFeeRule::Zip317,
-            *account_id,
-            ConfirmationsPolicy::MIN,
+            account_id,
+            self.confirmations_policy()?,
             &address,
             send_amount,
-            Some(memo_bytes),
+            (!is_transparent).then_some(memo_bytes),
             None,
             ShieldedProtocol::Orchard,
         )
-        .map_err(|e| anyhow::anyhow!("Failed to create transaction proposal: {e:?}"))?;
+        .map_err(proposal_error)?;
+
+        if is_tex {
+            info!(
+                "TEX payment proposal has {} steps, combined fee {}",
+                proposal.steps().len(),
+                proposal_fee(&proposal)
+            );
+        }
+
+        Ok(proposal)
+    }
+
+    /// Propose sending the wallet's entire spendable shielded balance to an address
+    ///
+    /// Returns the exact amount the recipient would receive and the ZIP-317 fee,
+    /// without building or broadcasting anything.
+    pub fn propose_max_transfer(
+        &mut self,
+        to_address: &str,
+        memo: Option<Vec<u8>>,
+    ) -> anyhow::Result<MaxTransfer> {
+        let proposal = self.max_transfer_proposal(to_address, memo)?;
+        Ok(MaxTransfer {
+            amount: proposal_amount(&proposal)?,
+            fee: proposal_fee(&proposal),
+        })
+    }
+
+    /// Send the wallet's entire spendable shielded balance to an address
+    pub async fn send_max(
+        &mut self,
+        to_address: &str,
+        memo: Option<Vec<u8>>,
+    ) -> anyhow::Result<SendResult> {
+        let proposal = self.max_transfer_proposal(to_address, memo)?;
+        info!(
+            "Sending max {} zatoshis to {} (fee {})",
+            proposal_amount(&proposal)?,
+            to_address,
+            proposal_fee(&proposal)
+        );
+        self.execute_proposal(&proposal).await
+    }
+
+    /// Build a proposal spending all spendable Sapling and Orchard notes, less the fee
+    fn max_transfer_proposal(
+        &mut self,
+        to_address: &str,
+        memo: Option<Vec<u8>>,
+    ) -> anyhow::Result<IkkiProposal> {
+        let account_id = self.primary_account_id()?;
 
-        let fee = 10000u64;
+        let (recipient, address) = parse_recipient(to_address, self.config.network)?;
+        let is_transparent = matches!(
+            address,
+            zcash_keys::address::Address::Transparent(_) | zcash_keys::address::Address::Tex(_)
+        );
+        let memo_bytes = parse_memo(memo, is_transparent)?;
+
+        propose_send_max_transfer::<_, _, _, SqliteClientError>(
+            &mut self.db,
+            &self.config.network,
+            account_id,
+            &[ShieldedProtocol::Sapling, ShieldedProtocol::Orchard],
+            &StandardFeeRule::Zip317,
+            recipient,
+            memo_bytes,
+            MaxSpendMode::MaxSpendable,
+            self.confirmations_policy()?,
+        )
+        .map_err(proposal_error)
+    }
+
+    /// Sign, build and broadcast every transaction of a proposal
+    async fn execute_proposal(&mut self, proposal: &IkkiProposal) -> anyhow::Result<SendResult> {
+        let amount = proposal_amount(proposal)?;
+        let fee = proposal_fee(proposal);
+
+        // Derive spending key
+        debug!("Deriving unified spending key for transaction");
+        let usk = self.spending_key()?;
+        self.last_used = Instant::now();
 
         // Load prover and build transaction
         let prover = LocalTxProver::bundled();
@@ -395,218 +1086,201 @@ impl IkkiWallet {
 
         let txids = build_and_sign_transaction(
             &mut self.db,
-            &TEST_NETWORK,
+            &self.config.network,
             &prover,
             &spending_keys,
-            &proposal,
+            proposal,
         )?;
 
-        let txid = *txids.first();
-        info!("Transaction built with txid {}", txid);
+        // Broadcast in order: a TEX payment spends the output of the first transaction
+        let mut broadcast = Vec::with_capacity(txids.len());
+        for txid in txids.iter() {
+            info!("Transaction built with txid {}", txid);
+            if let Err(e) = self.broadcast_transaction(*txid).await {
+                if broadcast.is_empty() {
+                    return Err(e);
+                }
+                return Err(IkkiError::PartialBroadcast {
+                    txids: broadcast,
+                    message: IkkiError::from(e).to_string(),
+                }
+                .into());
+            }
+            broadcast.push(txid.to_string());
+        }
 
-        // Get the transaction and broadcast
-        let tx = self
-            .db
-            .get_transaction(txid)?
-            .ok_or_else(|| anyhow::anyhow!("Transaction not found in database"))?;
+        let txid = txids.last().to_string();
+        Ok(SendResult {
+            txid,
+            txids: broadcast,
+            amount,
+            fee,
+        })
+    }
 
-        let mut tx_bytes = Vec::new();
-        tx.write(&mut tx_bytes)
-            .map_err(|e| anyhow::anyhow!("Failed to serialize transaction: {e:?}"))?;
+    /// Spending key of the wallet's account
+    fn spending_key(&self) -> anyhow::Result<UnifiedSpendingKey> {
+        account_spending_key(self.unlocked_seed()?, self.config.network)
+    }
 
-        let raw_tx = RawTransaction {
-            data: tx_bytes,
-            height: 0,
-        };
+    /// Viewing key for setting up a watch-only copy of this wallet
+    pub fn export_viewing_key(&self) -> anyhow::Result<WatchOnlyKey> {
+        match (self.seed.as_deref(), &self.config.viewing_key) {
+            (Some(seed), _) => offline::watch_only_key(seed, self.config.network),
+            (None, Some(key)) => Ok(key.clone()),
+            (None, None) => anyhow::bail!("No seed phrase or viewing key configured"),
+        }
+    }
 
-        let response = self
-            .client
-            .send_transaction(raw_tx)
-            .await
-            .map_err(|e| anyhow::anyhow!("Failed to broadcast transaction: {e:?}"))?;
-
-        let send_response = response.into_inner();
-        if send_response.error_code != 0 {
-            return Err(anyhow::anyhow!(
-                "Transaction rejected (code {}): {}",
-                send_response.error_code,
-                send_response.error_message
-            ));
+    /// Create an unsigned PCZT paying an address, for signing by an offline wallet
+    ///
+    /// Sends the entire spendable shielded balance when `amount_zatoshi` is `None`.
+    pub fn create_pczt(
+        &mut self,
+        to_address: &str,
+        amount_zatoshi: Option<u64>,
+        memo: Option<Vec<u8>>,
+    ) -> anyhow::Result<PcztProposal> {
+        let proposal = match amount_zatoshi {
+            Some(amount) => self.transfer_proposal(to_address, amount, memo)?,
+            None => self.max_transfer_proposal(to_address, memo)?,
+        };
+        if proposal.steps().len() > 1 {
+            return Err(IkkiError::Proposal(
+                "TEX payments cannot be signed offline".to_string(),
+            )
+            .into());
         }
 
-        info!("Transaction {} broadcast successfully", txid);
-        Ok(SendResult {
-            txid: txid.to_string(),
-            fee,
+        let account_id = self.primary_account_id()?;
+        let pczt = build_pczt(&mut self.db, &self.config.network, account_id, &proposal)?;
+        info!("Created PCZT paying {} to {}", proposal_amount(&proposal)?, to_address);
+
+        Ok(PcztProposal {
+            pczt: pczt.serialize(),
+            amount: proposal_amount(&proposal)?,
+            fee: proposal_fee(&proposal),
         })
     }
 
-    /// Get recent transactions from the wallet
-    pub fn get_recent_transactions(&self, limit: usize) -> anyhow::Result<Vec<TransactionRecord>> {
-        use rusqlite::Connection;
-
-        let db_path = self.config.wallet_db_path();
+    /// Decode what a PCZT pays so it can be confirmed before signing
+    pub fn inspect_pczt(&self, pczt: &[u8]) -> anyhow::Result<PcztSummary> {
+        offline::inspect_pczt(self.unlocked_seed()?, self.config.network, pczt)
+    }
 
-        let conn =
-            Connection::open_with_flags(&db_path, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    /// Prove and sign a PCZT with this wallet's seed
+    pub fn sign_pczt(&mut self, pczt: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let signed = offline::sign_pczt(self.unlocked_seed()?, self.config.network, pczt)?;
+        self.last_used = Instant::now();
+        Ok(signed)
+    }
 
-        // Include both mined and unmined transactions
-        // Order by mined_height DESC (NULLs first for pending), then by transaction id
-        let mut stmt = conn.prepare(
-            "SELECT
-                txid,
-                mined_height,
-                account_balance_delta,
-                block_time,
-                sent_note_count,
-                is_shielding,
-                expired_unmined
-            FROM v_transactions
-            WHERE expired_unmined = 0
-            ORDER BY
-                CASE WHEN mined_height IS NULL THEN 0 ELSE 1 END,
-                mined_height DESC
-            LIMIT ?",
-        )?;
+    /// Finalize a signed PCZT, store the transaction and broadcast it
+    pub async fn broadcast_pczt(&mut self, pczt: &[u8]) -> anyhow::Result<SendResult> {
+        let pczt = pczt::Pczt::parse(pczt).map_err(|e| anyhow::anyhow!("Invalid PCZT: {e:?}"))?;
 
-        let rows = stmt.query_map([limit as i64], |row| {
-            let txid_bytes: Vec<u8> = row.get(0)?;
-            let mined_height: Option<u32> = row.get(1)?;
-            let balance_delta: i64 = row.get(2)?;
-            let block_time: Option<u32> = row.get(3)?;
-            let _sent_note_count: i64 = row.get(4)?;
-            let is_shielding: bool = row.get(5)?;
-            let _expired: bool = row.get(6)?;
-            Ok((
-                txid_bytes,
-                mined_height,
-                balance_delta,
-                block_time,
-                is_shielding,
-            ))
-        })?;
+        let (spend_vk, output_vk) = LocalTxProver::bundled().verifying_keys();
+        let orchard_vk = orchard::circuit::VerifyingKey::build();
+        let txid = extract_and_store_transaction_from_pczt::<
+            _,
+            zcash_client_sqlite::ReceivedNoteId,
+        >(
+            &mut self.db,
+            pczt,
+            Some((&spend_vk, &output_vk)),
+            Some(&orchard_vk),
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to extract transaction from PCZT: {e:?}"))?;
+        info!("Transaction extracted from PCZT with txid {}", txid);
 
-        let mut transactions = Vec::new();
-        for (txid_bytes, mined_height, balance_delta, block_time, is_shielding) in rows.flatten()
-        {
-            let mut txid_arr = [0u8; 32];
-            if txid_bytes.len() == 32 {
-                txid_arr.copy_from_slice(&txid_bytes);
-                txid_arr.reverse();
-            }
-            let txid: String = txid_arr.iter().map(|b| format!("{b:02x}")).collect();
-
-            // Fetch memo for this transaction
-            let memo = self.get_transaction_memo(&conn, &txid_bytes);
-
-            // Use current time for pending transactions, block time for mined
-            let timestamp = block_time.map(|t| t as u64).unwrap_or_else(|| {
-                std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .map(|d| d.as_secs())
-                    .unwrap_or(0)
-            });
-
-            // Determine if sent based on balance delta (negative = sent, positive = received)
-            // Shielding transactions are internal transfers, not real sends
-            let is_sent = balance_delta < 0 && !is_shielding;
-
-            // Determine if pending (unmined)
-            let is_pending = mined_height.is_none();
-
-            transactions.push(TransactionRecord {
-                txid,
-                amount: balance_delta,
-                timestamp,
-                is_sent,
-                memo,
-                is_pending,
-                mined_height,
-            });
-        }
+        self.broadcast_transaction(txid).await?;
 
-        Ok(transactions)
-    }
-
-    /// Get memo for a transaction from received or sent notes
-    fn get_transaction_memo(&self, conn: &rusqlite::Connection, txid_bytes: &[u8]) -> Option<String> {
-        // Try sent_notes first (for outgoing transactions)
-        let sent_memo: Option<String> = conn
-            .query_row(
-                "SELECT sn.memo
-                FROM sent_notes sn
-                JOIN transactions t ON sn.transaction_id = t.id_tx
-                WHERE t.txid = ? AND sn.memo IS NOT NULL AND length(sn.memo) > 1
-                LIMIT 1",
-                [txid_bytes],
-                |row| row.get(0),
-            )
-            .ok()
-            .and_then(|memo_bytes: Vec<u8>| Self::decode_memo(&memo_bytes));
+        let txid = txid.to_string();
+        let details = self
+            .get_transaction_details(&txid)?
+            .ok_or_else(|| anyhow::anyhow!("Transaction not found in database"))?;
+        let amount = details
+            .outputs
+            .iter()
+            .filter(|output| !output.is_change && !output.is_received)
+            .map(|output| output.value)
+            .sum();
+        Ok(SendResult {
+            txids: vec![txid.clone()],
+            txid,
+            amount,
+            fee: details.fee.unwrap_or(0),
+        })
+    }
 
-        if sent_memo.is_some() {
-            return sent_memo;
-        }
+    /// Broadcast a transaction stored in the wallet database
+    async fn broadcast_transaction(&mut self, txid: zcash_protocol::TxId) -> anyhow::Result<()> {
+        let tx = self
+            .db
+            .get_transaction(txid)?
+            .ok_or_else(|| anyhow::anyhow!("Transaction not found in database"))?;
 
-        // Try Orchard received notes (for incoming transactions)
-        let orchard_memo: Option<String> = conn
-            .query_row(
-                "SELECT rn.memo
-                FROM orchard_received_notes rn
-                JOIN transactions t ON rn.transaction_id = t.id_tx
-                WHERE t.txid = ? AND rn.memo IS NOT NULL AND length(rn.memo) > 1
-                LIMIT 1",
-                [txid_bytes],
-                |row| row.get(0),
-            )
-            .ok()
-            .and_then(|memo_bytes: Vec<u8>| Self::decode_memo(&memo_bytes));
+        let mut tx_bytes = Vec::new();
+        tx.write(&mut tx_bytes)
+            .map_err(|e| anyhow::anyhow!("Failed to serialize transaction: {e:?}"))?;
 
-        if orchard_memo.is_some() {
-            return orchard_memo;
-        }
+        self.client.broadcast_transaction(tx_bytes).await?;
 
-        // Try Sapling received notes
-        let sapling_memo: Option<String> = conn
-            .query_row(
-                "SELECT rn.memo
-                FROM sapling_received_notes rn
-                JOIN transactions t ON rn.transaction_id = t.id_tx
-                WHERE t.txid = ? AND rn.memo IS NOT NULL AND length(rn.memo) > 1
-                LIMIT 1",
-                [txid_bytes],
-                |row| row.get(0),
-            )
-            .ok()
-            .and_then(|memo_bytes: Vec<u8>| Self::decode_memo(&memo_bytes));
+        info!("Transaction {} broadcast successfully", txid);
+        Ok(())
+    }
 
-        sapling_memo
+    /// Get recent transactions from the wallet
+    pub fn get_recent_transactions(&self, limit: usize) -> anyhow::Result<Vec<TransactionRecord>> {
+        let query = TransactionQuery {
+            limit,
+            ..Default::default()
+        };
+        Ok(self.query_transactions(&query)?.transactions)
     }
 
-    /// Decode memo bytes to string, filtering out empty memos
-    fn decode_memo(memo_bytes: &[u8]) -> Option<String> {
-        if memo_bytes.is_empty() {
-            return None;
+    /// Query transaction history with filters and cursor-based pagination
+    ///
+    /// Transactions sent to a saved contact are annotated with the contact name.
+    pub fn query_transactions(&self, query: &TransactionQuery) -> anyhow::Result<TransactionPage> {
+        let conn = self.open_read_only()?;
+        let mut page = history::query_transactions(&conn, query)?;
+
+        let names = self.address_book.names_by_address()?;
+        for record in &mut page.transactions {
+            record.contact_name = record
+                .address
+                .as_ref()
+                .and_then(|address| names.get(address))
+                .cloned();
         }
 
-        // Check for empty memo (all zeros or 0xF6 padding)
-        // Zcash memos are 512 bytes, padded with 0x00 or start with 0xF6 for empty
-        if memo_bytes.first() == Some(&0xF6) {
-            return None;
-        }
+        Ok(page)
+    }
 
-        // Find the end of the actual message (strip trailing zeros)
-        let end = memo_bytes
-            .iter()
-            .rposition(|&b| b != 0)
-            .map(|p| p + 1)
-            .unwrap_or(0);
+    /// Get the full details of a transaction by its txid
+    pub fn get_transaction_details(&self, txid: &str) -> anyhow::Result<Option<TransactionDetails>> {
+        let conn = self.open_read_only()?;
+        history::get_transaction_details(&conn, txid)
+    }
 
-        if end == 0 {
-            return None;
-        }
+    /// Export transaction history to a CSV or JSON file
+    ///
+    /// Returns the number of transactions written.
+    pub fn export_transactions(
+        &self,
+        format: ExportFormat,
+        range: ExportRange,
+        path: &std::path::Path,
+    ) -> anyhow::Result<usize> {
+        let conn = self.open_read_only()?;
+        let labels = self.address_book.names_by_address()?;
+        export::export_transactions(&conn, format, range, &labels, path)
+    }
 
-        // Try to decode as UTF-8
-        String::from_utf8(memo_bytes[..end].to_vec()).ok()
+    /// Get every memo attached to a transaction by its txid
+    pub fn get_transaction_memos(&self, txid: &str) -> anyhow::Result<Vec<TransactionMemo>> {
+        let conn = self.open_read_only()?;
+        history::get_transaction_memos(&conn, &history::txid_from_hex(txid)?)
     }
 }
@@ -0,0 +1,364 @@
+//! Encryption of the wallet databases at rest
+//!
+//! `wallet.db` and `address_book.db` are encrypted with SQLCipher using a
+//! random 256-bit key. The wallet config keeps that key sealed with
+//! ChaCha20-Poly1305 under a key derived from the user's unlock password with
+//! Argon2id, so changing the password only rewrites the config and never
+//! touches the databases. Every connection, including the read-only ones used
+//! for history queries, is opened through [`open_wallet_db`] so the key is
+//! applied before anything reads the file. SQLCipher encrypts WAL frames with
+//! the same key; the SHM file only holds the WAL index. The seed phrase is
+//! sealed under the same key in the config.
+
+use std::path::{Path, PathBuf};
+
+use argon2::Argon2;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand_core::{OsRng, RngCore};
+use rusqlite::{Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+use zeroize::Zeroizing;
+
+use crate::wallet::IkkiError;
+
+/// Header of an unencrypted SQLite database file
+const SQLITE_HEADER: &[u8] = b"SQLite format 3\0";
+
+/// Length of a ChaCha20-Poly1305 nonce
+const NONCE_LEN: usize = 12;
+
+/// Key for an encrypted wallet database
+#[derive(Clone)]
+pub struct DbKey(Zeroizing<[u8; 32]>);
+
+impl std::fmt::Debug for DbKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("DbKey(..)")
+    }
+}
+
+impl DbKey {
+    /// Generate a random key for newly encrypted databases
+    pub fn generate() -> Self {
+        let mut key = Zeroizing::new([0u8; 32]);
+        OsRng.fill_bytes(key.as_mut());
+        Self(key)
+    }
+
+    /// Derive a key from an unlock password and salt
+    fn derive(password: &str, salt: &[u8]) -> anyhow::Result<Self> {
+        let mut key = Zeroizing::new([0u8; 32]);
+        Argon2::default()
+            .hash_password_into(password.as_bytes(), salt, key.as_mut())
+            .map_err(|e| anyhow::anyhow!("Failed to derive database key: {e}"))?;
+        Ok(Self(key))
+    }
+
+    /// Seal the key under an unlock password, for storing in the wallet config
+    pub fn wrap(&self, password: &str) -> anyhow::Result<WrappedDbKey> {
+        if password.is_empty() {
+            anyhow::bail!("Password cannot be empty");
+        }
+        let mut salt = [0u8; 16];
+        OsRng.fill_bytes(&mut salt);
+        let password_key = Self::derive(password, &salt)?;
+        Ok(WrappedDbKey {
+            salt: hex::encode(salt),
+            key: password_key.seal(self.0.as_ref())?,
+        })
+    }
+
+    /// Encrypt bytes under this key, returning hex-encoded nonce and ciphertext
+    pub fn seal(&self, plaintext: &[u8]) -> anyhow::Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        OsRng.fill_bytes(&mut nonce);
+        let ciphertext = ChaCha20Poly1305::new(Key::from_slice(self.0.as_slice()))
+            .encrypt(Nonce::from_slice(&nonce), plaintext)
+            .map_err(|_| anyhow::anyhow!("Encryption failed"))?;
+        Ok(hex::encode(
+            [nonce.as_slice(), ciphertext.as_slice()].concat(),
+        ))
+    }
+
+    /// Decrypt bytes sealed under this key
+    ///
+    /// Fails with `IkkiError::InvalidPassword` if the key is wrong.
+    pub fn open(&self, sealed: &str) -> anyhow::Result<Zeroizing<Vec<u8>>> {
+        let sealed = hex::decode(sealed).map_err(|_| anyhow::anyhow!("Invalid sealed data"))?;
+        if sealed.len() < NONCE_LEN {
+            anyhow::bail!("Invalid sealed data");
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let plaintext = ChaCha20Poly1305::new(Key::from_slice(self.0.as_slice()))
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| IkkiError::InvalidPassword)?;
+        Ok(Zeroizing::new(plaintext))
+    }
+
+    /// SQLCipher raw key literal, which skips SQLCipher's own slow KDF
+    fn sqlcipher_key(&self) -> Zeroizing<String> {
+        Zeroizing::new(format!("x'{}'", hex::encode(self.0.as_ref())))
+    }
+}
+
+/// Database key sealed under an unlock password
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WrappedDbKey {
+    /// Hex-encoded Argon2id salt of the password key
+    pub salt: String,
+    /// Hex-encoded nonce and ciphertext of the database key
+    pub key: String,
+}
+
+impl WrappedDbKey {
+    /// Recover the database key with the unlock password
+    ///
+    /// A wrong password surfaces as `IkkiError::InvalidPassword`.
+    pub fn unwrap_with(&self, password: &str) -> anyhow::Result<DbKey> {
+        let salt = hex::decode(&self.salt).map_err(|_| anyhow::anyhow!("Invalid database salt"))?;
+        let key = DbKey::derive(password, &salt)?.open(&self.key)?;
+        let key: [u8; 32] = key
+            .as_slice()
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Invalid database key"))?;
+        Ok(DbKey(Zeroizing::new(key)))
+    }
+}
+
+/// Open the wallet database, applying the key if it is encrypted
+///
+/// A wrong key surfaces as `IkkiError::InvalidPassword`.
+pub fn open_wallet_db(
+    path: &Path,
+    key: Option<&DbKey>,
+    read_only: bool,
+) -> anyhow::Result<Connection> {
+    let flags = if read_only {
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX
+    } else {
+        OpenFlags::default()
+    };
+    let conn = Connection::open_with_flags(path, flags)?;
+    if let Some(key) = key {
+        conn.pragma_update(None, "key", key.sqlcipher_key().as_str())?;
+    }
+
+    // SQLCipher only checks the key when the first page is read
+    conn.query_row("SELECT count(*) FROM sqlite_master", [], |_| Ok(()))
+        .map_err(|_| match key {
+            Some(_) => IkkiError::InvalidPassword,
+            None => IkkiError::Database("Wallet database is encrypted".to_string()),
+        })?;
+    Ok(conn)
+}
+
+/// Whether an existing database file is unencrypted
+pub fn is_plaintext_db(path: &Path) -> anyhow::Result<bool> {
+    let mut header = [0u8; 16];
+    let mut file = std::fs::File::open(path)?;
+    match std::io::Read::read_exact(&mut file, &mut header) {
+        Ok(()) => Ok(header == SQLITE_HEADER),
+        // An empty file has no pages to protect yet
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(true),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Path with a suffix appended to the file name, e.g. `wallet.db-wal`
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Encrypt an existing plaintext wallet database in place
+///
+/// The database is exported into a new encrypted file that then atomically
+/// replaces the original, and the plaintext WAL and SHM files are removed. If
+/// this is interrupted the original file is left untouched.
+pub fn encrypt_wallet_db(path: &Path, key: &DbKey) -> anyhow::Result<()> {
+    let encrypted_path = with_suffix(path, ".encrypting");
+    if encrypted_path.exists() {
+        std::fs::remove_file(&encrypted_path)?;
+    }
+
+    {
+        let conn = Connection::open(path)?;
+        // Fold the WAL into the main file so the export sees every page
+        conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+        let user_version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+
+        conn.execute(
+            "ATTACH DATABASE ?1 AS encrypted KEY ?2",
+            rusqlite::params![
+                encrypted_path.to_string_lossy(),
+                key.sqlcipher_key().as_str()
+            ],
+        )?;
+        conn.query_row("SELECT sqlcipher_export('encrypted')", [], |_| Ok(()))?;
+        conn.pragma_update(Some("encrypted"), "user_version", user_version)?;
+        conn.execute("DETACH DATABASE encrypted", [])?;
+    }
+
+    std::fs::rename(&encrypted_path, path)?;
+    for suffix in ["-wal", "-shm"] {
+        let companion = with_suffix(path, suffix);
+        if companion.exists() {
+            std::fs::remove_file(&companion)?;
+        }
+    }
+
+    info!("Encrypted wallet database {}", path.display());
+    Ok(())
+}
+
+/// Encrypt each of the given databases that exists and is still plaintext
+///
+/// Files that are already encrypted are skipped, so an interrupted run can
+/// simply be repeated.
+pub fn encrypt_plaintext_dbs(paths: &[PathBuf], key: &DbKey) -> anyhow::Result<()> {
+    for path in paths {
+        if path.exists() && is_plaintext_db(path)? {
+            encrypt_wallet_db(path, key)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Create a plaintext database with one row and a schema version
+    fn create_plaintext_db(path: &Path) {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch(
+            "PRAGMA journal_mode = WAL;
+            CREATE TABLE notes (value TEXT NOT NULL);
+            INSERT INTO notes (value) VALUES ('hello');
+            PRAGMA user_version = 7;",
+        )
+        .unwrap();
+    }
+
+    fn read_note(conn: &Connection) -> String {
+        conn.query_row("SELECT value FROM notes", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn user_version(conn: &Connection) -> i64 {
+        conn.pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap()
+    }
+
+    fn is_invalid_password(error: &anyhow::Error) -> bool {
+        matches!(
+            error.downcast_ref::<IkkiError>(),
+            Some(IkkiError::InvalidPassword)
+        )
+    }
+
+    #[test]
+    fn encrypted_db_reopens_only_with_its_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wallet.db");
+        create_plaintext_db(&path);
+        assert!(is_plaintext_db(&path).unwrap());
+
+        let key = DbKey::generate();
+        encrypt_wallet_db(&path, &key).unwrap();
+        assert!(!is_plaintext_db(&path).unwrap());
+        assert!(!with_suffix(&path, "-wal").exists());
+        assert!(!with_suffix(&path, ".encrypting").exists());
+
+        let conn = open_wallet_db(&path, Some(&key), true).unwrap();
+        assert_eq!(read_note(&conn), "hello");
+        assert_eq!(user_version(&conn), 7);
+        drop(conn);
+
+        let error = open_wallet_db(&path, Some(&DbKey::generate()), true).unwrap_err();
+        assert!(is_invalid_password(&error));
+        assert!(open_wallet_db(&path, None, true).is_err());
+    }
+
+    #[test]
+    fn encrypted_db_accepts_writes_after_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wallet.db");
+        create_plaintext_db(&path);
+        let key = DbKey::generate();
+        encrypt_wallet_db(&path, &key).unwrap();
+
+        let conn = open_wallet_db(&path, Some(&key), false).unwrap();
+        conn.execute("UPDATE notes SET value = 'updated'", [])
+            .unwrap();
+        drop(conn);
+
+        assert!(!is_plaintext_db(&path).unwrap());
+        let conn = open_wallet_db(&path, Some(&key), true).unwrap();
+        assert_eq!(read_note(&conn), "updated");
+    }
+
+    #[test]
+    fn encrypting_plaintext_dbs_skips_missing_and_encrypted_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let wallet_db = dir.path().join("wallet.db");
+        let address_book = dir.path().join("address_book.db");
+        let missing = dir.path().join("missing.db");
+        create_plaintext_db(&wallet_db);
+        create_plaintext_db(&address_book);
+        let key = DbKey::generate();
+
+        // An interrupted run that only got through wallet.db
+        encrypt_wallet_db(&wallet_db, &key).unwrap();
+        let paths = [wallet_db.clone(), address_book.clone(), missing.clone()];
+        encrypt_plaintext_dbs(&paths, &key).unwrap();
+        encrypt_plaintext_dbs(&paths, &key).unwrap();
+
+        assert!(!missing.exists());
+        for path in [&wallet_db, &address_book] {
+            let conn = open_wallet_db(path, Some(&key), true).unwrap();
+            assert_eq!(read_note(&conn), "hello");
+        }
+    }
+
+    #[test]
+    fn wrapped_key_unwraps_only_with_its_password() {
+        let key = DbKey::generate();
+        let wrapped = key.wrap("correct horse").unwrap();
+
+        let unwrapped = wrapped.unwrap_with("correct horse").unwrap();
+        assert_eq!(*unwrapped.sqlcipher_key(), *key.sqlcipher_key());
+
+        let error = wrapped.unwrap_with("battery staple").unwrap_err();
+        assert!(is_invalid_password(&error));
+        assert!(key.wrap("").is_err());
+    }
+
+    #[test]
+    fn changing_the_password_keeps_the_db_readable() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("wallet.db");
+        create_plaintext_db(&path);
+        let key = DbKey::generate();
+        encrypt_wallet_db(&path, &key).unwrap();
+        let old = key.wrap("old password").unwrap();
+
+        // A password change only seals the same key under the new password
+        let new = old
+            .unwrap_with("old password")
+            .unwrap()
+            .wrap("new password")
+            .unwrap();
+        assert_ne!(old.salt, new.salt);
+
+        let key = new.unwrap_with("new password").unwrap();
+        let conn = open_wallet_db(&path, Some(&key), true).unwrap();
+        assert_eq!(read_note(&conn), "hello");
+        assert!(is_invalid_password(
+            &new.unwrap_with("old password").unwrap_err()
+        ));
+    }
+}
@@ -0,0 +1,177 @@
+//! Typed wallet errors
+//!
+//! `IkkiWallet` methods return `anyhow::Error`; failures the frontend needs to
+//! distinguish are raised as an `IkkiError` inside it and recovered with
+//! `IkkiError::from`. Commands serialize the error as `{ code, message, details }`.
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+
+/// Error returned by wallet operations and Tauri commands
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum IkkiError {
+    /// lightwalletd could not be reached or returned an error
+    #[error("Network error: {0}")]
+    Network(String),
+    /// Not enough spendable funds for the payment and its fee
+    #[error("Insufficient funds: need {required} zatoshis, have {available} zatoshis")]
+    InsufficientFunds { required: u64, available: u64 },
+    /// The address could not be parsed or is for another network
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+    /// The memo is too long or not allowed for the recipient
+    #[error("Invalid memo: {0}")]
+    InvalidMemo(String),
+    /// Spending keys are not available until the wallet is unlocked
+    #[error("Wallet is locked")]
+    WalletLocked,
+    /// The unlock password does not decrypt the wallet database
+    #[error("Incorrect wallet password")]
+    InvalidPassword,
+    /// The wallet only holds a viewing key; spends must be signed offline
+    #[error("Watch-only wallet cannot sign transactions")]
+    WatchOnly,
+    /// No wallet has been created or loaded
+    #[error("Wallet not initialized")]
+    NotInitialized,
+    /// The operation needs a wallet that has finished syncing
+    #[error("Wallet is not synced: {0}")]
+    NotSynced(String),
+    /// A transaction, contact or other record does not exist
+    #[error("{0} not found")]
+    NotFound(String),
+    /// The wallet or address book database failed
+    #[error("Database error: {0}")]
+    Database(String),
+    /// A transaction proposal could not be created
+    #[error("Failed to create transaction proposal: {0}")]
+    Proposal(String),
+    /// lightwalletd rejected a broadcast transaction
+    #[error("Transaction rejected (code {code}): {message}")]
+    BroadcastRejected { code: i32, message: String },
+    /// A later transaction of a multi-transaction payment failed after earlier ones were broadcast
+    #[error("Payment only partially broadcast ({}): {message}", txids.join(", "))]
+    PartialBroadcast { txids: Vec<String>, message: String },
+    /// Any other failure
+    #[error("{0}")]
+    Other(String),
+}
+
+impl IkkiError {
+    /// Stable machine-readable error code
+    pub fn code(&self) -> &'static str {
+        match self {
+            IkkiError::Network(_) => "network",
+            IkkiError::InsufficientFunds { .. } => "insufficient_funds",
+            IkkiError::InvalidAddress(_) => "invalid_address",
+            IkkiError::InvalidMemo(_) => "invalid_memo",
+            IkkiError::WalletLocked => "wallet_locked",
+            IkkiError::InvalidPassword => "invalid_password",
+            IkkiError::WatchOnly => "watch_only",
+            IkkiError::NotInitialized => "not_initialized",
+            IkkiError::NotSynced(_) => "not_synced",
+            IkkiError::NotFound(_) => "not_found",
+            IkkiError::Database(_) => "database",
+            IkkiError::Proposal(_) => "proposal",
+            IkkiError::BroadcastRejected { .. } => "broadcast_rejected",
+            IkkiError::PartialBroadcast { .. } => "partial_broadcast",
+            IkkiError::Other(_) => "internal",
+        }
+    }
+
+    /// Structured data for errors that carry more than a message
+    pub fn details(&self) -> Option<Value> {
+        match self {
+            IkkiError::InsufficientFunds {
+                required,
+                available,
+            } => Some(json!({ "required": required, "available": available })),
+            IkkiError::BroadcastRejected { code, message } => {
+                Some(json!({ "code": code, "message": message }))
+            }
+            IkkiError::PartialBroadcast { txids, message } => {
+                Some(json!({ "txids": txids, "message": message }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Prefix generic errors with what was being attempted
+    ///
+    /// Typed errors already describe themselves and are returned unchanged.
+    pub fn context(self, context: &str) -> Self {
+        match self {
+            IkkiError::Other(message) => IkkiError::Other(format!("{context}: {message}")),
+            error => error,
+        }
+    }
+}
+
+impl Serialize for IkkiError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("IkkiError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("details", &self.details())?;
+        state.end()
+    }
+}
+
+impl From<anyhow::Error> for IkkiError {
+    fn from(error: anyhow::Error) -> Self {
+        let error = match error.downcast::<IkkiError>() {
+            Ok(error) => return error,
+            Err(error) => error,
+        };
+
+        if error.downcast_ref::<rusqlite::Error>().is_some()
+            || error
+                .downcast_ref::<zcash_client_sqlite::error::SqliteClientError>()
+                .is_some()
+        {
+            IkkiError::Database(format!("{error:#}"))
+        } else if error.downcast_ref::<tonic::Status>().is_some()
+            || error.downcast_ref::<tonic::transport::Error>().is_some()
+        {
+            IkkiError::Network(format!("{error:#}"))
+        } else {
+            IkkiError::Other(format!("{error:#}"))
+        }
+    }
+}
+
+impl From<rusqlite::Error> for IkkiError {
+    fn from(error: rusqlite::Error) -> Self {
+        IkkiError::Database(error.to_string())
+    }
+}
+
+impl From<std::io::Error> for IkkiError {
+    fn from(error: std::io::Error) -> Self {
+        IkkiError::Other(error.to_string())
+    }
+}
+
+impl From<String> for IkkiError {
+    fn from(message: String) -> Self {
+        IkkiError::Other(message)
+    }
+}
+
+impl From<&str> for IkkiError {
+    fn from(message: &str) -> Self {
+        IkkiError::Other(message.to_string())
+    }
+}
+
+/// Convert a failed result into an `IkkiError` with context
+pub trait ErrorContext<T> {
+    fn context(self, context: &str) -> Result<T, IkkiError>;
+}
+
+impl<T, E: Into<IkkiError>> ErrorContext<T> for Result<T, E> {
+    fn context(self, context: &str) -> Result<T, IkkiError> {
+        self.map_err(|e| e.into().context(context))
+    }
+}
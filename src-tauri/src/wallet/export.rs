@@ -0,0 +1,215 @@
+//! Transaction history export for accounting
+//!
+//! Writes the full wallet history to CSV or JSON with a stable column order.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+
+use rusqlite::Connection;
+use serde::Serialize;
+
+use super::history::{self, MemoContent, TransactionKind, TransactionQuery};
+
+/// Number of transactions fetched per history page while exporting
+const EXPORT_PAGE_SIZE: usize = 500;
+
+/// Column order of the CSV export (matches the field order of `ExportRow`)
+const CSV_HEADER: [&str; 11] = [
+    "txid",
+    "date",
+    "height",
+    "direction",
+    "amount_zat",
+    "amount_zec",
+    "fee_zat",
+    "fee_zec",
+    "memo",
+    "counterparty_address",
+    "label",
+];
+
+/// Export file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// Time range of transactions to export (inclusive, Unix seconds)
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExportRange {
+    pub from_time: Option<u64>,
+    pub to_time: Option<u64>,
+}
+
+/// One exported transaction
+#[derive(Debug, Clone, Serialize)]
+struct ExportRow {
+    txid: String,
+    date: String,
+    height: Option<u32>,
+    direction: &'static str,
+    amount_zat: i64,
+    amount_zec: String,
+    fee_zat: Option<u64>,
+    fee_zec: Option<String>,
+    memo: Option<String>,
+    counterparty_address: Option<String>,
+    label: Option<String>,
+}
+
+impl ExportRow {
+    /// Field values in `CSV_HEADER` order
+    fn csv_fields(&self) -> [String; 11] {
+        [
+            self.txid.clone(),
+            self.date.clone(),
+            self.height.map(|h| h.to_string()).unwrap_or_default(),
+            self.direction.to_string(),
+            self.amount_zat.to_string(),
+            self.amount_zec.clone(),
+            self.fee_zat.map(|f| f.to_string()).unwrap_or_default(),
+            self.fee_zec.clone().unwrap_or_default(),
+            self.memo.clone().unwrap_or_default(),
+            self.counterparty_address.clone().unwrap_or_default(),
+            self.label.clone().unwrap_or_default(),
+        ]
+    }
+}
+
+/// Lowercase name of a transaction kind as used in exports
+fn kind_name(kind: TransactionKind) -> &'static str {
+    match kind {
+        TransactionKind::Sent => "sent",
+        TransactionKind::Received => "received",
+        TransactionKind::Shielding => "shielding",
+        TransactionKind::Internal => "internal",
+        TransactionKind::Mixed => "mixed",
+    }
+}
+
+/// Format zatoshis as a ZEC decimal string with 8 fractional digits
+fn format_zec(zatoshis: i64) -> String {
+    let sign = if zatoshis < 0 { "-" } else { "" };
+    let abs = zatoshis.unsigned_abs();
+    format!("{sign}{}.{:08}", abs / 100_000_000, abs % 100_000_000)
+}
+
+/// Format a Unix timestamp as an RFC 3339 UTC date
+fn format_date(timestamp: u64) -> String {
+    chrono::DateTime::from_timestamp(timestamp as i64, 0)
+        .map(|d| d.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+        .unwrap_or_default()
+}
+
+/// Quote a CSV field if it contains separators, quotes or line breaks
+fn csv_escape(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Collect every transaction in the range as export rows, newest first
+///
+/// `labels` maps counterparty addresses to address book names.
+fn collect_rows(
+    conn: &Connection,
+    range: ExportRange,
+    labels: &HashMap<String, String>,
+) -> anyhow::Result<Vec<ExportRow>> {
+    let mut rows = Vec::new();
+    let mut cursor = None;
+
+    loop {
+        let query = TransactionQuery {
+            from_time: range.from_time,
+            to_time: range.to_time,
+            cursor,
+            limit: EXPORT_PAGE_SIZE,
+            ..Default::default()
+        };
+        let page = history::query_transactions(conn, &query)?;
+
+        for record in page.transactions {
+            let details = history::get_transaction_details(conn, &record.txid)?;
+
+            // The counterparty is only known for outputs the wallet sent elsewhere
+            let (fee, counterparty_address, memo) = match details {
+                Some(d) => {
+                    let counterparty = d
+                        .outputs
+                        .iter()
+                        .find(|o| !o.is_received && o.address.is_some())
+                        .and_then(|o| o.address.clone());
+                    let memo = d.outputs.iter().find_map(|o| match &o.memo {
+                        Some(MemoContent::Text(text)) => Some(text.clone()),
+                        Some(MemoContent::Arbitrary(data)) | Some(MemoContent::Future(data)) => {
+                            Some(hex::encode(data))
+                        }
+                        _ => None,
+                    });
+                    (d.fee, counterparty, memo)
+                }
+                None => (None, record.address.clone(), record.memo.clone()),
+            };
+            let label = counterparty_address
+                .as_ref()
+                .and_then(|address| labels.get(address))
+                .cloned();
+
+            rows.push(ExportRow {
+                txid: record.txid,
+                date: format_date(record.timestamp),
+                height: record.mined_height,
+                direction: kind_name(record.kind),
+                amount_zat: record.amount,
+                amount_zec: format_zec(record.amount),
+                fee_zat: fee,
+                fee_zec: fee.map(|f| format_zec(f as i64)),
+                memo,
+                counterparty_address,
+                label,
+            });
+        }
+
+        match page.next_cursor {
+            Some(next) => cursor = Some(next),
+            None => break,
+        }
+    }
+
+    Ok(rows)
+}
+
+/// Export transaction history to a file, returning the number of transactions written
+pub fn export_transactions(
+    conn: &Connection,
+    format: ExportFormat,
+    range: ExportRange,
+    labels: &HashMap<String, String>,
+    path: &Path,
+) -> anyhow::Result<usize> {
+    let rows = collect_rows(conn, range, labels)?;
+
+    let mut file = std::fs::File::create(path)
+        .map_err(|e| anyhow::anyhow!("Failed to create export file: {e}"))?;
+
+    match format {
+        ExportFormat::Csv => {
+            writeln!(file, "{}", CSV_HEADER.join(","))?;
+            for row in &rows {
+                let line: Vec<String> = row.csv_fields().iter().map(|f| csv_escape(f)).collect();
+                writeln!(file, "{}", line.join(","))?;
+            }
+        }
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut file, &rows)?;
+        }
+    }
+
+    file.flush()?;
+    Ok(rows.len())
+}
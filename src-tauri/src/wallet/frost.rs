@@ -0,0 +1,731 @@
+//! FROST threshold multisig for Orchard
+//!
+//! A t-of-n group shares one Orchard account. Its spend validating key `ak` is
+//! a FROST (RedPallas) group key created by a trusted dealer or by a
+//! distributed key generation, so no single participant can spend. The
+//! nullifier and commitment randomness keys are shared by every participant,
+//! which gives the group a normal UFVK that a watch-only wallet imports.
+//!
+//! Spends are authorized over exchanged files: the coordinator creates a PCZT
+//! with the watch-only wallet, participants commit to nonces, the coordinator
+//! builds a signing package, participants sign it and the coordinator
+//! aggregates the shares into the PCZT's Orchard spend authorization
+//! signatures. Each Orchard spend is signed with its PCZT `alpha` as the FROST
+//! randomizer, so the signatures verify against the spend's `rk`.
+
+use std::collections::BTreeMap;
+
+use pczt::roles::signer::Signer;
+use pczt::Pczt;
+use rand_core::{OsRng, RngCore};
+use reddsa::frost::redpallas as frost;
+use serde::{Deserialize, Serialize};
+use zcash_client_backend::keys::UnifiedFullViewingKey;
+
+use crate::wallet::offline::prove_pczt;
+use crate::wallet::{Network, WatchOnlyKey};
+
+/// Public data of a multisig account, shared by participants and the coordinator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrostGroup {
+    /// Signatures needed to spend
+    pub min_signers: u16,
+    /// Number of participants
+    pub max_signers: u16,
+    /// Unified full viewing key of the shared Orchard account
+    pub ufvk: String,
+    /// Group verifying key and each participant's verifying share
+    pub public_key_package: frost::keys::PublicKeyPackage,
+}
+
+impl FrostGroup {
+    /// Viewing key for the coordinator's watch-only wallet
+    pub fn viewing_key(&self) -> WatchOnlyKey {
+        WatchOnlyKey {
+            ufvk: self.ufvk.clone(),
+            seed_fingerprint: None,
+        }
+    }
+}
+
+/// A participant's secret share of a multisig account
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrostKeyShare {
+    /// Participant number, from 1 to `max_signers`
+    pub identifier: u16,
+    pub key_package: frost::keys::KeyPackage,
+    pub group: FrostGroup,
+}
+
+/// Participant identifier for a participant number
+fn identifier(participant: u16) -> anyhow::Result<frost::Identifier> {
+    frost::Identifier::try_from(participant)
+        .map_err(|e| anyhow::anyhow!("Invalid participant number {participant}: {e}"))
+}
+
+/// Check the threshold parameters of a new group
+fn check_threshold(min_signers: u16, max_signers: u16) -> anyhow::Result<()> {
+    if min_signers < 2 || min_signers > max_signers {
+        anyhow::bail!(
+            "Threshold must be between 2 and the number of participants, got {min_signers} of \
+             {max_signers}"
+        );
+    }
+    Ok(())
+}
+
+/// Whether a serialized Pallas point has its y-coordinate sign bit set
+fn has_odd_y(point: &[u8]) -> bool {
+    point.last().is_some_and(|byte| byte & 0x80 != 0)
+}
+
+/// Replace a group key with an odd y-coordinate by its negation
+///
+/// Orchard only accepts an `ak` whose y-coordinate has the sign bit clear, so
+/// about half of all group keys cannot be used as-is. Negating the group key
+/// together with every signing share and verifying share gives an equivalent
+/// group: the negated shares interpolate to the negated group secret. Every
+/// DKG participant sees the same group key, so each normalizes its own share
+/// the same way.
+fn into_even_y(
+    public_key_package: frost::keys::PublicKeyPackage,
+    key_packages: Vec<frost::keys::KeyPackage>,
+) -> anyhow::Result<(frost::keys::PublicKeyPackage, Vec<frost::keys::KeyPackage>)> {
+    let group_key = public_key_package
+        .verifying_key()
+        .serialize()
+        .map_err(|e| anyhow::anyhow!("Failed to encode group key: {e}"))?;
+    if !has_odd_y(&group_key) {
+        return Ok((public_key_package, key_packages));
+    }
+
+    let verifying_key = frost::VerifyingKey::new(-public_key_package.verifying_key().to_element());
+    let negate_share =
+        |share: &frost::keys::VerifyingShare| frost::keys::VerifyingShare::new(-share.to_element());
+    let verifying_shares = public_key_package
+        .verifying_shares()
+        .iter()
+        .map(|(id, share)| (*id, negate_share(share)))
+        .collect();
+    let key_packages = key_packages
+        .into_iter()
+        .map(|package| {
+            frost::keys::KeyPackage::new(
+                *package.identifier(),
+                frost::keys::SigningShare::new(-package.signing_share().to_scalar()),
+                negate_share(package.verifying_share()),
+                verifying_key,
+                *package.min_signers(),
+            )
+        })
+        .collect();
+    Ok((
+        frost::keys::PublicKeyPackage::new(verifying_shares, verifying_key),
+        key_packages,
+    ))
+}
+
+/// Build the group's UFVK from its FROST group key and shared viewing entropy
+///
+/// The entropy is expanded into an Orchard spending key whose `nk` and `rivk`
+/// complete the full viewing key; its `ask` is discarded.
+fn group_ufvk(
+    public_key_package: &frost::keys::PublicKeyPackage,
+    entropy: [u8; 32],
+    network: Network,
+) -> anyhow::Result<String> {
+    let sk = Option::from(orchard::keys::SpendingKey::from_bytes(entropy))
+        .ok_or_else(|| anyhow::anyhow!("Viewing key entropy does not give a valid key"))?;
+    let viewing = orchard::keys::FullViewingKey::from(&sk).to_bytes();
+
+    let ak = public_key_package
+        .verifying_key()
+        .serialize()
+        .map_err(|e| anyhow::anyhow!("Failed to encode group key: {e}"))?;
+    let mut fvk_bytes = [0u8; 96];
+    fvk_bytes[..32].copy_from_slice(&ak);
+    fvk_bytes[32..].copy_from_slice(&viewing[32..]);
+    let fvk = orchard::keys::FullViewingKey::from_bytes(&fvk_bytes)
+        .ok_or_else(|| anyhow::anyhow!("Group key is not a valid Orchard spend validating key"))?;
+
+    let ufvk = UnifiedFullViewingKey::from_orchard_fvk(fvk)
+        .map_err(|e| anyhow::anyhow!("Failed to build UFVK: {e:?}"))?;
+    Ok(ufvk.encode(&network))
+}
+
+/// Split a new multisig account into shares with a trusted dealer
+///
+/// The dealer sees every share, so the shares must be handed out and the
+/// dealer's copies destroyed.
+pub fn frost_dealer_keygen(
+    min_signers: u16,
+    max_signers: u16,
+    network: Network,
+) -> anyhow::Result<Vec<FrostKeyShare>> {
+    check_threshold(min_signers, max_signers)?;
+    let (shares, public_key_package) = frost::keys::generate_with_dealer(
+        max_signers,
+        min_signers,
+        frost::keys::IdentifierList::Default,
+        OsRng,
+    )
+    .map_err(|e| anyhow::anyhow!("Key generation failed: {e}"))?;
+
+    let key_packages = (1..=max_signers)
+        .map(|participant| {
+            let share = shares
+                .get(&identifier(participant)?)
+                .ok_or_else(|| anyhow::anyhow!("Missing share for participant {participant}"))?;
+            frost::keys::KeyPackage::try_from(share.clone())
+                .map_err(|e| anyhow::anyhow!("Invalid share: {e}"))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let (public_key_package, key_packages) = into_even_y(public_key_package, key_packages)?;
+
+    let mut entropy = [0u8; 32];
+    OsRng.fill_bytes(&mut entropy);
+    let group = FrostGroup {
+        min_signers,
+        max_signers,
+        ufvk: group_ufvk(&public_key_package, entropy, network)?,
+        public_key_package,
+    };
+
+    Ok((1..=max_signers)
+        .zip(key_packages)
+        .map(|(participant, key_package)| FrostKeyShare {
+            identifier: participant,
+            key_package,
+            group: group.clone(),
+        })
+        .collect())
+}
+
+/// Round 1 DKG message, broadcast to every other participant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DkgRound1Package {
+    pub identifier: u16,
+    pub package: frost::keys::dkg::round1::Package,
+    /// Hex-encoded contribution to the group's viewing key
+    pub viewing_entropy: String,
+}
+
+/// Round 2 DKG message, sent privately to one participant
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DkgRound2Package {
+    pub sender: u16,
+    pub recipient: u16,
+    pub package: frost::keys::dkg::round2::Package,
+}
+
+/// Secret state a participant keeps between DKG rounds
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DkgSecret {
+    pub identifier: u16,
+    pub min_signers: u16,
+    pub max_signers: u16,
+    pub round1_secret: Option<frost::keys::dkg::round1::SecretPackage>,
+    pub round2_secret: Option<frost::keys::dkg::round2::SecretPackage>,
+    /// Round 1 packages of every participant, including our own
+    #[serde(default)]
+    pub round1_packages: Vec<DkgRound1Package>,
+}
+
+/// Start a distributed key generation as participant `participant`
+pub fn frost_dkg_part1(
+    participant: u16,
+    min_signers: u16,
+    max_signers: u16,
+) -> anyhow::Result<(DkgSecret, DkgRound1Package)> {
+    check_threshold(min_signers, max_signers)?;
+    let (secret, package) =
+        frost::keys::dkg::part1(identifier(participant)?, max_signers, min_signers, OsRng)
+            .map_err(|e| anyhow::anyhow!("DKG round 1 failed: {e}"))?;
+
+    let mut entropy = [0u8; 32];
+    OsRng.fill_bytes(&mut entropy);
+    Ok((
+        DkgSecret {
+            identifier: participant,
+            min_signers,
+            max_signers,
+            round1_secret: Some(secret),
+            round2_secret: None,
+            round1_packages: Vec::new(),
+        },
+        DkgRound1Package {
+            identifier: participant,
+            package,
+            viewing_entropy: hex::encode(entropy),
+        },
+    ))
+}
+
+/// Process the round 1 packages, producing a round 2 package for each other
+/// participant
+///
+/// `received` includes our own round 1 package, since its viewing entropy is
+/// needed in part 3.
+pub fn frost_dkg_part2(
+    secret: &mut DkgSecret,
+    received: Vec<DkgRound1Package>,
+) -> anyhow::Result<Vec<DkgRound2Package>> {
+    if received.len() != usize::from(secret.max_signers) {
+        anyhow::bail!(
+            "Expected round 1 packages from all {} participants, got {}",
+            secret.max_signers,
+            received.len()
+        );
+    }
+    let round1_secret = secret
+        .round1_secret
+        .take()
+        .ok_or_else(|| anyhow::anyhow!("DKG round 2 has already been run"))?;
+
+    let (round2_secret, packages) =
+        frost::keys::dkg::part2(round1_secret, &round1_map(secret.identifier, &received)?)
+            .map_err(|e| anyhow::anyhow!("DKG round 2 failed: {e}"))?;
+
+    let packages = received
+        .iter()
+        .filter(|package| package.identifier != secret.identifier)
+        .map(|package| {
+            let round2 = packages
+                .get(&identifier(package.identifier)?)
+                .ok_or_else(|| anyhow::anyhow!("Missing package for {}", package.identifier))?;
+            Ok(DkgRound2Package {
+                sender: secret.identifier,
+                recipient: package.identifier,
+                package: round2.clone(),
+            })
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    secret.round2_secret = Some(round2_secret);
+    secret.round1_packages = received;
+    Ok(packages)
+}
+
+/// Finish the distributed key generation with the round 2 packages sent to us
+pub fn frost_dkg_part3(
+    secret: &DkgSecret,
+    received: &[DkgRound2Package],
+    network: Network,
+) -> anyhow::Result<FrostKeyShare> {
+    let round2_secret = secret
+        .round2_secret
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("DKG round 2 has not been run"))?;
+
+    let mut round2_packages = BTreeMap::new();
+    for package in received {
+        if package.recipient != secret.identifier {
+            anyhow::bail!(
+                "Round 2 package from {} is for participant {}",
+                package.sender,
+                package.recipient
+            );
+        }
+        round2_packages.insert(identifier(package.sender)?, package.package.clone());
+    }
+
+    let (key_package, public_key_package) = frost::keys::dkg::part3(
+        round2_secret,
+        &round1_map(secret.identifier, &secret.round1_packages)?,
+        &round2_packages,
+    )
+    .map_err(|e| anyhow::anyhow!("DKG round 3 failed: {e}"))?;
+    let (public_key_package, mut key_packages) =
+        into_even_y(public_key_package, vec![key_package])?;
+    let key_package = key_packages.remove(0);
+
+    // Every participant combines the same contributions into the viewing key
+    let mut entropy = [0u8; 32];
+    for package in &secret.round1_packages {
+        let contribution = hex::decode(&package.viewing_entropy)
+            .ok()
+            .filter(|bytes| bytes.len() == 32)
+            .ok_or_else(|| {
+                anyhow::anyhow!("Invalid viewing entropy from {}", package.identifier)
+            })?;
+        for (byte, other) in entropy.iter_mut().zip(contribution) {
+            *byte ^= other;
+        }
+    }
+
+    Ok(FrostKeyShare {
+        identifier: secret.identifier,
+        key_package,
+        group: FrostGroup {
+            min_signers: secret.min_signers,
+            max_signers: secret.max_signers,
+            ufvk: group_ufvk(&public_key_package, entropy, network)?,
+            public_key_package,
+        },
+    })
+}
+
+/// Round 1 packages of the other participants, keyed by identifier
+fn round1_map(
+    own: u16,
+    packages: &[DkgRound1Package],
+) -> anyhow::Result<BTreeMap<frost::Identifier, frost::keys::dkg::round1::Package>> {
+    packages
+        .iter()
+        .filter(|package| package.identifier != own)
+        .map(|package| Ok((identifier(package.identifier)?, package.package.clone())))
+        .collect()
+}
+
+/// Orchard spend of a PCZT that needs the group's signature
+struct UnsignedSpend {
+    /// Index of the action in the Orchard bundle
+    index: usize,
+    /// The spend's `alpha`, used as the FROST randomizer
+    randomizer: frost::Randomizer,
+}
+
+/// Shielded sighash and unsigned Orchard spends of a PCZT
+///
+/// Dummy spends are signed when the PCZT is created, so every spend still
+/// missing a signature belongs to the group.
+fn signing_data(pczt: &[u8]) -> anyhow::Result<([u8; 32], Vec<UnsignedSpend>)> {
+    let pczt = Pczt::parse(pczt).map_err(|e| anyhow::anyhow!("Invalid PCZT: {e:?}"))?;
+    let spends = pczt
+        .orchard()
+        .actions()
+        .iter()
+        .enumerate()
+        .filter(|(_, action)| action.spend().spend_auth_sig().is_none())
+        .map(|(index, action)| {
+            let alpha = action
+                .spend()
+                .alpha()
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("Orchard action {index} has no alpha"))?;
+            let randomizer = frost::Randomizer::deserialize(alpha)
+                .map_err(|e| anyhow::anyhow!("Invalid alpha in action {index}: {e}"))?;
+            Ok(UnsignedSpend { index, randomizer })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if spends.is_empty() {
+        anyhow::bail!("The PCZT has no Orchard spends to sign");
+    }
+
+    let signer = Signer::new(pczt).map_err(|e| anyhow::anyhow!("Failed to read PCZT: {e:?}"))?;
+    Ok((signer.shielded_sighash(), spends))
+}
+
+/// Round 1 signing message: one nonce commitment per Orchard spend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrostCommitments {
+    pub identifier: u16,
+    pub commitments: Vec<frost::round1::SigningCommitments>,
+}
+
+/// Secret nonces matching a participant's commitments
+///
+/// Must be used for at most one signature and then deleted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrostNonces {
+    pub identifier: u16,
+    pub nonces: Vec<frost::round1::SigningNonces>,
+}
+
+/// Commit to signing nonces for every Orchard spend of a PCZT
+pub fn frost_commit(
+    share: &FrostKeyShare,
+    pczt: &[u8],
+) -> anyhow::Result<(FrostNonces, FrostCommitments)> {
+    let (_, spends) = signing_data(pczt)?;
+    let (nonces, commitments) = spends
+        .iter()
+        .map(|_| frost::round1::commit(share.key_package.signing_share(), &mut OsRng))
+        .unzip();
+    Ok((
+        FrostNonces {
+            identifier: share.identifier,
+            nonces,
+        },
+        FrostCommitments {
+            identifier: share.identifier,
+            commitments,
+        },
+    ))
+}
+
+/// Round 2 signing message assembled by the coordinator
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrostSigningPackage {
+    /// Hex-encoded PCZT being signed, so participants can check what they sign
+    pub pczt: String,
+    /// One signing package per Orchard spend
+    pub packages: Vec<frost::SigningPackage>,
+}
+
+/// Combine the participants' commitments into a signing package
+pub fn frost_signing_package(
+    group: &FrostGroup,
+    pczt: &[u8],
+    commitments: &[FrostCommitments],
+) -> anyhow::Result<FrostSigningPackage> {
+    if commitments.len() < usize::from(group.min_signers) {
+        anyhow::bail!(
+            "Need commitments from {} participants, got {}",
+            group.min_signers,
+            commitments.len()
+        );
+    }
+    let (sighash, spends) = signing_data(pczt)?;
+
+    let packages = (0..spends.len())
+        .map(|spend| {
+            let mut signers = BTreeMap::new();
+            for participant in commitments {
+                let commitment = participant.commitments.get(spend).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Participant {} sent too few commitments",
+                        participant.identifier
+                    )
+                })?;
+                signers.insert(identifier(participant.identifier)?, *commitment);
+            }
+            Ok(frost::SigningPackage::new(signers, &sighash))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(FrostSigningPackage {
+        pczt: hex::encode(pczt),
+        packages,
+    })
+}
+
+/// Round 2 signing message: one signature share per Orchard spend
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FrostSignatureShares {
+    pub identifier: u16,
+    pub shares: Vec<frost::round2::SignatureShare>,
+}
+
+/// Sign every Orchard spend of a signing package
+///
+/// The sighash is recomputed from the PCZT rather than trusted from the
+/// coordinator.
+pub fn frost_sign(
+    share: &FrostKeyShare,
+    nonces: &FrostNonces,
+    package: &FrostSigningPackage,
+) -> anyhow::Result<FrostSignatureShares> {
+    let pczt = hex::decode(&package.pczt).map_err(|_| anyhow::anyhow!("Invalid PCZT encoding"))?;
+    let (sighash, spends) = signing_data(&pczt)?;
+    if package.packages.len() != spends.len() || nonces.nonces.len() != spends.len() {
+        anyhow::bail!("The signing package does not match the PCZT's Orchard spends");
+    }
+
+    let shares = spends
+        .iter()
+        .zip(&package.packages)
+        .zip(&nonces.nonces)
+        .map(|((spend, signing_package), nonces)| {
+            if signing_package.message() != sighash.as_slice() {
+                anyhow::bail!("The signing package does not sign the PCZT's transaction");
+            }
+            frost::round2::sign(
+                signing_package,
+                nonces,
+                &share.key_package,
+                spend.randomizer,
+            )
+            .map_err(|e| anyhow::anyhow!("Failed to sign spend {}: {e}", spend.index))
+        })
+        .collect::<anyhow::Result<_>>()?;
+
+    Ok(FrostSignatureShares {
+        identifier: share.identifier,
+        shares,
+    })
+}
+
+/// Aggregate the signature shares into the PCZT and create its proofs
+///
+/// Returns the serialized PCZT, ready to broadcast.
+pub fn frost_aggregate(
+    group: &FrostGroup,
+    package: &FrostSigningPackage,
+    shares: &[FrostSignatureShares],
+) -> anyhow::Result<Vec<u8>> {
+    let pczt = hex::decode(&package.pczt).map_err(|_| anyhow::anyhow!("Invalid PCZT encoding"))?;
+    let (_, spends) = signing_data(&pczt)?;
+    let pczt = Pczt::parse(&pczt).map_err(|e| anyhow::anyhow!("Invalid PCZT: {e:?}"))?;
+    let pczt = prove_pczt(pczt)?;
+    let mut signer =
+        Signer::new(pczt).map_err(|e| anyhow::anyhow!("Failed to read PCZT: {e:?}"))?;
+
+    let group_key = group.public_key_package.verifying_key();
+    for (position, (spend, signing_package)) in spends.iter().zip(&package.packages).enumerate() {
+        let mut signature_shares = BTreeMap::new();
+        for participant in shares {
+            let share = participant.shares.get(position).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Participant {} sent too few signature shares",
+                    participant.identifier
+                )
+            })?;
+            signature_shares.insert(identifier(participant.identifier)?, *share);
+        }
+
+        let params = frost::RandomizedParams::from_randomizer(group_key, spend.randomizer);
+        let signature = frost::aggregate(
+            signing_package,
+            &signature_shares,
+            &group.public_key_package,
+            &params,
+        )
+        .map_err(|e| anyhow::anyhow!("Failed to aggregate spend {}: {e}", spend.index))?;
+
+        let bytes: [u8; 64] = signature
+            .serialize()
+            .map_err(|e| anyhow::anyhow!("Failed to encode signature: {e}"))?
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Unexpected signature length"))?;
+        let signature = orchard::primitives::redpallas::Signature::from(bytes);
+        signer
+            .apply_orchard_signature(spend.index, signature)
+            .map_err(|e| anyhow::anyhow!("Signature for spend {} rejected: {e:?}", spend.index))?;
+    }
+
+    Ok(signer.finish().serialize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Sign a message with the given participants, failing if the shares do
+    /// not aggregate to a valid signature under the group key
+    fn sign_with(signers: &[&FrostKeyShare]) -> anyhow::Result<()> {
+        let group = &signers[0].group;
+        let message = b"ikki frost test";
+        let mut alpha = [0u8; 32];
+        alpha[0] = 7;
+        let randomizer = frost::Randomizer::deserialize(&alpha)?;
+
+        let mut nonces = BTreeMap::new();
+        let mut commitments = BTreeMap::new();
+        for share in signers {
+            let (nonce, commitment) =
+                frost::round1::commit(share.key_package.signing_share(), &mut OsRng);
+            nonces.insert(share.identifier, nonce);
+            commitments.insert(identifier(share.identifier)?, commitment);
+        }
+        let signing_package = frost::SigningPackage::new(commitments, message);
+
+        let mut signature_shares = BTreeMap::new();
+        for share in signers {
+            let signature_share = frost::round2::sign(
+                &signing_package,
+                &nonces[&share.identifier],
+                &share.key_package,
+                randomizer,
+            )?;
+            signature_shares.insert(identifier(share.identifier)?, signature_share);
+        }
+
+        let params = frost::RandomizedParams::from_randomizer(
+            group.public_key_package.verifying_key(),
+            randomizer,
+        );
+        frost::aggregate(
+            &signing_package,
+            &signature_shares,
+            &group.public_key_package,
+            &params,
+        )?;
+        Ok(())
+    }
+
+    fn assert_even_y(group: &FrostGroup) {
+        let group_key = group
+            .public_key_package
+            .verifying_key()
+            .serialize()
+            .unwrap();
+        assert!(!has_odd_y(&group_key));
+    }
+
+    #[test]
+    fn dealer_groups_always_have_an_orchard_ufvk() {
+        // Half of all random group keys have an odd y, so without
+        // normalization this fails with probability 1 - 2^-32
+        for _ in 0..32 {
+            let shares = frost_dealer_keygen(2, 3, Network::Testnet).unwrap();
+            let group = &shares[0].group;
+            assert_even_y(group);
+            UnifiedFullViewingKey::decode(&Network::Testnet, &group.ufvk).unwrap();
+            sign_with(&[&shares[0], &shares[2]]).unwrap();
+        }
+    }
+
+    #[test]
+    fn dkg_participants_agree_on_an_orchard_ufvk() {
+        for _ in 0..8 {
+            let (mut secrets, round1): (Vec<_>, Vec<_>) = (1..=3)
+                .map(|participant| frost_dkg_part1(participant, 2, 3).unwrap())
+                .unzip();
+            let round2: Vec<_> = secrets
+                .iter_mut()
+                .flat_map(|secret| frost_dkg_part2(secret, round1.clone()).unwrap())
+                .collect();
+            let shares: Vec<_> = secrets
+                .iter()
+                .map(|secret| {
+                    let received: Vec<_> = round2
+                        .iter()
+                        .filter(|package| package.recipient == secret.identifier)
+                        .cloned()
+                        .collect();
+                    frost_dkg_part3(secret, &received, Network::Testnet).unwrap()
+                })
+                .collect();
+
+            assert_even_y(&shares[0].group);
+            assert!(shares
+                .iter()
+                .all(|share| share.group.ufvk == shares[0].group.ufvk));
+            sign_with(&[&shares[1], &shares[2]]).unwrap();
+        }
+    }
+
+    #[test]
+    fn odd_y_groups_are_negated_consistently() {
+        let mut found_odd = false;
+        while !found_odd {
+            let (shares, public_key_package) = frost::keys::generate_with_dealer(
+                3,
+                2,
+                frost::keys::IdentifierList::Default,
+                OsRng,
+            )
+            .unwrap();
+            let group_key = public_key_package.verifying_key().serialize().unwrap();
+            found_odd = has_odd_y(&group_key);
+
+            let key_packages: Vec<_> = shares
+                .into_values()
+                .map(|share| frost::keys::KeyPackage::try_from(share).unwrap())
+                .collect();
+            let (normalized, key_packages) = into_even_y(public_key_package, key_packages).unwrap();
+            let normalized_key = normalized.verifying_key().serialize().unwrap();
+            assert!(!has_odd_y(&normalized_key));
+            assert_eq!(normalized_key[..31], group_key[..31]);
+
+            for package in &key_packages {
+                assert_eq!(package.verifying_key(), normalized.verifying_key());
+                assert_eq!(
+                    Some(package.verifying_share()),
+                    normalized.verifying_shares().get(package.identifier())
+                );
+            }
+        }
+    }
+}
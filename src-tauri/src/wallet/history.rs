@@ -0,0 +1,893 @@
+//! Transaction history queries
+//!
+//! Reads transaction history directly from the wallet database views.
+
+use anyhow::Context;
+use rusqlite::functions::FunctionFlags;
+use rusqlite::types::Value;
+use rusqlite::Connection;
+use zcash_protocol::memo::{Memo, MemoBytes};
+
+/// Sort key used for pending transactions so they come before mined ones
+const PENDING_SORT_HEIGHT: i64 = u32::MAX as i64;
+
+/// Default page size for history queries
+const DEFAULT_PAGE_SIZE: usize = 50;
+
+/// SQL function returning the decoded text of a memo, or NULL if it is not text
+const MEMO_TEXT_FN: &str = "ikki_memo_text";
+
+/// Timestamp used for pending transactions in time filters
+const PENDING_TIME_SQL: &str = "COALESCE(block_time, CAST(strftime('%s', 'now') AS INTEGER))";
+
+/// SQL expression classifying a `v_transactions` row (see `TransactionKind`)
+///
+/// Transactions the wallet did not fund are received. Funded transactions are
+/// classified by their outputs: to external recipients only (sent), to the
+/// wallet only (internal), or both (mixed). Value leaving the wallet beyond the
+/// fee also counts as sent, for outputs the wallet has no record of.
+const TX_KIND_SQL: &str = "CASE
+        WHEN v_transactions.is_shielding THEN 'shielding'
+        WHEN v_transactions.total_spent = 0 THEN 'received'
+        WHEN ext.output_count > 0 AND own.output_count > 0 THEN 'mixed'
+        WHEN ext.output_count > 0
+            OR v_transactions.account_balance_delta + COALESCE(v_transactions.fee_paid, 0) < 0
+            THEN 'sent'
+        ELSE 'internal'
+    END";
+
+/// Joins providing the external and own non-change output counts used by `TX_KIND_SQL`
+const TX_KIND_JOINS: &str = "LEFT JOIN (
+        SELECT txid, COUNT(*) AS output_count FROM v_tx_outputs
+        WHERE to_account_uuid IS NULL GROUP BY txid
+    ) ext ON ext.txid = v_transactions.txid
+    LEFT JOIN (
+        SELECT txid, COUNT(*) AS output_count FROM v_tx_outputs
+        WHERE to_account_uuid IS NOT NULL AND is_change = 0 GROUP BY txid
+    ) own ON own.txid = v_transactions.txid";
+
+/// Transaction record for display
+#[derive(Debug, Clone)]
+pub struct TransactionRecord {
+    /// Transaction ID as hex string
+    pub txid: String,
+    /// Amount in zatoshis (negative for sent)
+    pub amount: i64,
+    /// Unix timestamp (approximate from block height)
+    pub timestamp: u64,
+    /// How the transaction moved funds relative to the wallet
+    pub kind: TransactionKind,
+    /// Memo text if available
+    pub memo: Option<String>,
+    /// Whether this transaction is pending (unmined)
+    pub is_pending: bool,
+    /// Block height where transaction was mined (None if pending)
+    pub mined_height: Option<u32>,
+    /// External recipient address, if the wallet sent funds to one
+    pub address: Option<String>,
+    /// Address book name matching `address`
+    pub contact_name: Option<String>,
+}
+
+/// Classification of a transaction relative to the wallet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionKind {
+    /// Funds sent to external recipients
+    Sent,
+    /// Funds received from someone else
+    Received,
+    /// Transparent funds moved into a shielded pool
+    Shielding,
+    /// Funds moved between the wallet's own addresses or pools
+    Internal,
+    /// Funds sent to external recipients and to the wallet's own addresses
+    Mixed,
+}
+
+impl TransactionKind {
+    /// Parse the kind produced by `TX_KIND_SQL`
+    fn from_sql(kind: &str) -> Self {
+        match kind {
+            "shielding" => TransactionKind::Shielding,
+            "received" => TransactionKind::Received,
+            "mixed" => TransactionKind::Mixed,
+            "internal" => TransactionKind::Internal,
+            _ => TransactionKind::Sent,
+        }
+    }
+}
+
+/// Direction of a transaction relative to the wallet
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionDirection {
+    Sent,
+    Received,
+}
+
+/// Value pool of a transaction output
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ValuePool {
+    Transparent,
+    Sapling,
+    Orchard,
+}
+
+impl ValuePool {
+    /// Pool code used by the wallet database
+    fn code(self) -> i64 {
+        match self {
+            ValuePool::Transparent => 0,
+            ValuePool::Sapling => 2,
+            ValuePool::Orchard => 3,
+        }
+    }
+
+    /// Parse a pool code from the wallet database
+    fn from_code(code: i64) -> Option<Self> {
+        match code {
+            0 => Some(ValuePool::Transparent),
+            2 => Some(ValuePool::Sapling),
+            3 => Some(ValuePool::Orchard),
+            _ => None,
+        }
+    }
+}
+
+/// Filters and pagination for a transaction history query
+///
+/// All filters are optional and combined with AND.
+#[derive(Debug, Clone, Default)]
+pub struct TransactionQuery {
+    /// Only sent or only received transactions
+    pub direction: Option<TransactionDirection>,
+    /// Only transactions with outputs in this pool
+    pub pool: Option<ValuePool>,
+    /// Earliest timestamp (inclusive, Unix seconds)
+    pub from_time: Option<u64>,
+    /// Latest timestamp (inclusive, Unix seconds)
+    pub to_time: Option<u64>,
+    /// Minimum absolute amount in zatoshis
+    pub min_amount: Option<u64>,
+    /// Maximum absolute amount in zatoshis
+    pub max_amount: Option<u64>,
+    /// Text matched against memos and txids
+    pub search: Option<String>,
+    /// Only pending (true) or only mined (false) transactions
+    pub is_pending: Option<bool>,
+    /// Cursor returned by the previous page
+    pub cursor: Option<String>,
+    /// Maximum number of transactions to return (defaults to 50)
+    pub limit: usize,
+}
+
+/// A page of transaction history
+#[derive(Debug, Clone)]
+pub struct TransactionPage {
+    /// Transactions in this page, newest first
+    pub transactions: Vec<TransactionRecord>,
+    /// Cursor for the next page (None if this is the last page)
+    pub next_cursor: Option<String>,
+}
+
+/// Memo content, typed according to ZIP 302
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MemoContent {
+    /// No memo (0xF6 followed by zeros)
+    Empty,
+    /// UTF-8 text memo
+    Text(String),
+    /// Arbitrary binary data, or a text memo that is not valid UTF-8
+    Arbitrary(Vec<u8>),
+    /// Memo in a format reserved for future use
+    Future(Vec<u8>),
+}
+
+/// A memo attached to one output of a transaction
+#[derive(Debug, Clone)]
+pub struct TransactionMemo {
+    /// Pool of the output carrying the memo
+    pub pool: ValuePool,
+    /// Index of the output within its pool's bundle
+    pub output_index: u32,
+    /// Whether the output was received by the wallet
+    pub is_received: bool,
+    /// Decoded memo
+    pub memo: MemoContent,
+}
+
+/// A single output of a transaction
+#[derive(Debug, Clone)]
+pub struct TransactionOutput {
+    /// Pool the output was created in
+    pub pool: ValuePool,
+    /// Index of the output within its pool's bundle
+    pub output_index: u32,
+    /// Recipient address, if known
+    pub address: Option<String>,
+    /// Value in zatoshis
+    pub value: u64,
+    /// Memo if available
+    pub memo: Option<MemoContent>,
+    /// Whether this output is change returned to the wallet
+    pub is_change: bool,
+    /// Whether this output was received by the wallet
+    pub is_received: bool,
+}
+
+/// Full details of a single transaction
+#[derive(Debug, Clone)]
+pub struct TransactionDetails {
+    /// Transaction ID as hex string
+    pub txid: String,
+    /// Net change to the wallet balance in zatoshis
+    pub amount: i64,
+    /// How the transaction moved funds relative to the wallet
+    pub kind: TransactionKind,
+    /// Fee paid in zatoshis (only known for transactions the wallet funded)
+    pub fee: Option<u64>,
+    /// Block height where transaction was mined (None if pending)
+    pub mined_height: Option<u32>,
+    /// Hash of the block the transaction was mined in
+    pub block_hash: Option<String>,
+    /// Unix timestamp of the block (None if pending)
+    pub block_time: Option<u64>,
+    /// Height after which the transaction expires if unmined
+    pub expiry_height: Option<u32>,
+    /// Size of the raw transaction in bytes
+    pub raw_size: Option<usize>,
+    /// Every sent and received output the wallet knows about
+    pub outputs: Vec<TransactionOutput>,
+}
+
+/// Format raw txid bytes from the database in display (reversed hex) order
+pub(crate) fn txid_to_hex(txid_bytes: &[u8]) -> String {
+    let mut txid_arr = [0u8; 32];
+    if txid_bytes.len() == 32 {
+        txid_arr.copy_from_slice(txid_bytes);
+        txid_arr.reverse();
+    }
+    txid_arr.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Parse a display-order txid into the byte order stored in the database
+pub(crate) fn txid_from_hex(txid: &str) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = hex::decode(txid.trim()).context("Invalid txid")?;
+    if bytes.len() != 32 {
+        anyhow::bail!(
+            "Invalid txid length: expected 32 bytes, got {}",
+            bytes.len()
+        );
+    }
+    bytes.reverse();
+    Ok(bytes)
+}
+
+/// Encode the position of a row in the history ordering
+fn encode_cursor(sort_height: i64, id_tx: i64) -> String {
+    format!("{sort_height}:{id_tx}")
+}
+
+/// Decode a cursor produced by `encode_cursor`
+fn decode_cursor(cursor: &str) -> anyhow::Result<(i64, i64)> {
+    let (height, id_tx) = cursor
+        .split_once(':')
+        .ok_or_else(|| anyhow::anyhow!("Invalid cursor: {cursor}"))?;
+    Ok((
+        height.parse().context("Invalid cursor height")?,
+        id_tx.parse().context("Invalid cursor position")?,
+    ))
+}
+
+/// Convert a display-order txid fragment to the uppercase hex of stored txid bytes
+///
+/// Only whole bytes can be matched, so odd-length or non-hex input returns None.
+fn txid_search_hex(search: &str) -> Option<String> {
+    if search.is_empty() || search.len() % 2 != 0 {
+        return None;
+    }
+    let mut bytes = hex::decode(search).ok()?;
+    bytes.reverse();
+    Some(hex::encode_upper(bytes))
+}
+
+/// Run a transaction history query against `v_transactions`
+///
+/// Results are ordered pending first, then by mined height descending, with
+/// the internal transaction id as a tie-breaker so cursors are stable.
+pub fn query_transactions(
+    conn: &Connection,
+    query: &TransactionQuery,
+) -> anyhow::Result<TransactionPage> {
+    let limit = if query.limit == 0 {
+        DEFAULT_PAGE_SIZE
+    } else {
+        query.limit
+    };
+
+    let mut conditions = vec!["expired_unmined = 0".to_string()];
+    let mut params: Vec<Value> = Vec::new();
+
+    match query.direction {
+        Some(TransactionDirection::Sent) => {
+            conditions.push("tx_kind IN ('sent', 'mixed')".to_string());
+        }
+        Some(TransactionDirection::Received) => {
+            conditions.push("tx_kind = 'received'".to_string());
+        }
+        None => {}
+    }
+
+    if let Some(pool) = query.pool {
+        conditions.push(
+            "EXISTS (SELECT 1 FROM v_tx_outputs o WHERE o.txid = tx.txid AND o.output_pool = ?)"
+                .to_string(),
+        );
+        params.push(Value::Integer(pool.code()));
+    }
+
+    if let Some(from_time) = query.from_time {
+        conditions.push(format!("{PENDING_TIME_SQL} >= ?"));
+        params.push(Value::Integer(from_time as i64));
+    }
+
+    if let Some(to_time) = query.to_time {
+        conditions.push(format!("{PENDING_TIME_SQL} <= ?"));
+        params.push(Value::Integer(to_time as i64));
+    }
+
+    if let Some(min_amount) = query.min_amount {
+        conditions.push("ABS(account_balance_delta) >= ?".to_string());
+        params.push(Value::Integer(min_amount as i64));
+    }
+
+    if let Some(max_amount) = query.max_amount {
+        conditions.push("ABS(account_balance_delta) <= ?".to_string());
+        params.push(Value::Integer(max_amount as i64));
+    }
+
+    if let Some(search) = query
+        .search
+        .as_deref()
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+    {
+        register_memo_text_fn(conn)?;
+        let memo_match = format!(
+            "EXISTS (SELECT 1 FROM v_tx_outputs o \
+            WHERE o.txid = tx.txid AND {MEMO_TEXT_FN}(o.memo) LIKE ? ESCAPE '\\')"
+        );
+        params.push(Value::Text(format!("%{}%", escape_like(search))));
+
+        match txid_search_hex(search) {
+            Some(txid_hex) => {
+                conditions.push(format!("({memo_match} OR instr(hex(txid), ?) > 0)"));
+                params.push(Value::Text(txid_hex));
+            }
+            None => conditions.push(memo_match),
+        }
+    }
+
+    match query.is_pending {
+        Some(true) => conditions.push("mined_height IS NULL".to_string()),
+        Some(false) => conditions.push("mined_height IS NOT NULL".to_string()),
+        None => {}
+    }
+
+    if let Some(cursor) = &query.cursor {
+        let (sort_height, id_tx) = decode_cursor(cursor)?;
+        conditions.push("(COALESCE(mined_height, ?), id_tx) < (?, ?)".to_string());
+        params.push(Value::Integer(PENDING_SORT_HEIGHT));
+        params.push(Value::Integer(sort_height));
+        params.push(Value::Integer(id_tx));
+    }
+
+    // Fetch one extra row to know whether another page follows
+    params.push(Value::Integer(limit as i64 + 1));
+
+    let sql = format!(
+        "WITH tx AS (
+            SELECT
+                v_transactions.txid,
+                v_transactions.mined_height,
+                v_transactions.account_balance_delta,
+                v_transactions.block_time,
+                v_transactions.expired_unmined,
+                v_transactions.id_tx,
+                {TX_KIND_SQL} AS tx_kind,
+                (
+                    SELECT o.to_address FROM v_tx_outputs o
+                    WHERE o.txid = v_transactions.txid
+                        AND o.to_account_uuid IS NULL
+                        AND o.to_address IS NOT NULL
+                    ORDER BY o.output_pool, o.output_index
+                    LIMIT 1
+                ) AS counterparty
+            FROM v_transactions
+            {TX_KIND_JOINS}
+        )
+        SELECT
+            txid,
+            mined_height,
+            account_balance_delta,
+            block_time,
+            tx_kind,
+            id_tx,
+            counterparty
+        FROM tx
+        WHERE {}
+        ORDER BY COALESCE(mined_height, {PENDING_SORT_HEIGHT}) DESC, id_tx DESC
+        LIMIT ?",
+        conditions.join(" AND ")
+    );
+
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(rusqlite::params_from_iter(params.iter()), |row| {
+        let txid_bytes: Vec<u8> = row.get(0)?;
+        let mined_height: Option<u32> = row.get(1)?;
+        let balance_delta: i64 = row.get(2)?;
+        let block_time: Option<u32> = row.get(3)?;
+        let tx_kind: String = row.get(4)?;
+        let id_tx: i64 = row.get(5)?;
+        let counterparty: Option<String> = row.get(6)?;
+        Ok((
+            txid_bytes,
+            mined_height,
+            balance_delta,
+            block_time,
+            tx_kind,
+            id_tx,
+            counterparty,
+        ))
+    })?;
+
+    let mut transactions = Vec::new();
+    let mut last_position = None;
+    let mut has_more = false;
+    for (txid_bytes, mined_height, balance_delta, block_time, tx_kind, id_tx, counterparty) in
+        rows.flatten()
+    {
+        if transactions.len() == limit {
+            has_more = true;
+            break;
+        }
+
+        let txid = txid_to_hex(&txid_bytes);
+
+        // Fetch memo for this transaction
+        let memo = get_transaction_memo(conn, &txid_bytes);
+
+        // Use current time for pending transactions, block time for mined
+        let timestamp = block_time.map(|t| t as u64).unwrap_or_else(|| {
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0)
+        });
+
+        let kind = TransactionKind::from_sql(&tx_kind);
+
+        // Determine if pending (unmined)
+        let is_pending = mined_height.is_none();
+
+        let sort_height = mined_height.map(i64::from).unwrap_or(PENDING_SORT_HEIGHT);
+        last_position = Some((sort_height, id_tx));
+
+        transactions.push(TransactionRecord {
+            txid,
+            amount: balance_delta,
+            timestamp,
+            kind,
+            memo,
+            is_pending,
+            mined_height,
+            address: counterparty,
+            contact_name: None,
+        });
+    }
+
+    let next_cursor = if has_more {
+        last_position.map(|(sort_height, id_tx)| encode_cursor(sort_height, id_tx))
+    } else {
+        None
+    };
+
+    Ok(TransactionPage {
+        transactions,
+        next_cursor,
+    })
+}
+
+/// Load the full details of a transaction, including every output
+pub fn get_transaction_details(
+    conn: &Connection,
+    txid: &str,
+) -> anyhow::Result<Option<TransactionDetails>> {
+    let txid_bytes = txid_from_hex(txid)?;
+
+    let summary = conn.query_row(
+        &format!(
+            "SELECT
+                v_transactions.mined_height,
+                v_transactions.account_balance_delta,
+                v_transactions.fee_paid,
+                v_transactions.block_time,
+                v_transactions.expiry_height,
+                length(v_transactions.raw),
+                {TX_KIND_SQL}
+            FROM v_transactions
+            {TX_KIND_JOINS}
+            WHERE v_transactions.txid = ?"
+        ),
+        [&txid_bytes],
+        |row| {
+            Ok((
+                row.get::<_, Option<u32>>(0)?,
+                row.get::<_, i64>(1)?,
+                row.get::<_, Option<i64>>(2)?,
+                row.get::<_, Option<i64>>(3)?,
+                row.get::<_, Option<u32>>(4)?,
+                row.get::<_, Option<i64>>(5)?,
+                row.get::<_, String>(6)?,
+            ))
+        },
+    );
+
+    let (mined_height, amount, fee, block_time, expiry_height, raw_size, tx_kind) = match summary {
+        Ok(row) => row,
+        Err(rusqlite::Error::QueryReturnedNoRows) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+
+    // Blocks are only stored for scanned ranges, so the hash may be unknown
+    let block_hash = match mined_height {
+        Some(height) => conn
+            .query_row(
+                "SELECT hash FROM blocks WHERE height = ?",
+                [height],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .ok()
+            .map(|mut hash| {
+                hash.reverse();
+                hex::encode(hash)
+            }),
+        None => None,
+    };
+
+    let mut stmt = conn.prepare(
+        "SELECT
+            output_pool,
+            output_index,
+            to_address,
+            value,
+            memo,
+            is_change,
+            to_account_uuid IS NOT NULL
+        FROM v_tx_outputs
+        WHERE txid = ?
+        ORDER BY output_pool, output_index",
+    )?;
+
+    let rows = stmt.query_map([&txid_bytes], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, u32>(1)?,
+            row.get::<_, Option<String>>(2)?,
+            row.get::<_, i64>(3)?,
+            row.get::<_, Option<Vec<u8>>>(4)?,
+            row.get::<_, bool>(5)?,
+            row.get::<_, bool>(6)?,
+        ))
+    })?;
+
+    let mut outputs = Vec::new();
+    for (pool_code, output_index, address, value, memo, is_change, is_received) in rows.flatten() {
+        let Some(pool) = ValuePool::from_code(pool_code) else {
+            continue;
+        };
+        outputs.push(TransactionOutput {
+            pool,
+            output_index,
+            address,
+            value: value.max(0) as u64,
+            memo: memo.as_deref().and_then(decode_memo),
+            is_change,
+            is_received,
+        });
+    }
+
+    Ok(Some(TransactionDetails {
+        txid: txid_to_hex(&txid_bytes),
+        amount,
+        kind: TransactionKind::from_sql(&tx_kind),
+        fee: fee.map(|f| f.max(0) as u64),
+        mined_height,
+        block_hash,
+        block_time: block_time.map(|t| t.max(0) as u64),
+        expiry_height,
+        raw_size: raw_size.map(|s| s.max(0) as usize),
+        outputs,
+    }))
+}
+
+/// Get every memo attached to a transaction's outputs, in output order
+pub fn get_transaction_memos(
+    conn: &Connection,
+    txid_bytes: &[u8],
+) -> anyhow::Result<Vec<TransactionMemo>> {
+    let mut stmt = conn.prepare(
+        "SELECT
+            output_pool,
+            output_index,
+            memo,
+            to_account_uuid IS NOT NULL
+        FROM v_tx_outputs
+        WHERE txid = ? AND memo IS NOT NULL
+        ORDER BY output_pool, output_index",
+    )?;
+
+    let rows = stmt.query_map([txid_bytes], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, u32>(1)?,
+            row.get::<_, Vec<u8>>(2)?,
+            row.get::<_, bool>(3)?,
+        ))
+    })?;
+
+    let mut memos = Vec::new();
+    for (pool_code, output_index, memo_bytes, is_received) in rows.flatten() {
+        let (Some(pool), Some(memo)) = (ValuePool::from_code(pool_code), decode_memo(&memo_bytes))
+        else {
+            continue;
+        };
+        memos.push(TransactionMemo {
+            pool,
+            output_index,
+            is_received,
+            memo,
+        });
+    }
+
+    Ok(memos)
+}
+
+/// Register the SQL function that decodes memos for text search
+fn register_memo_text_fn(conn: &Connection) -> anyhow::Result<()> {
+    conn.create_scalar_function(
+        MEMO_TEXT_FN,
+        1,
+        FunctionFlags::SQLITE_UTF8 | FunctionFlags::SQLITE_DETERMINISTIC,
+        |ctx| {
+            let memo: Option<Vec<u8>> = ctx.get(0)?;
+            Ok(memo
+                .as_deref()
+                .and_then(decode_memo)
+                .and_then(|memo| match memo {
+                    MemoContent::Text(text) => Some(text),
+                    _ => None,
+                }))
+        },
+    )?;
+    Ok(())
+}
+
+/// Escape the LIKE wildcards in user input, for use with `ESCAPE '\'`
+fn escape_like(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '%' | '_') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Get the first text memo of a transaction for display in lists
+fn get_transaction_memo(conn: &Connection, txid_bytes: &[u8]) -> Option<String> {
+    get_transaction_memos(conn, txid_bytes)
+        .ok()?
+        .into_iter()
+        .find_map(|m| match m.memo {
+            MemoContent::Text(text) => Some(text),
+            _ => None,
+        })
+}
+
+/// Decode stored memo bytes according to ZIP 302
+///
+/// Text memos that are not valid UTF-8 are returned as arbitrary data so the
+/// raw bytes are still available to the caller. Only text memos lose their
+/// zero padding: binary memos keep all 511 bytes after the 0xFF marker, and
+/// future-format and undecodable memos all 512 bytes, since trailing zeros
+/// can be part of their payload.
+pub(crate) fn decode_memo(memo_bytes: &[u8]) -> Option<MemoContent> {
+    if memo_bytes.is_empty() {
+        return None;
+    }
+
+    let memo_bytes = MemoBytes::from_bytes(memo_bytes).ok()?;
+    let content = match Memo::try_from(memo_bytes.clone()) {
+        Ok(Memo::Empty) => MemoContent::Empty,
+        Ok(Memo::Text(text)) => MemoContent::Text(text.to_string()),
+        Ok(Memo::Arbitrary(data)) => MemoContent::Arbitrary(data.to_vec()),
+        Ok(Memo::Future(bytes)) => MemoContent::Future(bytes.as_slice().to_vec()),
+        Err(_) => MemoContent::Arbitrary(memo_bytes.as_slice().to_vec()),
+    };
+
+    Some(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    /// In-memory stand-ins for the wallet database views the queries read
+    fn history_db() -> Connection {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(
+            "CREATE TABLE v_transactions (
+                id_tx INTEGER PRIMARY KEY,
+                txid BLOB NOT NULL,
+                mined_height INTEGER,
+                account_balance_delta INTEGER NOT NULL,
+                block_time INTEGER,
+                expired_unmined INTEGER NOT NULL DEFAULT 0,
+                is_shielding INTEGER NOT NULL DEFAULT 0,
+                total_spent INTEGER NOT NULL DEFAULT 0,
+                fee_paid INTEGER,
+                expiry_height INTEGER,
+                raw BLOB
+            );
+            CREATE TABLE v_tx_outputs (
+                txid BLOB NOT NULL,
+                output_pool INTEGER NOT NULL,
+                output_index INTEGER NOT NULL,
+                to_account_uuid BLOB,
+                to_address TEXT,
+                value INTEGER NOT NULL,
+                is_change INTEGER NOT NULL DEFAULT 0,
+                memo BLOB
+            );",
+        )
+        .unwrap();
+        conn
+    }
+
+    /// Add a received Orchard transaction with a text memo
+    fn add_received(
+        conn: &Connection,
+        id: u8,
+        mined_height: Option<u32>,
+        block_time: Option<i64>,
+        memo: &str,
+    ) {
+        let txid = vec![id; 32];
+        conn.execute(
+            "INSERT INTO v_transactions (txid, mined_height, account_balance_delta, block_time)
+            VALUES (?, ?, 10000, ?)",
+            rusqlite::params![txid, mined_height, block_time],
+        )
+        .unwrap();
+        let memo = Memo::from_str(memo).unwrap().encode();
+        conn.execute(
+            "INSERT INTO v_tx_outputs
+                (txid, output_pool, output_index, to_account_uuid, value, memo)
+            VALUES (?, 3, 0, x'01', 10000, ?)",
+            rusqlite::params![txid, memo.as_slice()],
+        )
+        .unwrap();
+    }
+
+    fn query(conn: &Connection, query: TransactionQuery) -> Vec<TransactionRecord> {
+        query_transactions(conn, &query).unwrap().transactions
+    }
+
+    #[test]
+    fn pending_transactions_use_current_time_in_time_filters() {
+        let conn = history_db();
+        add_received(&conn, 1, Some(100), Some(1_000), "mined");
+        add_received(&conn, 2, None, None, "pending");
+
+        let after = query(
+            &conn,
+            TransactionQuery {
+                from_time: Some(2_000),
+                ..Default::default()
+            },
+        );
+        assert_eq!(after.len(), 1);
+        assert!(after[0].is_pending);
+
+        let before = query(
+            &conn,
+            TransactionQuery {
+                to_time: Some(1_500),
+                ..Default::default()
+            },
+        );
+        assert_eq!(before.len(), 1);
+        assert_eq!(before[0].mined_height, Some(100));
+    }
+
+    #[test]
+    fn search_matches_decoded_memo_text() {
+        let conn = history_db();
+        add_received(&conn, 1, Some(100), Some(1_000), "Rent for March");
+        add_received(&conn, 2, Some(101), Some(1_100), "coffee");
+
+        let found = query(
+            &conn,
+            TransactionQuery {
+                search: Some("rent".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].memo.as_deref(), Some("Rent for March"));
+    }
+
+    #[test]
+    fn search_treats_like_wildcards_literally() {
+        let conn = history_db();
+        add_received(&conn, 1, Some(100), Some(1_000), "paid 100% upfront");
+        add_received(&conn, 2, Some(101), Some(1_100), "paid_in_full");
+        add_received(&conn, 3, Some(102), Some(1_200), "nothing special");
+
+        let percent = query(
+            &conn,
+            TransactionQuery {
+                search: Some("%".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(percent.len(), 1);
+        assert_eq!(percent[0].memo.as_deref(), Some("paid 100% upfront"));
+
+        let underscore = query(
+            &conn,
+            TransactionQuery {
+                search: Some("d_i".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(underscore.len(), 1);
+        assert_eq!(underscore[0].memo.as_deref(), Some("paid_in_full"));
+    }
+
+    #[test]
+    fn binary_memos_keep_their_trailing_zeros() {
+        let padded = |prefix: &[u8]| {
+            let mut bytes = [0u8; 512];
+            bytes[..prefix.len()].copy_from_slice(prefix);
+            bytes
+        };
+
+        let text = padded(b"hello");
+        assert_eq!(
+            decode_memo(&text),
+            Some(MemoContent::Text("hello".to_string()))
+        );
+
+        let arbitrary = padded(&[0xFF, 1, 2, 0, 3]);
+        let Some(MemoContent::Arbitrary(data)) = decode_memo(&arbitrary) else {
+            panic!("expected an arbitrary memo");
+        };
+        assert_eq!(data.len(), 511);
+        assert_eq!(&data[..4], &[1, 2, 0, 3]);
+
+        let future = padded(&[0xF7, 9]);
+        assert_eq!(
+            decode_memo(&future),
+            Some(MemoContent::Future(future.to_vec()))
+        );
+
+        let invalid_text = padded(&[0xC3, 0x28]);
+        assert_eq!(
+            decode_memo(&invalid_text),
+            Some(MemoContent::Arbitrary(invalid_text.to_vec()))
+        );
+    }
+}
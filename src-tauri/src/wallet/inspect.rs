@@ -0,0 +1,279 @@
+//! Address validation and inspection
+//!
+//! Decodes any Zcash address encoding without converting it to a spendable
+//! recipient, so the UI can explain what an address is before sending to it.
+
+use std::collections::HashSet;
+
+use zcash_address::unified::{self, Container, Encoding};
+use zcash_address::{ConversionError, TryFromAddress, ZcashAddress};
+use zcash_protocol::consensus::NetworkType;
+
+use super::Network;
+
+/// Encoding kind of an address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressEncoding {
+    Unified,
+    Sapling,
+    TransparentP2pkh,
+    TransparentP2sh,
+    Tex,
+    Sprout,
+}
+
+/// A receiver contained in an address
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReceiverKind {
+    Orchard,
+    Sapling,
+    TransparentP2pkh,
+    TransparentP2sh,
+    /// Receiver with a typecode this wallet does not know
+    Unknown(u32),
+}
+
+/// Result of inspecting an address
+#[derive(Debug, Clone)]
+pub struct AddressInspection {
+    /// Whether the address parsed as a Zcash address
+    pub is_valid: bool,
+    /// Network the address is encoded for
+    pub network: Option<NetworkType>,
+    /// Encoding kind
+    pub encoding: Option<AddressEncoding>,
+    /// Receivers contained in the address (a single one for non-unified addresses)
+    pub receivers: Vec<ReceiverKind>,
+    /// Whether any receiver belongs to this wallet
+    pub is_mine: bool,
+    /// Issues the user should be warned about before sending
+    pub warnings: Vec<String>,
+    /// Parse error for invalid addresses
+    pub error: Option<String>,
+}
+
+/// An address decoded into its network, encoding and raw receivers
+struct DecodedAddress {
+    network: NetworkType,
+    encoding: AddressEncoding,
+    receivers: Vec<unified::Receiver>,
+}
+
+impl DecodedAddress {
+    fn new(
+        network: NetworkType,
+        encoding: AddressEncoding,
+        receivers: Vec<unified::Receiver>,
+    ) -> Result<Self, ConversionError<std::convert::Infallible>> {
+        Ok(Self {
+            network,
+            encoding,
+            receivers,
+        })
+    }
+}
+
+impl TryFromAddress for DecodedAddress {
+    type Error = std::convert::Infallible;
+
+    fn try_from_sprout(
+        net: NetworkType,
+        _data: [u8; 64],
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        Self::new(net, AddressEncoding::Sprout, vec![])
+    }
+
+    fn try_from_sapling(
+        net: NetworkType,
+        data: [u8; 43],
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        Self::new(
+            net,
+            AddressEncoding::Sapling,
+            vec![unified::Receiver::Sapling(data)],
+        )
+    }
+
+    fn try_from_unified(
+        net: NetworkType,
+        data: unified::Address,
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        Self::new(net, AddressEncoding::Unified, data.items())
+    }
+
+    fn try_from_transparent_p2pkh(
+        net: NetworkType,
+        data: [u8; 20],
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        Self::new(
+            net,
+            AddressEncoding::TransparentP2pkh,
+            vec![unified::Receiver::P2pkh(data)],
+        )
+    }
+
+    fn try_from_transparent_p2sh(
+        net: NetworkType,
+        data: [u8; 20],
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        Self::new(
+            net,
+            AddressEncoding::TransparentP2sh,
+            vec![unified::Receiver::P2sh(data)],
+        )
+    }
+
+    fn try_from_tex(
+        net: NetworkType,
+        data: [u8; 20],
+    ) -> Result<Self, ConversionError<Self::Error>> {
+        Self::new(
+            net,
+            AddressEncoding::Tex,
+            vec![unified::Receiver::P2pkh(data)],
+        )
+    }
+}
+
+/// Decode an address into its network, encoding and receivers
+fn decode(address: &str) -> Result<DecodedAddress, String> {
+    let parsed: ZcashAddress = address.trim().parse().map_err(|e| format!("{e}"))?;
+    parsed
+        .convert::<DecodedAddress>()
+        .map_err(|e| format!("{e:?}"))
+}
+
+/// Whether an address is a ZIP 320 TEX (transparent-source-only) address
+pub fn is_tex_address(address: &str) -> bool {
+    matches!(decode(address), Ok(decoded) if decoded.encoding == AddressEncoding::Tex)
+}
+
+/// Receiver kind of a raw receiver
+fn receiver_kind(receiver: &unified::Receiver) -> ReceiverKind {
+    match receiver {
+        unified::Receiver::Orchard(_) => ReceiverKind::Orchard,
+        unified::Receiver::Sapling(_) => ReceiverKind::Sapling,
+        unified::Receiver::P2pkh(_) => ReceiverKind::TransparentP2pkh,
+        unified::Receiver::P2sh(_) => ReceiverKind::TransparentP2sh,
+        unified::Receiver::Unknown { typecode, .. } => ReceiverKind::Unknown(*typecode),
+    }
+}
+
+/// Human-readable network name
+fn network_name(network: NetworkType) -> &'static str {
+    match network {
+        NetworkType::Main => "mainnet",
+        NetworkType::Test => "testnet",
+        NetworkType::Regtest => "regtest",
+    }
+}
+
+/// Encode a single receiver as a standalone address, for comparing receivers
+fn encode_receiver(network: NetworkType, receiver: &unified::Receiver) -> Option<String> {
+    let address = match receiver {
+        unified::Receiver::Orchard(data) => {
+            let ua =
+                unified::Address::try_from_items(vec![unified::Receiver::Orchard(*data)]).ok()?;
+            ZcashAddress::from_unified(network, ua)
+        }
+        unified::Receiver::Sapling(data) => ZcashAddress::from_sapling(network, *data),
+        unified::Receiver::P2pkh(data) => ZcashAddress::from_transparent_p2pkh(network, *data),
+        unified::Receiver::P2sh(data) => ZcashAddress::from_transparent_p2sh(network, *data),
+        unified::Receiver::Unknown { .. } => return None,
+    };
+    Some(address.to_string())
+}
+
+/// Standalone encodings of every receiver of the given addresses
+pub fn receiver_encodings<'a>(
+    addresses: impl IntoIterator<Item = &'a str>,
+) -> HashSet<String> {
+    addresses
+        .into_iter()
+        .filter_map(|address| decode(address).ok())
+        .flat_map(|decoded| {
+            decoded
+                .receivers
+                .iter()
+                .filter_map(|r| encode_receiver(decoded.network, r))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Inspect an address against the wallet's network and its own receivers
+///
+/// `own_receivers` holds the standalone receiver encodings of the wallet's
+/// addresses (see `receiver_encodings`).
+pub fn inspect_address(
+    address: &str,
+    wallet_network: Network,
+    own_receivers: &HashSet<String>,
+) -> AddressInspection {
+    let decoded = match decode(address) {
+        Ok(decoded) => decoded,
+        Err(error) => {
+            return AddressInspection {
+                is_valid: false,
+                network: None,
+                encoding: None,
+                receivers: vec![],
+                is_mine: false,
+                warnings: vec![],
+                error: Some(error),
+            }
+        }
+    };
+
+    let receivers: Vec<ReceiverKind> = decoded.receivers.iter().map(receiver_kind).collect();
+    let is_mine = decoded
+        .receivers
+        .iter()
+        .filter_map(|r| encode_receiver(decoded.network, r))
+        .any(|encoded| own_receivers.contains(&encoded));
+
+    let mut warnings = Vec::new();
+    if decoded.network != wallet_network.network_type() {
+        warnings.push(format!(
+            "Address is for {}, but this wallet uses {}",
+            network_name(decoded.network),
+            network_name(wallet_network.network_type())
+        ));
+    }
+
+    let has_shielded = receivers
+        .iter()
+        .any(|r| matches!(r, ReceiverKind::Orchard | ReceiverKind::Sapling));
+    match decoded.encoding {
+        AddressEncoding::Sprout => {
+            warnings.push("Sprout addresses are no longer supported".to_string());
+        }
+        AddressEncoding::Tex => {
+            warnings.push(
+                "TEX address: funds must be sent from transparent inputs, which makes the payment public"
+                    .to_string(),
+            );
+        }
+        _ if !has_shielded => {
+            warnings.push(
+                "Transparent-only recipient: the amount and recipient will be publicly visible"
+                    .to_string(),
+            );
+        }
+        _ => {}
+    }
+
+    if is_mine {
+        warnings.push("This address belongs to your wallet".to_string());
+    }
+
+    AddressInspection {
+        is_valid: true,
+        network: Some(decoded.network),
+        encoding: Some(decoded.encoding),
+        receivers,
+        is_mine,
+        warnings,
+        error: None,
+    }
+}
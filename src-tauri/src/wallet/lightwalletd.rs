@@ -0,0 +1,106 @@
+//! lightwalletd network layer
+//!
+//! `IkkiWallet` talks to the chain through a `GrpcClient`, which
+//! `zcash_client_backend` sync drives directly. Offline runs swap the
+//! transport rather than the client: the `test-fixtures` feature's
+//! `MockLightwalletd` serves the same gRPC service in-process.
+
+use std::future::Future;
+
+use tonic::transport::{Channel, ClientTlsConfig, Endpoint};
+use tracing::warn;
+use zcash_client_backend::proto::service::{
+    compact_tx_streamer_client::CompactTxStreamerClient, BlockId, ChainSpec, RawTransaction,
+    TreeState,
+};
+
+use crate::wallet::IkkiError;
+
+/// gRPC client for a lightwalletd server
+pub type GrpcClient = CompactTxStreamerClient<Channel>;
+
+/// lightwalletd calls the wallet makes outside of sync, with server errors
+/// mapped to `IkkiError`
+pub trait Lightwalletd {
+    /// Height of the chain tip
+    fn latest_height(&mut self) -> impl Future<Output = anyhow::Result<u64>> + Send;
+
+    /// Note commitment tree state as of the end of the block at `height`
+    fn tree_state(&mut self, height: u64)
+        -> impl Future<Output = anyhow::Result<TreeState>> + Send;
+
+    /// Broadcast a serialized transaction
+    ///
+    /// A rejection by the server is returned as `IkkiError::BroadcastRejected`.
+    fn broadcast_transaction(
+        &mut self,
+        data: Vec<u8>,
+    ) -> impl Future<Output = anyhow::Result<()>> + Send;
+}
+
+impl Lightwalletd for GrpcClient {
+    async fn latest_height(&mut self) -> anyhow::Result<u64> {
+        let block = self
+            .get_latest_block(ChainSpec::default())
+            .await
+            .map_err(|e| {
+                IkkiError::Network(format!("Failed to get latest block: {}", e.message()))
+            })?
+            .into_inner();
+        Ok(block.height)
+    }
+
+    async fn tree_state(&mut self, height: u64) -> anyhow::Result<TreeState> {
+        let request = BlockId {
+            height,
+            ..Default::default()
+        };
+        let tree_state = self
+            .get_tree_state(request)
+            .await
+            .map_err(|e| {
+                IkkiError::Network(format!(
+                    "Failed to get tree state at height {height}: {}",
+                    e.message()
+                ))
+            })?
+            .into_inner();
+        Ok(tree_state)
+    }
+
+    async fn broadcast_transaction(&mut self, data: Vec<u8>) -> anyhow::Result<()> {
+        let raw_tx = RawTransaction { data, height: 0 };
+        let response = CompactTxStreamerClient::send_transaction(self, raw_tx)
+            .await
+            .map_err(|e| {
+                IkkiError::Network(format!("Failed to broadcast transaction: {}", e.message()))
+            })?
+            .into_inner();
+
+        if response.error_code != 0 {
+            warn!(
+                "Transaction rejected (code {}): {}",
+                response.error_code, response.error_message
+            );
+            return Err(IkkiError::BroadcastRejected {
+                code: response.error_code,
+                message: response.error_message,
+            }
+            .into());
+        }
+        Ok(())
+    }
+}
+
+/// Connect to a lightwalletd server, using TLS for `https` URLs
+pub async fn connect_lightwalletd(url: &str) -> anyhow::Result<GrpcClient> {
+    let mut endpoint = Endpoint::from_shared(url.to_string())?;
+    if url.starts_with("https://") {
+        endpoint = endpoint.tls_config(ClientTlsConfig::new().with_native_roots())?;
+    }
+    let channel = endpoint
+        .connect()
+        .await
+        .map_err(|e| IkkiError::Network(format!("Failed to connect to {url}: {e}")))?;
+    Ok(CompactTxStreamerClient::new(channel))
+}
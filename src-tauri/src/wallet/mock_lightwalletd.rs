@@ -0,0 +1,1008 @@
+//! In-process lightwalletd for offline use
+//!
+//! `MockLightwalletd` serves the `CompactTxStreamer` gRPC service from
+//! recorded fixtures over in-memory streams, and captures broadcast
+//! transactions instead of relaying them. Clients returned by
+//! [`MockLightwalletd::connect`] are ordinary `GrpcClient`s, so the wallet,
+//! including `zcash_client_backend` sync, runs unchanged against it.
+
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use hyper_util::rt::TokioIo;
+use prost::Message;
+use serde::{Deserialize, Serialize};
+use tokio::io::DuplexStream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::codegen::{http, Body, StdError};
+use tonic::server::{Grpc, NamedService, ServerStreamingService, UnaryService};
+use tonic::transport::{Endpoint, Server, Uri};
+use tonic::Status;
+use tonic_prost::ProstCodec;
+use tracing::{debug, warn};
+use zcash_client_backend::proto::compact_formats::CompactBlock;
+use zcash_client_backend::proto::service::{
+    compact_tx_streamer_client::CompactTxStreamerClient, BlockId, BlockRange, ChainSpec, Empty,
+    GetAddressUtxosArg, GetAddressUtxosReply, GetAddressUtxosReplyList, GetSubtreeRootsArg,
+    LightdInfo, RawTransaction, SendResponse, ShieldedProtocol, SubtreeRoot, TreeState, TxFilter,
+};
+
+use crate::wallet::history::txid_to_hex;
+use crate::wallet::GrpcClient;
+
+const SERVICE_NAME: &str = "cash.z.wallet.sdk.rpc.CompactTxStreamer";
+
+/// Buffer size of each in-memory connection
+const CONNECTION_BUFFER: usize = 1 << 20;
+
+/// Recorded chain data served by `MockLightwalletd`
+///
+/// Stored as JSON; compact blocks are hex-encoded protobuf messages.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MockFixtures {
+    /// Chain name reported by `GetLightdInfo` ("main", "test" or "regtest")
+    pub chain_name: String,
+    pub sapling_activation_height: u64,
+    /// Hex consensus branch ID reported by `GetLightdInfo`
+    pub consensus_branch_id: String,
+    /// Compact blocks, hex-encoded
+    pub blocks: Vec<String>,
+    pub tree_states: Vec<FixtureTreeState>,
+    pub transactions: Vec<FixtureTransaction>,
+    pub sapling_subtree_roots: Vec<FixtureSubtreeRoot>,
+    pub orchard_subtree_roots: Vec<FixtureSubtreeRoot>,
+    pub utxos: Vec<FixtureUtxo>,
+}
+
+/// Note commitment tree state at the end of a block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureTreeState {
+    pub height: u64,
+    pub hash: String,
+    pub time: u32,
+    pub sapling_tree: String,
+    pub orchard_tree: String,
+}
+
+/// Full transaction returned by `GetTransaction`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureTransaction {
+    /// Transaction ID in display (byte-reversed) hex
+    pub txid: String,
+    pub height: u64,
+    /// Serialized transaction, hex-encoded
+    pub data: String,
+}
+
+/// Root of a completed 2^16-leaf note commitment subtree
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureSubtreeRoot {
+    pub root_hash: String,
+    pub completing_block_hash: String,
+    pub completing_block_height: u64,
+}
+
+/// Transparent output returned by `GetAddressUtxos`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureUtxo {
+    pub address: String,
+    /// Transaction ID in display (byte-reversed) hex
+    pub txid: String,
+    pub index: i32,
+    /// Hex-encoded output script
+    pub script: String,
+    pub value_zat: i64,
+    pub height: u64,
+}
+
+/// Decode a display-order txid into the byte order used on the wire
+fn txid_from_hex(txid: &str) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = hex::decode(txid)?;
+    bytes.reverse();
+    Ok(bytes)
+}
+
+impl MockFixtures {
+    /// Load fixtures from a JSON file
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let json = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// Save fixtures to a JSON file
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        std::fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Record the blocks `from..=to` from a live server
+    ///
+    /// Fetches every block in the range with its tree state (plus the tree
+    /// state of block `from - 1`, used as a wallet birthday), the full
+    /// transactions in those blocks, and the subtree roots completed by `to`.
+    /// Intended for short ranges around the transactions a test needs.
+    pub async fn record(client: &mut GrpcClient, from: u64, to: u64) -> anyhow::Result<Self> {
+        anyhow::ensure!(from > 0 && from <= to, "Invalid block range {from}..={to}");
+
+        let info = client.get_lightd_info(Empty {}).await?.into_inner();
+        let mut fixtures = MockFixtures {
+            chain_name: info.chain_name,
+            sapling_activation_height: info.sapling_activation_height,
+            consensus_branch_id: info.consensus_branch_id,
+            ..Default::default()
+        };
+
+        let range = BlockRange {
+            start: Some(BlockId {
+                height: from,
+                ..Default::default()
+            }),
+            end: Some(BlockId {
+                height: to,
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut blocks = client.get_block_range(range).await?.into_inner();
+        while let Some(block) = blocks.message().await? {
+            for tx in &block.vtx {
+                let filter = TxFilter {
+                    hash: tx.txid().as_ref().to_vec(),
+                    ..Default::default()
+                };
+                let raw = client.get_transaction(filter).await?.into_inner();
+                fixtures.transactions.push(FixtureTransaction {
+                    txid: tx.txid().to_string(),
+                    height: block.height,
+                    data: hex::encode(raw.data),
+                });
+            }
+            fixtures.blocks.push(hex::encode(block.encode_to_vec()));
+        }
+
+        for height in (from - 1)..=to {
+            let request = BlockId {
+                height,
+                ..Default::default()
+            };
+            let state = client.get_tree_state(request).await?.into_inner();
+            fixtures.tree_states.push(FixtureTreeState {
+                height: state.height,
+                hash: state.hash,
+                time: state.time,
+                sapling_tree: state.sapling_tree,
+                orchard_tree: state.orchard_tree,
+            });
+        }
+
+        for (protocol, roots) in [
+            (ShieldedProtocol::Sapling, &mut fixtures.sapling_subtree_roots),
+            (ShieldedProtocol::Orchard, &mut fixtures.orchard_subtree_roots),
+        ] {
+            let request = GetSubtreeRootsArg {
+                start_index: 0,
+                shielded_protocol: protocol as i32,
+                max_entries: 0,
+            };
+            let mut stream = client.get_subtree_roots(request).await?.into_inner();
+            while let Some(root) = stream.message().await? {
+                if root.completing_block_height <= to {
+                    roots.push(FixtureSubtreeRoot {
+                        root_hash: hex::encode(root.root_hash),
+                        completing_block_hash: hex::encode(root.completing_block_hash),
+                        completing_block_height: root.completing_block_height,
+                    });
+                }
+            }
+        }
+
+        Ok(fixtures)
+    }
+}
+
+/// Chain state served by the mock
+#[derive(Default)]
+struct MockChain {
+    chain_name: String,
+    sapling_activation_height: u64,
+    consensus_branch_id: String,
+    blocks: BTreeMap<u64, CompactBlock>,
+    tree_states: BTreeMap<u64, TreeState>,
+    /// Raw transactions keyed by wire-order txid
+    transactions: BTreeMap<Vec<u8>, RawTransaction>,
+    sapling_subtree_roots: Vec<SubtreeRoot>,
+    orchard_subtree_roots: Vec<SubtreeRoot>,
+    utxos: Vec<GetAddressUtxosReply>,
+    /// Transactions received by `SendTransaction`, in order
+    broadcasts: Vec<Vec<u8>>,
+    /// Error code and message returned for broadcasts instead of accepting them
+    broadcast_rejection: Option<(i32, String)>,
+}
+
+impl MockChain {
+    fn from_fixtures(fixtures: MockFixtures) -> anyhow::Result<Self> {
+        let mut chain = MockChain {
+            chain_name: fixtures.chain_name,
+            sapling_activation_height: fixtures.sapling_activation_height,
+            consensus_branch_id: fixtures.consensus_branch_id,
+            ..Default::default()
+        };
+
+        for block in fixtures.blocks {
+            let block = CompactBlock::decode(hex::decode(block)?.as_slice())?;
+            chain.blocks.insert(block.height, block);
+        }
+        for state in fixtures.tree_states {
+            chain.tree_states.insert(
+                state.height,
+                TreeState {
+                    network: chain.chain_name.clone(),
+                    height: state.height,
+                    hash: state.hash,
+                    time: state.time,
+                    sapling_tree: state.sapling_tree,
+                    orchard_tree: state.orchard_tree,
+                },
+            );
+        }
+        for tx in fixtures.transactions {
+            chain.transactions.insert(
+                txid_from_hex(&tx.txid)?,
+                RawTransaction {
+                    data: hex::decode(&tx.data)?,
+                    height: tx.height,
+                },
+            );
+        }
+        for (roots, fixture_roots) in [
+            (&mut chain.sapling_subtree_roots, fixtures.sapling_subtree_roots),
+            (&mut chain.orchard_subtree_roots, fixtures.orchard_subtree_roots),
+        ] {
+            for root in fixture_roots {
+                roots.push(SubtreeRoot {
+                    root_hash: hex::decode(&root.root_hash)?,
+                    completing_block_hash: hex::decode(&root.completing_block_hash)?,
+                    completing_block_height: root.completing_block_height,
+                });
+            }
+        }
+        for utxo in fixtures.utxos {
+            chain.utxos.push(GetAddressUtxosReply {
+                address: utxo.address,
+                txid: txid_from_hex(&utxo.txid)?,
+                index: utxo.index,
+                script: hex::decode(&utxo.script)?,
+                value_zat: utxo.value_zat,
+                height: utxo.height,
+            });
+        }
+
+        Ok(chain)
+    }
+
+    fn tip_height(&self) -> u64 {
+        let block_tip = self.blocks.keys().next_back().copied().unwrap_or(0);
+        let state_tip = self.tree_states.keys().next_back().copied().unwrap_or(0);
+        block_tip.max(state_tip)
+    }
+
+    fn block_id(&self, height: u64) -> BlockId {
+        let hash = self
+            .blocks
+            .get(&height)
+            .map(|block| block.hash.clone())
+            .or_else(|| {
+                self.tree_states
+                    .get(&height)
+                    .and_then(|state| hex::decode(&state.hash).ok())
+                    .map(|mut hash| {
+                        hash.reverse();
+                        hash
+                    })
+            })
+            .unwrap_or_default();
+        BlockId { height, hash }
+    }
+
+    /// Resolve a block ID by height, or by hash when the height is zero
+    fn find_block(&self, id: &BlockId) -> Result<&CompactBlock, Status> {
+        let block = if id.height == 0 && !id.hash.is_empty() {
+            self.blocks.values().find(|block| block.hash == id.hash)
+        } else {
+            self.blocks.get(&id.height)
+        };
+        block.ok_or_else(|| Status::not_found(format!("Block {} not found", id.height)))
+    }
+
+    fn latest_block(&self, _: ChainSpec) -> Result<BlockId, Status> {
+        Ok(self.block_id(self.tip_height()))
+    }
+
+    fn block(&self, id: BlockId) -> Result<CompactBlock, Status> {
+        self.find_block(&id).cloned()
+    }
+
+    fn block_range(&self, range: BlockRange) -> Result<Vec<CompactBlock>, Status> {
+        let start = range.start.map(|id| id.height).unwrap_or(0);
+        let end = range.end.map(|id| id.height).unwrap_or(start);
+        let (low, high) = (start.min(end), start.max(end));
+        if high > self.tip_height() {
+            return Err(Status::out_of_range(format!(
+                "Block {high} is above the chain tip {}",
+                self.tip_height()
+            )));
+        }
+
+        let mut blocks: Vec<_> = self.blocks.range(low..=high).map(|(_, b)| b.clone()).collect();
+        if start > end {
+            blocks.reverse();
+        }
+        Ok(blocks)
+    }
+
+    fn transaction(&self, filter: TxFilter) -> Result<RawTransaction, Status> {
+        self.transactions
+            .get(&filter.hash)
+            .cloned()
+            .ok_or_else(|| {
+                Status::not_found(format!("Transaction {} not found", txid_to_hex(&filter.hash)))
+            })
+    }
+
+    fn send_transaction(&mut self, tx: RawTransaction) -> Result<SendResponse, Status> {
+        if let Some((error_code, error_message)) = self.broadcast_rejection.clone() {
+            return Ok(SendResponse {
+                error_code,
+                error_message,
+            });
+        }
+        debug!("Mock lightwalletd captured a {}-byte transaction", tx.data.len());
+        self.broadcasts.push(tx.data);
+        Ok(SendResponse {
+            error_code: 0,
+            error_message: String::new(),
+        })
+    }
+
+    fn tree_state(&self, id: BlockId) -> Result<TreeState, Status> {
+        let height = if id.height == 0 && !id.hash.is_empty() {
+            self.find_block(&id)?.height
+        } else {
+            id.height
+        };
+        self.tree_states
+            .get(&height)
+            .cloned()
+            .ok_or_else(|| Status::not_found(format!("Tree state at height {height} not found")))
+    }
+
+    fn latest_tree_state(&self, _: Empty) -> Result<TreeState, Status> {
+        self.tree_states
+            .values()
+            .next_back()
+            .cloned()
+            .ok_or_else(|| Status::not_found("No tree states recorded"))
+    }
+
+    fn subtree_roots(&self, arg: GetSubtreeRootsArg) -> Result<Vec<SubtreeRoot>, Status> {
+        let roots = match ShieldedProtocol::try_from(arg.shielded_protocol) {
+            Ok(ShieldedProtocol::Sapling) => &self.sapling_subtree_roots,
+            Ok(ShieldedProtocol::Orchard) => &self.orchard_subtree_roots,
+            Err(_) => return Err(Status::invalid_argument("Unknown shielded protocol")),
+        };
+        let max_entries = match arg.max_entries {
+            0 => usize::MAX,
+            n => n as usize,
+        };
+        Ok(roots
+            .iter()
+            .skip(arg.start_index as usize)
+            .take(max_entries)
+            .cloned()
+            .collect())
+    }
+
+    fn address_utxos(&self, arg: GetAddressUtxosArg) -> Result<Vec<GetAddressUtxosReply>, Status> {
+        let max_entries = match arg.max_entries {
+            0 => usize::MAX,
+            n => n as usize,
+        };
+        Ok(self
+            .utxos
+            .iter()
+            .filter(|utxo| arg.addresses.contains(&utxo.address))
+            .filter(|utxo| utxo.height >= arg.start_height && utxo.height <= self.tip_height())
+            .take(max_entries)
+            .cloned()
+            .collect())
+    }
+
+    fn address_utxos_list(
+        &self,
+        arg: GetAddressUtxosArg,
+    ) -> Result<GetAddressUtxosReplyList, Status> {
+        Ok(GetAddressUtxosReplyList {
+            address_utxos: self.address_utxos(arg)?,
+        })
+    }
+
+    fn lightd_info(&self, _: Empty) -> Result<LightdInfo, Status> {
+        let tip = self.tip_height();
+        Ok(LightdInfo {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            vendor: "Ikki mock lightwalletd".to_string(),
+            taddr_support: true,
+            chain_name: self.chain_name.clone(),
+            sapling_activation_height: self.sapling_activation_height,
+            consensus_branch_id: self.consensus_branch_id.clone(),
+            block_height: tip,
+            estimated_height: tip,
+            ..Default::default()
+        })
+    }
+}
+
+type SharedChain = Arc<Mutex<MockChain>>;
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = Result<T, Infallible>> + Send>>;
+
+/// Lock the chain, recovering from a panicked handler
+fn lock(chain: &SharedChain) -> std::sync::MutexGuard<'_, MockChain> {
+    chain.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+/// Unary gRPC method backed by a synchronous handler
+struct UnaryFn<F>(F);
+
+impl<Req, Resp, F> UnaryService<Req> for UnaryFn<F>
+where
+    F: FnMut(Req) -> Result<Resp, Status>,
+{
+    type Response = Resp;
+    type Future = std::future::Ready<Result<tonic::Response<Resp>, Status>>;
+
+    fn call(&mut self, request: tonic::Request<Req>) -> Self::Future {
+        std::future::ready((self.0)(request.into_inner()).map(tonic::Response::new))
+    }
+}
+
+/// Server-streaming gRPC method backed by a handler returning every message
+struct StreamFn<F>(F);
+
+impl<Req, Resp, F> ServerStreamingService<Req> for StreamFn<F>
+where
+    F: FnMut(Req) -> Result<Vec<Resp>, Status>,
+    Resp: Send + 'static,
+{
+    type Response = Resp;
+    type ResponseStream = tokio_stream::Iter<std::vec::IntoIter<Result<Resp, Status>>>;
+    type Future = std::future::Ready<Result<tonic::Response<Self::ResponseStream>, Status>>;
+
+    fn call(&mut self, request: tonic::Request<Req>) -> Self::Future {
+        let response = (self.0)(request.into_inner()).map(|messages| {
+            let messages: Vec<_> = messages.into_iter().map(Ok).collect();
+            tonic::Response::new(tokio_stream::iter(messages))
+        });
+        std::future::ready(response)
+    }
+}
+
+/// `CompactTxStreamer` service over a `MockChain`
+#[derive(Clone)]
+struct MockService {
+    chain: SharedChain,
+}
+
+impl NamedService for MockService {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+impl<B> tower::Service<http::Request<B>> for MockService
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, req: http::Request<B>) -> Self::Future {
+        let chain = self.chain.clone();
+        Box::pin(async move {
+            let method = req
+                .uri()
+                .path()
+                .strip_prefix(format!("/{SERVICE_NAME}/").as_str())
+                .unwrap_or_default()
+                .to_string();
+            let response = match method.as_str() {
+                "GetLatestBlock" => unary(req, |r| lock(&chain).latest_block(r)).await,
+                "GetBlock" => unary(req, |r| lock(&chain).block(r)).await,
+                "GetBlockRange" => streaming(req, |r| lock(&chain).block_range(r)).await,
+                "GetTransaction" => unary(req, |r| lock(&chain).transaction(r)).await,
+                "SendTransaction" => unary(req, |r| lock(&chain).send_transaction(r)).await,
+                "GetTreeState" => unary(req, |r| lock(&chain).tree_state(r)).await,
+                "GetLatestTreeState" => unary(req, |r| lock(&chain).latest_tree_state(r)).await,
+                "GetSubtreeRoots" => streaming(req, |r| lock(&chain).subtree_roots(r)).await,
+                "GetAddressUtxos" => unary(req, |r| lock(&chain).address_utxos_list(r)).await,
+                "GetAddressUtxosStream" => {
+                    streaming(req, |r| lock(&chain).address_utxos(r)).await
+                }
+                "GetLightdInfo" => unary(req, |r| lock(&chain).lightd_info(r)).await,
+                _ => {
+                    warn!("Mock lightwalletd does not implement {}", method);
+                    Status::unimplemented(format!("{method} is not implemented by the mock"))
+                        .into_http()
+                }
+            };
+            Ok(response)
+        })
+    }
+}
+
+async fn unary<Req, Resp, B>(
+    req: http::Request<B>,
+    handler: impl FnMut(Req) -> Result<Resp, Status> + Send,
+) -> http::Response<tonic::body::Body>
+where
+    Req: Message + Default + Send + 'static,
+    Resp: Message + Send + 'static,
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    Grpc::new(ProstCodec::default())
+        .unary(UnaryFn(handler), req)
+        .await
+}
+
+async fn streaming<Req, Resp, B>(
+    req: http::Request<B>,
+    handler: impl FnMut(Req) -> Result<Vec<Resp>, Status> + Send,
+) -> http::Response<tonic::body::Body>
+where
+    Req: Message + Default + Send + 'static,
+    Resp: Message + Send + 'static,
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    Grpc::new(ProstCodec::default())
+        .server_streaming(StreamFn(handler), req)
+        .await
+}
+
+/// In-process lightwalletd serving recorded fixtures
+///
+/// The server runs on the current Tokio runtime until the mock is dropped.
+pub struct MockLightwalletd {
+    chain: SharedChain,
+    connections: mpsc::Sender<std::io::Result<DuplexStream>>,
+    server: JoinHandle<()>,
+}
+
+impl MockLightwalletd {
+    /// Start serving the given fixtures
+    pub fn start(fixtures: MockFixtures) -> anyhow::Result<Self> {
+        let chain = Arc::new(Mutex::new(MockChain::from_fixtures(fixtures)?));
+        let (connections, incoming) = mpsc::channel(8);
+
+        let service = MockService {
+            chain: chain.clone(),
+        };
+        let server = tokio::spawn(async move {
+            let result = Server::builder()
+                .add_service(service)
+                .serve_with_incoming(ReceiverStream::new(incoming))
+                .await;
+            if let Err(e) = result {
+                warn!("Mock lightwalletd stopped: {}", e);
+            }
+        });
+
+        Ok(Self {
+            chain,
+            connections,
+            server,
+        })
+    }
+
+    /// Open a client connection to the mock
+    pub async fn connect(&self) -> anyhow::Result<GrpcClient> {
+        let connections = self.connections.clone();
+        let channel = Endpoint::from_static("http://mock.lightwalletd")
+            .connect_with_connector(tower::service_fn(move |_: Uri| {
+                let connections = connections.clone();
+                async move {
+                    let (client, server) = tokio::io::duplex(CONNECTION_BUFFER);
+                    connections
+                        .send(Ok(server))
+                        .await
+                        .map_err(|_| std::io::Error::other("Mock lightwalletd is not running"))?;
+                    Ok::<_, std::io::Error>(TokioIo::new(client))
+                }
+            }))
+            .await?;
+        Ok(CompactTxStreamerClient::new(channel))
+    }
+
+    /// Append a block to the served chain, with its tree state if known
+    pub fn add_block(&self, block: CompactBlock, tree_state: Option<FixtureTreeState>) {
+        let mut chain = lock(&self.chain);
+        if let Some(state) = tree_state {
+            let network = chain.chain_name.clone();
+            chain.tree_states.insert(
+                state.height,
+                TreeState {
+                    network,
+                    height: state.height,
+                    hash: state.hash,
+                    time: state.time,
+                    sapling_tree: state.sapling_tree,
+                    orchard_tree: state.orchard_tree,
+                },
+            );
+        }
+        chain.blocks.insert(block.height, block);
+    }
+
+    /// Drop every block above `height`, simulating a chain reorganization
+    pub fn truncate(&self, height: u64) {
+        let mut chain = lock(&self.chain);
+        chain.blocks.retain(|h, _| *h <= height);
+        chain.tree_states.retain(|h, _| *h <= height);
+    }
+
+    /// Transactions broadcast to the mock so far, in order
+    pub fn broadcasts(&self) -> Vec<Vec<u8>> {
+        lock(&self.chain).broadcasts.clone()
+    }
+
+    /// Reject subsequent broadcasts with the given error code and message,
+    /// or accept them again with `None`
+    pub fn reject_broadcasts(&self, rejection: Option<(i32, String)>) {
+        lock(&self.chain).broadcast_rejection = rejection;
+    }
+}
+
+impl Drop for MockLightwalletd {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bip0039::{Count, English, Mnemonic};
+    use rand_core::OsRng;
+    use zcash_client_backend::proto::compact_formats::{
+        ChainMetadata, CompactOrchardAction, CompactTx,
+    };
+
+    use super::*;
+    use crate::wallet::{
+        ActivationHeights, AddressType, ConfirmationSettings, IkkiError, IkkiWallet, Lightwalletd,
+        Network, ZcashConfig, DEFAULT_LIGHTWALLETD_URL,
+    };
+
+    const BIRTHDAY: u64 = 10;
+    const TIP: u64 = 20;
+
+    /// Hash of the test block at `height`, in wire order
+    fn block_hash(height: u64) -> Vec<u8> {
+        vec![height as u8; 32]
+    }
+
+    /// Empty regtest chain with a birthday tree state and blocks up to `TIP`
+    fn empty_chain() -> MockFixtures {
+        let empty_tree = "000000".to_string();
+        let mut birthday_hash = block_hash(BIRTHDAY - 1);
+        birthday_hash.reverse();
+        MockFixtures {
+            chain_name: "regtest".to_string(),
+            sapling_activation_height: 1,
+            consensus_branch_id: "c8e71055".to_string(),
+            blocks: (BIRTHDAY..=TIP)
+                .map(|height| hex::encode(empty_block(height).encode_to_vec()))
+                .collect(),
+            tree_states: vec![FixtureTreeState {
+                height: BIRTHDAY - 1,
+                hash: hex::encode(birthday_hash),
+                time: 0,
+                sapling_tree: empty_tree.clone(),
+                orchard_tree: empty_tree,
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn empty_block(height: u64) -> CompactBlock {
+        CompactBlock {
+            height,
+            hash: block_hash(height),
+            prev_hash: block_hash(height - 1),
+            time: height as u32 * 75,
+            chain_metadata: Some(ChainMetadata {
+                sapling_commitment_tree_size: 0,
+                orchard_commitment_tree_size: 0,
+            }),
+            ..Default::default()
+        }
+    }
+
+    /// Hash of the block replacing the test block at `height` in a reorg
+    fn replaced_block_hash(height: u64) -> Vec<u8> {
+        vec![height as u8 | 0x80; 32]
+    }
+
+    /// Compact transaction paying `value` to the wallet's Orchard receiver
+    fn payment_to(wallet: &IkkiWallet, txid: [u8; 32], value: u64) -> CompactTx {
+        use orchard::builder::{Builder, BundleType};
+        use orchard::tree::Anchor;
+        use orchard::value::NoteValue;
+        use zcash_keys::address::Address;
+
+        let network = Network::Regtest(ActivationHeights::default());
+        let address = wallet.get_address().unwrap();
+        let Some(Address::Unified(ua)) = Address::decode(&network, &address) else {
+            panic!("wallet address is not unified");
+        };
+        let recipient = *ua
+            .orchard()
+            .expect("wallet address has no Orchard receiver");
+
+        let mut builder = Builder::new(BundleType::DEFAULT, Anchor::empty_tree());
+        builder
+            .add_output(None, recipient, NoteValue::from_raw(value), [0; 512])
+            .unwrap();
+        let (bundle, _) = builder.build::<i64>(&mut OsRng).unwrap().unwrap();
+
+        CompactTx {
+            hash: txid.to_vec(),
+            actions: bundle
+                .actions()
+                .iter()
+                .map(|action| CompactOrchardAction {
+                    nullifier: action.nullifier().to_bytes().to_vec(),
+                    cmx: action.cmx().to_bytes().to_vec(),
+                    ephemeral_key: action.encrypted_note().epk_bytes.to_vec(),
+                    ciphertext: action.encrypted_note().enc_ciphertext[..52].to_vec(),
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn wallet_config(data_dir: &Path) -> ZcashConfig {
+        let seed = Mnemonic::<English>::generate(Count::Words24);
+        ZcashConfig {
+            seed_phrase: Some(seed.phrase().to_string()),
+            viewing_key: None,
+            birthday_height: BIRTHDAY,
+            lightwalletd_url: DEFAULT_LIGHTWALLETD_URL.to_string(),
+            data_dir: data_dir.to_path_buf(),
+            network: Network::Regtest(ActivationHeights::default()),
+            confirmations: ConfirmationSettings::default(),
+            db_key: None,
+            auto_lock_minutes: 0,
+        }
+    }
+
+    async fn open_wallet(mock: &MockLightwalletd, config: ZcashConfig) -> IkkiWallet {
+        let mut wallet = IkkiWallet::with_client(config, mock.connect().await.unwrap()).unwrap();
+        wallet.init_account().await.unwrap();
+        wallet
+    }
+
+    async fn new_wallet(mock: &MockLightwalletd, data_dir: &Path) -> IkkiWallet {
+        open_wallet(mock, wallet_config(data_dir)).await
+    }
+
+    #[tokio::test]
+    async fn wallet_syncs_to_the_mock_tip() {
+        let mock = MockLightwalletd::start(empty_chain()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut wallet = new_wallet(&mock, dir.path()).await;
+
+        assert_eq!(wallet.get_block_height().await.unwrap(), TIP);
+        assert!(wallet.sync().await.unwrap().is_none());
+
+        let breakdown = wallet.get_balance_breakdown().unwrap();
+        assert_eq!(breakdown.chain_tip_height, Some(TIP as u32));
+        assert_eq!(breakdown.fully_scanned_height, Some(TIP as u32));
+        assert_eq!(breakdown.total(), 0);
+
+        mock.add_block(empty_block(TIP + 1), None);
+        wallet.sync().await.unwrap();
+        let breakdown = wallet.get_balance_breakdown().unwrap();
+        assert_eq!(breakdown.fully_scanned_height, Some(TIP as u32 + 1));
+    }
+
+    #[tokio::test]
+    async fn send_without_funds_broadcasts_nothing() {
+        let mock = MockLightwalletd::start(empty_chain()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut wallet = new_wallet(&mock, dir.path()).await;
+        wallet.sync().await.unwrap();
+
+        let address = wallet.get_address().unwrap();
+        let error = wallet
+            .send_to_address(&address, 10_000, None)
+            .await
+            .unwrap_err();
+        // The required amount comes from the proposal and includes its fee
+        assert!(matches!(
+            error.downcast_ref::<IkkiError>(),
+            Some(IkkiError::InsufficientFunds { required, available: 0 }) if *required > 10_000
+        ));
+        assert!(mock.broadcasts().is_empty());
+    }
+
+    #[tokio::test]
+    async fn max_amount_is_accepted_as_a_payment() {
+        let mock = MockLightwalletd::start(empty_chain()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut wallet = new_wallet(&mock, dir.path()).await;
+        wallet.sync().await.unwrap();
+
+        // A payment to the wallet with enough confirmations to be spendable
+        let mined_at = TIP + 1;
+        let payment = payment_to(&wallet, [0xcd; 32], 50_000);
+        let tree_size = payment.actions.len() as u32;
+        for height in mined_at..=mined_at + 10 {
+            let mut block = empty_block(height);
+            if height == mined_at {
+                block.vtx.push(payment.clone());
+            }
+            block.chain_metadata = Some(ChainMetadata {
+                sapling_commitment_tree_size: 0,
+                orchard_commitment_tree_size: tree_size,
+            });
+            mock.add_block(block, None);
+        }
+        wallet.sync().await.unwrap();
+        assert_eq!(
+            wallet.get_balance_breakdown().unwrap().shielded_spendable(),
+            50_000
+        );
+
+        let address = wallet.get_address().unwrap();
+        let max = wallet.propose_max_transfer(&address, None).unwrap();
+        assert_eq!(max.amount + max.fee, 50_000);
+
+        // The max amount must not be rejected by a balance check that guesses the fee
+        let pczt = wallet
+            .create_pczt(&address, Some(max.amount), None)
+            .unwrap();
+        assert_eq!(pczt.amount, max.amount);
+        assert_eq!(pczt.fee, max.fee);
+        assert!(mock.broadcasts().is_empty());
+    }
+
+    #[tokio::test]
+    async fn broadcasts_are_captured_or_rejected() {
+        let mock = MockLightwalletd::start(empty_chain()).unwrap();
+        let mut client = mock.connect().await.unwrap();
+
+        client.broadcast_transaction(vec![1, 2, 3]).await.unwrap();
+        assert_eq!(mock.broadcasts(), vec![vec![1, 2, 3]]);
+
+        mock.reject_broadcasts(Some((-26, "bad-txns".to_string())));
+        let error = client.broadcast_transaction(vec![4]).await.unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<IkkiError>(),
+            Some(IkkiError::BroadcastRejected { code: -26, .. })
+        ));
+        assert_eq!(mock.broadcasts().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn tree_state_is_served_for_the_birthday() {
+        let mock = MockLightwalletd::start(empty_chain()).unwrap();
+        let mut client = mock.connect().await.unwrap();
+
+        let state = client.tree_state(BIRTHDAY - 1).await.unwrap();
+        assert_eq!(state.height, BIRTHDAY - 1);
+        assert!(client.tree_state(TIP + 5).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn issued_addresses_survive_a_rebuild() {
+        let mock = MockLightwalletd::start(empty_chain()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let config = wallet_config(dir.path());
+        let mut wallet = open_wallet(&mock, config.clone()).await;
+
+        let mut issued = Vec::new();
+        for (address_type, label) in [
+            (AddressType::Shielded, "alice"),
+            (AddressType::Orchard, "bob"),
+            (AddressType::Full, "carol"),
+        ] {
+            issued.push(wallet.get_new_address(address_type, Some(label)).unwrap());
+        }
+        let highest = issued.iter().map(|a| a.diversifier_index).max().unwrap();
+
+        // What a rescan below the birthday does
+        wallet.record_address_indices().unwrap();
+        drop(wallet);
+        for file in ["wallet.db", "wallet.db-wal", "wallet.db-shm"] {
+            let _ = std::fs::remove_file(dir.path().join(file));
+        }
+        let mut wallet = open_wallet(&mock, config).await;
+        assert_eq!(wallet.restore_issued_addresses().unwrap(), issued.len());
+
+        let records = wallet.get_all_addresses().unwrap();
+        for (address, label) in issued.iter().zip(["alice", "bob", "carol"]) {
+            let record = records
+                .iter()
+                .find(|record| record.address == address.unified_address)
+                .expect("issued address was not restored");
+            assert_eq!(record.diversifier_index, address.diversifier_index);
+            assert_eq!(record.label.as_deref(), Some(label));
+        }
+
+        let next = wallet.get_new_address(AddressType::Shielded, None).unwrap();
+        assert!(next.diversifier_index > highest);
+    }
+
+    #[tokio::test]
+    async fn replaced_blocks_are_reported_as_a_reorg() {
+        let mock = MockLightwalletd::start(empty_chain()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let mut wallet = new_wallet(&mock, dir.path()).await;
+        wallet.sync().await.unwrap();
+
+        // A payment to the wallet, mined a few blocks below the new tip
+        let mined_at = TIP + 1;
+        let old_tip = TIP + 4;
+        let txid = [0xab; 32];
+        let payment = payment_to(&wallet, txid, 50_000);
+        let tree_size = payment.actions.len() as u32;
+        for height in mined_at..=old_tip {
+            let mut block = empty_block(height);
+            if height == mined_at {
+                block.vtx.push(payment.clone());
+            }
+            block.chain_metadata = Some(ChainMetadata {
+                sapling_commitment_tree_size: 0,
+                orchard_commitment_tree_size: tree_size,
+            });
+            mock.add_block(block, None);
+        }
+        assert!(wallet.sync().await.unwrap().is_none());
+
+        // A longer chain from the payment's height on, without the payment
+        mock.truncate(mined_at - 1);
+        for height in mined_at..=old_tip + 2 {
+            let mut block = empty_block(height);
+            block.hash = replaced_block_hash(height);
+            if height > mined_at {
+                block.prev_hash = replaced_block_hash(height - 1);
+            }
+            mock.add_block(block, None);
+        }
+
+        let reorg = wallet
+            .sync()
+            .await
+            .unwrap()
+            .expect("the reorg was not detected");
+        assert_eq!(reorg.old_height, old_tip);
+        assert_eq!(reorg.new_height, mined_at - 1);
+        assert_eq!(reorg.affected_txids, vec![txid_to_hex(&txid)]);
+
+        let breakdown = wallet.get_balance_breakdown().unwrap();
+        assert_eq!(breakdown.fully_scanned_height, Some(old_tip as u32 + 2));
+    }
+}
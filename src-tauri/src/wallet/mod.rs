@@ -2,8 +2,36 @@
 //!
 //! Provides wallet management, sync, and transaction operations.
 
+mod address_book;
+mod animated_qr;
+mod addresses;
 mod config;
 mod core;
+mod database;
+mod error;
+mod export;
+mod frost;
+mod history;
+mod inspect;
+mod lightwalletd;
+#[cfg(any(test, feature = "test-fixtures"))]
+mod mock_lightwalletd;
+mod notes;
+mod offline;
 
+pub use address_book::*;
+pub use animated_qr::*;
+pub use addresses::*;
 pub use config::*;
 pub use core::*;
+pub use database::*;
+pub use error::*;
+pub use export::*;
+pub use frost::*;
+pub use history::*;
+pub use inspect::*;
+pub use lightwalletd::*;
+#[cfg(any(test, feature = "test-fixtures"))]
+pub use mock_lightwalletd::*;
+pub use notes::*;
+pub use offline::*;
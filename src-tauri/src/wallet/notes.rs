@@ -0,0 +1,139 @@
+//! Unspent shielded notes
+//!
+//! Lists the wallet's received Sapling and Orchard notes that have not been
+//! spent by a mined or still-valid transaction.
+
+use rusqlite::{named_params, Connection};
+use zcash_client_sqlite::AccountUuid;
+
+use crate::wallet::history::{decode_memo, txid_to_hex};
+use crate::wallet::{MemoContent, ValuePool};
+
+/// An unspent note received by the wallet
+#[derive(Debug, Clone)]
+pub struct UnspentNote {
+    /// Transaction ID as hex string
+    pub txid: String,
+    /// Sapling or Orchard
+    pub pool: ValuePool,
+    /// Output index (Sapling) or action index (Orchard)
+    pub output_index: u32,
+    /// Value in zatoshis
+    pub value: u64,
+    /// Height the note was mined at
+    pub mined_height: u32,
+    /// Confirmations at the wallet's chain tip
+    pub confirmations: u32,
+    /// Wallet address that received the note, if known
+    pub address: Option<String>,
+    /// Whether the note is change from the wallet's own transaction
+    pub is_change: bool,
+    /// Decoded memo
+    pub memo: Option<MemoContent>,
+    /// Raw memo bytes
+    pub memo_bytes: Option<Vec<u8>>,
+}
+
+/// Unspent notes of one pool, given its received-notes and spends tables and index column
+fn pool_notes_sql(notes: &str, spends: &str, note_ref: &str, index: &str) -> String {
+    format!(
+        "SELECT t.txid, n.{index}, n.value, t.mined_height, a.address, n.is_change, n.memo
+        FROM {notes} n
+        JOIN transactions t ON t.id_tx = n.transaction_id
+        JOIN accounts acc ON acc.id = n.account_id
+        LEFT JOIN addresses a ON a.id = n.address_id
+        WHERE t.mined_height IS NOT NULL
+        AND acc.uuid = :account
+        AND NOT EXISTS (
+            SELECT 1 FROM {spends} s
+            JOIN transactions st ON st.id_tx = s.transaction_id
+            WHERE s.{note_ref} = n.id
+            AND (st.mined_height IS NOT NULL
+                OR st.expiry_height IS NULL
+                OR st.expiry_height = 0
+                OR st.expiry_height >= :tip)
+        )"
+    )
+}
+
+/// List unspent notes of an account with between `min_confirmations` and
+/// `max_confirmations` confirmations
+pub fn list_unspent_notes(
+    conn: &Connection,
+    account: AccountUuid,
+    min_confirmations: u32,
+    max_confirmations: u32,
+) -> anyhow::Result<Vec<UnspentNote>> {
+    let tip: u32 = conn
+        .query_row("SELECT MAX(height) FROM blocks", [], |row| {
+            row.get::<_, Option<u32>>(0)
+        })?
+        .unwrap_or(0);
+
+    let pools = [
+        (
+            ValuePool::Sapling,
+            pool_notes_sql(
+                "sapling_received_notes",
+                "sapling_received_note_spends",
+                "sapling_received_note_id",
+                "output_index",
+            ),
+        ),
+        (
+            ValuePool::Orchard,
+            pool_notes_sql(
+                "orchard_received_notes",
+                "orchard_received_note_spends",
+                "orchard_received_note_id",
+                "action_index",
+            ),
+        ),
+    ];
+
+    let mut notes = Vec::new();
+    for (pool, sql) in pools {
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map(
+            named_params! {
+                ":tip": tip,
+                ":account": account.expose_uuid().as_bytes(),
+            },
+            |row| {
+                Ok((
+                    row.get::<_, Vec<u8>>(0)?,
+                    row.get::<_, u32>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, u32>(3)?,
+                    row.get::<_, Option<String>>(4)?,
+                    row.get::<_, bool>(5)?,
+                    row.get::<_, Option<Vec<u8>>>(6)?,
+                ))
+            },
+        )?;
+
+        for (txid, output_index, value, mined_height, address, is_change, memo_bytes) in
+            rows.flatten()
+        {
+            let confirmations = tip.saturating_sub(mined_height) + 1;
+            if confirmations < min_confirmations || confirmations > max_confirmations {
+                continue;
+            }
+            notes.push(UnspentNote {
+                txid: txid_to_hex(&txid),
+                pool,
+                output_index,
+                value: value.max(0) as u64,
+                mined_height,
+                confirmations,
+                address,
+                is_change,
+                memo: memo_bytes.as_deref().and_then(decode_memo),
+                memo_bytes,
+            });
+        }
+    }
+
+    notes.sort_by(|a, b| b.mined_height.cmp(&a.mined_height));
+    Ok(notes)
+}
@@ -0,0 +1,265 @@
+//! Offline signing with Partially Created Zcash Transactions (PCZTs)
+//!
+//! A watch-only wallet creates an unproven, unsigned PCZT from a proposal.
+//! The instance holding the seed proves and signs it here without any network
+//! access, and the watch-only wallet then extracts and broadcasts the result.
+
+use bip0039::{English, Mnemonic};
+use pczt::Pczt;
+use pczt::roles::prover::Prover;
+use pczt::roles::signer::{Error as SignerError, Signer};
+use tracing::debug;
+use zcash_address::unified::{self, Encoding};
+use zcash_address::ZcashAddress;
+use zcash_client_backend::keys::UnifiedSpendingKey;
+use zcash_proofs::prover::LocalTxProver;
+use zip32::AccountId;
+use zip32::fingerprint::SeedFingerprint;
+
+use crate::wallet::{Network, ValuePool, WatchOnlyKey};
+
+/// A payment made by a PCZT
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcztOutput {
+    /// Pool the output is created in
+    pub pool: ValuePool,
+    /// Encoded recipient address
+    pub address: String,
+    /// Value in zatoshis
+    pub value: u64,
+    /// Whether the output returns change to this wallet
+    pub is_change: bool,
+}
+
+/// What a PCZT pays, decoded so the signer can confirm it before signing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PcztSummary {
+    /// Outputs with a value; zero-valued padding outputs are left out
+    pub outputs: Vec<PcztOutput>,
+    /// Fee in zatoshis
+    pub fee: u64,
+}
+
+impl PcztSummary {
+    /// Total paid to recipients other than this wallet's change
+    pub fn amount(&self) -> u64 {
+        self.outputs
+            .iter()
+            .filter(|output| !output.is_change)
+            .map(|output| output.value)
+            .sum()
+    }
+}
+
+/// Derive the 64-byte BIP-39 seed from a seed phrase
+pub fn seed_from_phrase(seed_phrase: &str) -> anyhow::Result<[u8; 64]> {
+    let mnemonic = Mnemonic::<English>::from_phrase(seed_phrase)
+        .map_err(|e| anyhow::anyhow!("Invalid seed phrase: {e:?}"))?;
+    Ok(mnemonic.to_seed(""))
+}
+
+/// Spending key of the wallet's account
+pub(crate) fn account_spending_key(
+    seed: &[u8; 64],
+    network: Network,
+) -> anyhow::Result<UnifiedSpendingKey> {
+    UnifiedSpendingKey::from_seed(&network, seed, AccountId::ZERO)
+        .map_err(|e| anyhow::anyhow!("Failed to derive spending key: {e:?}"))
+}
+
+/// Viewing key to set up a watch-only wallet for a seed
+pub fn watch_only_key(seed: &[u8; 64], network: Network) -> anyhow::Result<WatchOnlyKey> {
+    let ufvk = account_spending_key(seed, network)?.to_unified_full_viewing_key();
+    Ok(WatchOnlyKey {
+        ufvk: ufvk.encode(&network),
+        seed_fingerprint: SeedFingerprint::from_seed(seed).map(|fp| hex::encode(fp.to_bytes())),
+    })
+}
+
+/// Parse a hex-encoded seed fingerprint
+pub(crate) fn parse_seed_fingerprint(fingerprint: &str) -> anyhow::Result<SeedFingerprint> {
+    let bytes: [u8; 32] = hex::decode(fingerprint)
+        .ok()
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or_else(|| anyhow::anyhow!("Invalid seed fingerprint: {fingerprint}"))?;
+    Ok(SeedFingerprint::from_bytes(bytes))
+}
+
+/// Decode the outputs and fee of a serialized PCZT
+///
+/// Outputs to the account's internal addresses are marked as change.
+pub fn inspect_pczt(seed: &[u8; 64], network: Network, pczt: &[u8]) -> anyhow::Result<PcztSummary> {
+    let pczt = Pczt::parse(pczt).map_err(|e| anyhow::anyhow!("Invalid PCZT: {e:?}"))?;
+    let usk = account_spending_key(seed, network)?;
+    let orchard_fvk = orchard::keys::FullViewingKey::from(usk.orchard());
+    let sapling_dfvk = usk.sapling().to_diversifiable_full_viewing_key();
+
+    let mut inputs = 0u64;
+    let mut outputs = Vec::new();
+    let mut output_total = 0u64;
+
+    for input in pczt.transparent().inputs() {
+        inputs += *input.value();
+    }
+    for output in pczt.transparent().outputs() {
+        output_total += *output.value();
+        outputs.push(PcztOutput {
+            pool: ValuePool::Transparent,
+            address: match output.user_address() {
+                Some(address) => address.clone(),
+                None => transparent_address(network, output.script_pubkey())?,
+            },
+            value: *output.value(),
+            is_change: false,
+        });
+    }
+
+    for spend in pczt.sapling().spends() {
+        inputs += spend
+            .value()
+            .ok_or_else(|| anyhow::anyhow!("The PCZT hides the value of a Sapling spend"))?;
+    }
+    for output in pczt.sapling().outputs() {
+        let (Some(value), Some(recipient)) = (*output.value(), *output.recipient()) else {
+            anyhow::bail!("The PCZT hides the recipient or value of a Sapling output");
+        };
+        output_total += value;
+        if value == 0 {
+            continue;
+        }
+        let is_change = sapling::PaymentAddress::from_bytes(&recipient)
+            .and_then(|address| sapling_dfvk.decrypt_diversifier(&address))
+            .is_some_and(|(_, scope)| scope == zip32::Scope::Internal);
+        outputs.push(PcztOutput {
+            pool: ValuePool::Sapling,
+            address: ZcashAddress::from_sapling(network.network_type(), recipient).to_string(),
+            value,
+            is_change,
+        });
+    }
+
+    for action in pczt.orchard().actions() {
+        inputs += action
+            .spend()
+            .value()
+            .ok_or_else(|| anyhow::anyhow!("The PCZT hides the value of an Orchard spend"))?;
+
+        let output = action.output();
+        let (Some(value), Some(recipient)) = (*output.value(), *output.recipient()) else {
+            anyhow::bail!("The PCZT hides the recipient or value of an Orchard output");
+        };
+        output_total += value;
+        if value == 0 {
+            continue;
+        }
+        let is_change =
+            Option::<orchard::Address>::from(orchard::Address::from_raw_address_bytes(&recipient))
+                .and_then(|address| orchard_fvk.scope_for_address(&address))
+                .is_some_and(|scope| scope == orchard::keys::Scope::Internal);
+        let receiver =
+            unified::Address::try_from_items(vec![unified::Receiver::Orchard(recipient)])
+                .map_err(|e| anyhow::anyhow!("Invalid Orchard recipient: {e}"))?;
+        outputs.push(PcztOutput {
+            pool: ValuePool::Orchard,
+            address: ZcashAddress::from_unified(network.network_type(), receiver).to_string(),
+            value,
+            is_change,
+        });
+    }
+
+    let fee = inputs
+        .checked_sub(output_total)
+        .ok_or_else(|| anyhow::anyhow!("The PCZT pays out more than it spends"))?;
+    Ok(PcztSummary { outputs, fee })
+}
+
+/// Encode the address a transparent output script pays to
+fn transparent_address(network: Network, script: &[u8]) -> anyhow::Result<String> {
+    let address = match script {
+        // OP_DUP OP_HASH160 <20 bytes> OP_EQUALVERIFY OP_CHECKSIG
+        [0x76, 0xa9, 0x14, hash @ .., 0x88, 0xac] if hash.len() == 20 => {
+            ZcashAddress::from_transparent_p2pkh(network.network_type(), hash.try_into()?)
+        }
+        // OP_HASH160 <20 bytes> OP_EQUAL
+        [0xa9, 0x14, hash @ .., 0x87] if hash.len() == 20 => {
+            ZcashAddress::from_transparent_p2sh(network.network_type(), hash.try_into()?)
+        }
+        _ => anyhow::bail!(
+            "Unsupported transparent output script {}",
+            hex::encode(script)
+        ),
+    };
+    Ok(address.to_string())
+}
+
+/// Create proofs for and sign a serialized PCZT
+///
+/// Only Sapling and Orchard spends are signed; the wallet never proposes
+/// transactions with transparent inputs. Spends that belong to another key,
+/// such as Orchard dummy spends, are left untouched; any other signing
+/// failure aborts. Confirm the payment with `inspect_pczt` first.
+pub fn sign_pczt(seed: &[u8; 64], network: Network, pczt: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let pczt = Pczt::parse(pczt).map_err(|e| anyhow::anyhow!("Invalid PCZT: {e:?}"))?;
+    let usk = account_spending_key(seed, network)?;
+    let pczt = prove_pczt(pczt)?;
+
+    // Sign every spend authorized by the account's keys
+    let orchard_actions = pczt.orchard().actions().len();
+    let sapling_spends = pczt.sapling().spends().len();
+    let mut signer =
+        Signer::new(pczt).map_err(|e| anyhow::anyhow!("Failed to read PCZT: {e:?}"))?;
+
+    let orchard_ask = orchard::keys::SpendAuthorizingKey::from(usk.orchard());
+    let mut signed = 0;
+    for index in 0..orchard_actions {
+        match signer.sign_orchard(index, &orchard_ask) {
+            Ok(()) => signed += 1,
+            Err(e) if signed_by_another_key(&e) => {}
+            Err(e) => anyhow::bail!("Failed to sign Orchard action {index}: {e:?}"),
+        }
+    }
+    for index in 0..sapling_spends {
+        match signer.sign_sapling(index, &usk.sapling().expsk.ask) {
+            Ok(()) => signed += 1,
+            Err(e) if signed_by_another_key(&e) => {}
+            Err(e) => anyhow::bail!("Failed to sign Sapling spend {index}: {e:?}"),
+        }
+    }
+    if signed == 0 {
+        anyhow::bail!("The PCZT does not spend any notes belonging to this wallet");
+    }
+    debug!("Signed {} spends", signed);
+
+    Ok(signer.finish().serialize())
+}
+
+/// Whether signing failed only because the spend belongs to another key
+fn signed_by_another_key(error: &SignerError) -> bool {
+    matches!(
+        error,
+        SignerError::OrchardSign(orchard::pczt::SignerError::WrongSpendAuthorizingKey)
+            | SignerError::SaplingSign(sapling::pczt::SignerError::WrongSpendAuthorizingKey)
+    )
+}
+
+/// Create the Orchard and Sapling proofs a PCZT still needs
+///
+/// Proving needs no spending key, only the witness data in the PCZT.
+pub(crate) fn prove_pczt(pczt: Pczt) -> anyhow::Result<Pczt> {
+    let mut prover = Prover::new(pczt);
+    if prover.requires_orchard_proof() {
+        debug!("Creating Orchard proof");
+        let proving_key = orchard::circuit::ProvingKey::build();
+        prover = prover
+            .create_orchard_proof(&proving_key)
+            .map_err(|e| anyhow::anyhow!("Failed to create Orchard proof: {e:?}"))?;
+    }
+    if prover.requires_sapling_proofs() {
+        debug!("Creating Sapling proofs");
+        let sapling_prover = LocalTxProver::bundled();
+        prover = prover
+            .create_sapling_proofs(&sapling_prover, &sapling_prover)
+            .map_err(|e| anyhow::anyhow!("Failed to create Sapling proofs: {e:?}"))?;
+    }
+    Ok(prover.finish())
+}
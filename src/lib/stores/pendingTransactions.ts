@@ -1,5 +1,5 @@
 import { writable, derived } from "svelte/store";
-import type { PendingTransaction, PendingTxStatus } from "../utils/tauri";
+import type { IkkiErrorPayload, PendingTransaction, PendingTxStatus } from "../utils/tauri";
 
 interface PendingTransactionsState {
   transactions: PendingTransaction[];
@@ -27,7 +27,7 @@ function createPendingTransactionsStore() {
       id: string,
       status: PendingTxStatus,
       txid?: string,
-      error?: string
+      error?: IkkiErrorPayload
     ) =>
       update((state) => ({
         ...state,
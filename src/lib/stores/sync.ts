@@ -369,8 +369,8 @@ function createSyncStore() {
         });
 
         // Listen for sync errors
-        unlistenError = await onSyncError((error: string) => {
-          store.setError(error);
+        unlistenError = await onSyncError((error) => {
+          store.setError(error.message);
         });
       } catch (e) {
         console.error("Failed to setup sync event listeners:", e);
@@ -1,4 +1,5 @@
 import { writable, derived } from "svelte/store";
+import type { BalanceInfo } from "../utils/tauri";
 
 export interface WalletInfo {
   address: string;
@@ -6,12 +7,6 @@ export interface WalletInfo {
   blockHeight: number;
 }
 
-export interface BalanceInfo {
-  total: number;
-  shielded: number;
-  transparent: number;
-}
-
 export interface WalletState {
   initialized: boolean;
   loading: boolean;
@@ -63,4 +58,5 @@ export const isLoading = derived(wallet, ($w) => $w.loading);
 export const isSyncing = derived(wallet, ($w) => $w.syncing);
 export const walletError = derived(wallet, ($w) => $w.error);
 export const balance = derived(wallet, ($w) => $w.balance?.total ?? 0);
+export const spendableBalance = derived(wallet, ($w) => $w.balance?.spendable ?? 0);
 export const address = derived(wallet, ($w) => $w.info?.address ?? "");
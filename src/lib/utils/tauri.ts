@@ -1,11 +1,85 @@
-import { invoke } from "@tauri-apps/api/core";
+import { invoke as tauriInvoke, type InvokeArgs } from "@tauri-apps/api/core";
 import { listen, type UnlistenFn } from "@tauri-apps/api/event";
 
+// Errors returned by commands, matching the Rust IkkiError
+export type IkkiErrorCode =
+  | "network"
+  | "insufficient_funds"
+  | "invalid_address"
+  | "invalid_memo"
+  | "wallet_locked"
+  | "invalid_password"
+  | "watch_only"
+  | "not_initialized"
+  | "not_synced"
+  | "not_found"
+  | "database"
+  | "proposal"
+  | "broadcast_rejected"
+  | "partial_broadcast"
+  | "internal";
+
+export interface IkkiErrorPayload {
+  code: IkkiErrorCode;
+  message: string;
+  details: Record<string, unknown> | null;
+}
+
+export class IkkiError extends Error {
+  code: IkkiErrorCode;
+  details: Record<string, unknown> | null;
+
+  constructor(payload: IkkiErrorPayload) {
+    super(payload.message);
+    this.name = "IkkiError";
+    this.code = payload.code;
+    this.details = payload.details;
+  }
+
+  static from(error: unknown): IkkiError {
+    if (error instanceof IkkiError) return error;
+    if (error && typeof error === "object" && "code" in error && "message" in error) {
+      return new IkkiError(error as IkkiErrorPayload);
+    }
+    return new IkkiError({ code: "internal", message: String(error), details: null });
+  }
+
+  toString(): string {
+    return this.message;
+  }
+}
+
+// Invoke a command, rethrowing failures as IkkiError
+async function invoke<T>(command: string, args?: InvokeArgs): Promise<T> {
+  try {
+    return await tauriInvoke<T>(command, args);
+  } catch (e) {
+    throw IkkiError.from(e);
+  }
+}
+
 // Types matching Rust structs
+export interface PoolBalanceInfo {
+  spendable: number;
+  change_pending_confirmation: number;
+  value_pending_spendability: number;
+  total: number;
+}
+
 export interface BalanceInfo {
+  /** All value across all pools, including funds not yet spendable */
   total: number;
+  /** Value that can be sent right now */
+  spendable: number;
   shielded: number;
   transparent: number;
+  pools: {
+    transparent: PoolBalanceInfo;
+    sapling: PoolBalanceInfo;
+    orchard: PoolBalanceInfo;
+  };
+  fully_scanned_height: number | null;
+  chain_tip_height: number | null;
 }
 
 export interface WalletInfo {
@@ -14,6 +88,36 @@ export interface WalletInfo {
   block_height: number;
 }
 
+export interface ReceiverInfo {
+  orchard: boolean;
+  sapling: boolean;
+  transparent: boolean;
+}
+
+export type AddressType = "shielded" | "full" | "orchard" | "sapling" | "transparent";
+
+export interface AddressInfo {
+  address: string;
+  diversifier_index: string;
+  receivers: ReceiverInfo;
+  created_at: number | null;
+  exposed_at_height: number | null;
+  label: string | null;
+  address_type: AddressType | null;
+  issued_address: string | null;
+  received_total: number;
+}
+
+export interface NewAddressInfo {
+  address: string;
+  address_type: AddressType;
+  unified_address: string;
+  diversifier_index: string;
+  orchard: string | null;
+  sapling: string | null;
+  transparent: string | null;
+}
+
 export interface SyncResult {
   block_height: number;
   balance: BalanceInfo;
@@ -27,44 +131,187 @@ export interface SyncProgress {
   status: string;
 }
 
+export interface ChainReorgInfo {
+  old_height: number;
+  new_height: number;
+  affected_txids: string[];
+}
+
 export interface SyncStatus {
   is_syncing: boolean;
   is_first_sync: boolean;
   current_block: number;
   target_block: number;
   percentage: number;
+  last_reorg: ChainReorgInfo | null;
+}
+
+export interface RescanResult {
+  rescan_height: number;
+  rebuilt: boolean;
 }
 
 export interface SendResult {
   txid: string;
+  /** All transactions of the payment in broadcast order (two for TEX recipients) */
+  txids: string[];
   amount: number;
   fee: number;
 }
 
-export type PendingTxStatus = "building" | "broadcasting" | "broadcast" | "failed";
+/** Viewing key exported from a seed wallet to set up a watch-only wallet */
+export interface WatchOnlyKey {
+  ufvk: string;
+  seed_fingerprint?: string | null;
+}
+
+/** Unsigned PCZT written for an offline signer */
+export interface PcztInfo {
+  path: string;
+  amount: number;
+  fee: number;
+}
+
+/** Output of a PCZT; change returns to this wallet */
+export interface PcztOutput {
+  pool: Pool;
+  address: string;
+  value: number;
+  is_change: boolean;
+}
+
+/** What a PCZT pays, shown for confirmation before signing */
+export interface PcztSummary {
+  outputs: PcztOutput[];
+  /** Total paid to recipients, excluding change */
+  amount: number;
+  fee: number;
+}
+
+/** `partial`: a later transaction of a TEX payment failed after `txids` were broadcast */
+export type PendingTxStatus = "building" | "broadcasting" | "broadcast" | "partial" | "failed";
 
 export interface PendingTransaction {
   id: string;
   txid: string | null;
+  /** All transactions of the payment in broadcast order (two for TEX recipients) */
+  txids: string[];
+  is_tex: boolean;
+  fee: number | null;
   to_address: string;
   amount: number;
   memo: string | null;
   status: PendingTxStatus;
-  error: string | null;
+  /** Why the payment failed or was only partially broadcast */
+  error: IkkiErrorPayload | null;
   created_at: number;
 }
 
+export type TransactionType = "sent" | "received" | "shielding" | "internal" | "mixed";
+
 export interface Transaction {
   txid: string;
-  tx_type: "sent" | "received" | "shielding" | "internal";
+  tx_type: TransactionType;
   amount: number;
   timestamp: number;
   address: string | null;
+  contact_name: string | null;
   memo: string | null;
   status: "pending" | "confirmed" | "failed";
   confirmations: number;
 }
 
+export interface TransactionFilter {
+  direction?: "sent" | "received";
+  pool?: Pool;
+  from_time?: number;
+  to_time?: number;
+  min_amount?: number;
+  max_amount?: number;
+  search?: string;
+  is_pending?: boolean;
+}
+
+export interface TransactionPage {
+  transactions: Transaction[];
+  next_cursor: string | null;
+}
+
+export type Pool = "transparent" | "sapling" | "orchard";
+
+export type MemoType = "empty" | "text" | "arbitrary" | "future";
+
+export interface MemoInfo {
+  memo_type: MemoType;
+  text: string | null;
+  hex: string | null;
+}
+
+export interface TransactionMemo {
+  pool: Pool;
+  output_index: number;
+  is_received: boolean;
+  memo: MemoInfo;
+}
+
+export interface TransactionOutput {
+  pool: Pool;
+  output_index: number;
+  address: string | null;
+  value: number;
+  memo: MemoInfo | null;
+  is_change: boolean;
+  is_received: boolean;
+}
+
+export interface TransactionDetails {
+  txid: string;
+  tx_type: TransactionType;
+  amount: number;
+  fee: number | null;
+  mined_height: number | null;
+  block_hash: string | null;
+  block_time: number | null;
+  expiry_height: number | null;
+  raw_size: number | null;
+  confirmations: number;
+  outputs: TransactionOutput[];
+}
+
+export type ExportFormat = "csv" | "json";
+
+export interface ExportTimeRange {
+  from_time?: number;
+  to_time?: number;
+}
+
+export interface ExportResult {
+  path: string;
+  count: number;
+}
+
+export interface Contact {
+  id: number;
+  name: string;
+  address: string;
+  notes: string | null;
+  tags: string[];
+  created_at: number;
+  updated_at: number;
+}
+
+export interface ContactInput {
+  name: string;
+  address: string;
+  notes?: string | null;
+  tags?: string[];
+}
+
+export interface ImportContactsResult {
+  imported: number;
+  skipped: number;
+}
+
 // Wallet API
 export async function checkWalletExists(): Promise<boolean> {
   return invoke<boolean>("check_wallet_exists");
@@ -76,46 +323,160 @@ export async function generateSeed(): Promise<string> {
 
 export async function initWallet(
   seed: string,
-  birthdayHeight?: number
+  birthdayHeight?: number,
+  password?: string
 ): Promise<WalletInfo> {
   return invoke<WalletInfo>("init_wallet", {
     seed,
     birthdayHeight: birthdayHeight ?? null,
+    password: password ?? null,
   });
 }
 
 export async function loadWallet(
   seed: string,
-  birthdayHeight?: number
+  birthdayHeight?: number,
+  password?: string
 ): Promise<WalletInfo> {
   return invoke<WalletInfo>("load_wallet", {
     seed,
     birthdayHeight: birthdayHeight ?? null,
+    password: password ?? null,
   });
 }
 
+/** Load a watch-only wallet that creates PCZTs for an offline signer */
+export async function loadWatchOnlyWallet(
+  viewingKey: WatchOnlyKey,
+  birthdayHeight?: number,
+  password?: string
+): Promise<WalletInfo> {
+  return invoke<WalletInfo>("load_watch_only_wallet", {
+    viewingKey,
+    birthdayHeight: birthdayHeight ?? null,
+    password: password ?? null,
+  });
+}
+
+export async function exportViewingKey(): Promise<WatchOnlyKey> {
+  return invoke<WatchOnlyKey>("export_viewing_key");
+}
+
 export async function resetWallet(): Promise<void> {
   return invoke<void>("reset_wallet");
 }
 
-export async function autoLoadWallet(): Promise<WalletInfo | null> {
-  return invoke<WalletInfo | null>("auto_load_wallet");
+/** Fails with `wallet_locked` if the wallet has a password and none is given */
+export async function autoLoadWallet(
+  password?: string
+): Promise<WalletInfo | null> {
+  return invoke<WalletInfo | null>("auto_load_wallet", {
+    password: password ?? null,
+  });
+}
+
+/** Encrypt the wallet database with a password, or change the existing one */
+export async function setWalletPassword(
+  newPassword: string,
+  currentPassword?: string
+): Promise<void> {
+  return invoke<void>("set_wallet_password", {
+    currentPassword: currentPassword ?? null,
+    newPassword,
+  });
+}
+
+export interface LockStatus {
+  /** Sends fail with `wallet_locked` until the wallet is unlocked */
+  locked: boolean;
+  /** Whether unlocking needs the wallet password */
+  has_password: boolean;
+  /** Idle minutes after which the wallet locks, 0 if it never does */
+  auto_lock_minutes: number;
+}
+
+export async function getLockStatus(): Promise<LockStatus> {
+  return invoke<LockStatus>("get_lock_status");
+}
+
+/** Zeroize the seed; sync and balances keep working while locked */
+export async function lockWallet(): Promise<LockStatus> {
+  return invoke<LockStatus>("lock_wallet");
+}
+
+export async function unlockWallet(password?: string): Promise<LockStatus> {
+  return invoke<LockStatus>("unlock_wallet", { password: password ?? null });
+}
+
+/** Set the idle minutes after which the wallet locks, 0 to never lock */
+export async function setAutoLockTimeout(minutes: number): Promise<LockStatus> {
+  return invoke<LockStatus>("set_auto_lock_timeout", { minutes });
 }
 
 export async function getBalance(): Promise<BalanceInfo> {
   return invoke<BalanceInfo>("get_balance");
 }
 
+export interface ConfirmationSettings {
+  /** Confirmations required for the wallet's own change */
+  trusted: number;
+  /** Confirmations required for funds received from others */
+  untrusted: number;
+}
+
+export async function getConfirmationSettings(): Promise<ConfirmationSettings> {
+  return invoke<ConfirmationSettings>("get_confirmation_settings");
+}
+
+export async function setConfirmationSettings(
+  trusted: number,
+  untrusted: number
+): Promise<BalanceInfo> {
+  return invoke<BalanceInfo>("set_confirmation_settings", { trusted, untrusted });
+}
+
 export async function getAddress(): Promise<string> {
   return invoke<string>("get_address");
 }
 
-export async function getNewAddress(): Promise<string> {
-  return invoke<string>("get_new_address");
+export async function getNewAddress(
+  addressType?: AddressType,
+  label?: string
+): Promise<NewAddressInfo> {
+  return invoke<NewAddressInfo>("get_new_address", {
+    addressType: addressType ?? null,
+    label: label ?? null,
+  });
+}
+
+export async function getAllAddresses(): Promise<AddressInfo[]> {
+  return invoke<AddressInfo[]>("get_all_addresses");
+}
+
+export async function setAddressLabel(address: string, label: string | null): Promise<void> {
+  return invoke<void>("set_address_label", { address, label });
+}
+
+export type AddressEncoding =
+  | "unified"
+  | "sapling"
+  | "transparent_p2pkh"
+  | "transparent_p2sh"
+  | "tex"
+  | "sprout";
+
+export interface AddressInspection {
+  is_valid: boolean;
+  network: "mainnet" | "testnet" | "regtest" | null;
+  encoding: AddressEncoding | null;
+  receivers: string[];
+  is_mine: boolean;
+  warnings: string[];
+  error: string | null;
 }
 
-export async function getAllAddresses(): Promise<string[]> {
-  return invoke<string[]>("get_all_addresses");
+export async function inspectAddress(address: string): Promise<AddressInspection> {
+  return invoke<AddressInspection>("inspect_address", { address });
 }
 
 export async function syncWallet(): Promise<SyncResult> {
@@ -139,16 +500,100 @@ export async function getTransactions(): Promise<Transaction[]> {
   return invoke<Transaction[]>("get_transactions");
 }
 
+export async function queryTransactions(
+  filter?: TransactionFilter,
+  cursor?: string | null,
+  limit?: number
+): Promise<TransactionPage> {
+  return invoke<TransactionPage>("query_transactions", {
+    filter: filter ?? null,
+    cursor: cursor ?? null,
+    limit: limit ?? null,
+  });
+}
+
+export async function getTransactionDetails(txid: string): Promise<TransactionDetails> {
+  return invoke<TransactionDetails>("get_transaction_details", { txid });
+}
+
+export async function getTransactionMemos(txid: string): Promise<TransactionMemo[]> {
+  return invoke<TransactionMemo[]>("get_transaction_memos", { txid });
+}
+
+export async function exportTransactions(
+  format: ExportFormat,
+  path: string,
+  range?: ExportTimeRange
+): Promise<ExportResult> {
+  return invoke<ExportResult>("export_transactions", {
+    format,
+    path,
+    range: range ?? null,
+  });
+}
+
 // Background Transaction API
+export interface MaxTransfer {
+  amount: number;
+  fee: number;
+}
+
+/** Maximum amount sendable to an address after the ZIP-317 fee, without sending */
+export async function proposeMaxTransfer(toAddress: string, memo?: string): Promise<MaxTransfer> {
+  return invoke<MaxTransfer>("propose_max_transfer", { toAddress, memo: memo || null });
+}
+
+/** Send the entire spendable balance to an address */
+export async function sendMax(toAddress: string, memo?: string): Promise<SendResult> {
+  return invoke<SendResult>("send_max", { toAddress, memo: memo || null });
+}
+
+/** Create an unsigned PCZT file; sends the entire spendable balance when amount is omitted */
+export async function createPczt(
+  toAddress: string,
+  path: string,
+  amount?: number,
+  memo?: string
+): Promise<PcztInfo> {
+  return invoke<PcztInfo>("create_pczt", {
+    toAddress,
+    amount: amount ?? null,
+    memo: memo || null,
+    path,
+  });
+}
+
+/** Recipients, amounts and fee of a PCZT file, to confirm before signing */
+export async function inspectPczt(inputPath: string): Promise<PcztSummary> {
+  return invoke<PcztSummary>("inspect_pczt", { inputPath });
+}
+
+/** Prove and sign a PCZT file with this wallet's seed; fails unless it still matches `confirmed` */
+export async function signPczt(
+  inputPath: string,
+  outputPath: string,
+  confirmed: PcztSummary
+): Promise<void> {
+  return invoke<void>("sign_pczt", { inputPath, outputPath, confirmed });
+}
+
+/** Finalize and broadcast a signed PCZT file */
+export async function broadcastPczt(path: string): Promise<SendResult> {
+  return invoke<SendResult>("broadcast_pczt", { path });
+}
+
+/** Start a send in the background; with `max` the entire spendable balance is sent and `amount` is only an estimate */
 export async function sendTransactionBackground(
   toAddress: string,
   amount: number,
-  memo?: string
+  memo?: string,
+  max = false
 ): Promise<PendingTransaction> {
   return invoke<PendingTransaction>("send_transaction_background", {
     toAddress,
     amount,
     memo: memo || null,
+    max,
   });
 }
 
@@ -164,6 +609,106 @@ export async function dismissPendingTransaction(id: string): Promise<void> {
   return invoke<void>("dismiss_pending_transaction", { id });
 }
 
+// Address Book API
+export async function listContacts(): Promise<Contact[]> {
+  return invoke<Contact[]>("list_contacts");
+}
+
+export async function addContact(contact: ContactInput): Promise<Contact> {
+  return invoke<Contact>("add_contact", { contact });
+}
+
+export async function updateContact(id: number, contact: ContactInput): Promise<Contact> {
+  return invoke<Contact>("update_contact", { id, contact });
+}
+
+export async function deleteContact(id: number): Promise<void> {
+  return invoke<void>("delete_contact", { id });
+}
+
+export async function exportContacts(path: string): Promise<number> {
+  return invoke<number>("export_contacts", { path });
+}
+
+export async function importContacts(path: string): Promise<ImportContactsResult> {
+  return invoke<ImportContactsResult>("import_contacts", { path });
+}
+
+// Local JSON-RPC Server API
+export interface RpcServerInfo {
+  /** "http://127.0.0.1:<port>" or "unix:<path>" */
+  endpoint: string;
+  /** Cookie file holding "__cookie__:<password>" for HTTP Basic auth */
+  cookie_path: string;
+}
+
+export async function startRpcServer(
+  port?: number,
+  socketPath?: string
+): Promise<RpcServerInfo> {
+  return invoke<RpcServerInfo>("start_rpc_server", {
+    port: port ?? null,
+    socketPath: socketPath ?? null,
+  });
+}
+
+export async function stopRpcServer(): Promise<void> {
+  return invoke<void>("stop_rpc_server");
+}
+
+export async function getRpcServerStatus(): Promise<RpcServerInfo | null> {
+  return invoke<RpcServerInfo | null>("get_rpc_server_status");
+}
+
+// Animated QR (UR) API
+export type UrKind = "pczt" | "viewing_key" | "transaction";
+
+/** Frames of an animated QR code, shown in a loop */
+export interface UrFrames {
+  kind: UrKind;
+  frames: string[];
+}
+
+export interface UrScanProgress {
+  kind: UrKind;
+  /** Estimated completion from 0 to 100 */
+  percent: number;
+  complete: boolean;
+}
+
+export interface ScannedUr {
+  kind: UrKind;
+  /** File the PCZT or transaction was written to */
+  path: string | null;
+  viewing_key: WatchOnlyKey | null;
+}
+
+/** Encode a PCZT or transaction file, or the wallet's viewing key, as UR frames */
+export async function encodeUr(
+  kind: UrKind,
+  path?: string,
+  maxFragmentLen?: number
+): Promise<UrFrames> {
+  return invoke<UrFrames>("encode_ur", {
+    kind,
+    path: path ?? null,
+    maxFragmentLen: maxFragmentLen ?? null,
+  });
+}
+
+export async function scanUrFrame(frame: string): Promise<UrScanProgress> {
+  return invoke<UrScanProgress>("scan_ur_frame", { frame });
+}
+
+/** Take the completed scan, writing PCZTs and transactions to path */
+export async function finishUrScan(path?: string): Promise<ScannedUr> {
+  return invoke<ScannedUr>("finish_ur_scan", { path: path ?? null });
+}
+
+export async function resetUrScan(): Promise<void> {
+  return invoke<void>("reset_ur_scan");
+}
+
 // Background Sync API
 export async function startBackgroundSync(isFirstSync: boolean = false): Promise<void> {
   return invoke<void>("start_background_sync", { isFirstSync });
@@ -181,6 +726,10 @@ export async function resetSyncState(): Promise<void> {
   return invoke<void>("reset_sync_state");
 }
 
+export async function rescanFrom(height: number): Promise<RescanResult> {
+  return invoke<RescanResult>("rescan_from", { height });
+}
+
 // Sync event listeners (kept for potential future use, but polling is primary)
 export function onSyncProgress(
   callback: (progress: SyncProgress) => void
@@ -199,9 +748,23 @@ export function onSyncComplete(
 }
 
 export function onSyncError(
-  callback: (error: string) => void
+  callback: (error: IkkiError) => void
 ): Promise<UnlistenFn> {
-  return listen<string>("sync-error", (event) => {
+  return listen<IkkiErrorPayload>("sync-error", (event) => {
+    callback(new IkkiError(event.payload));
+  });
+}
+
+export function onChainReorg(
+  callback: (reorg: ChainReorgInfo) => void
+): Promise<UnlistenFn> {
+  return listen<ChainReorgInfo>("chain-reorg", (event) => {
     callback(event.payload);
   });
 }
+
+export function onWalletLocked(callback: () => void): Promise<UnlistenFn> {
+  return listen("wallet-locked", () => {
+    callback();
+  });
+}